@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use octocrab::models::{pulls::PullRequest, repos::RepoCommit};
+
+use crate::{
+    GitHubApi,
+    soc2::{
+        asset_level::{CustomProperty, RepositoryCustomProperties},
+        ci::{CheckRun, CommitStatus},
+        force_push::Comparison,
+        retroactive_review::{CommitComment, PullRequestReview},
+        rule_suit::{RuleSuite, RuleSuiteQuery, TimePeriod},
+        ruleset::Ruleset,
+    },
+};
+
+/// In-memory [`GitHubApi`] for running the pipeline without network access.
+///
+/// Unknown rule suites, commits, comparisons, rulesets and default branches result in errors,
+/// unknown pull requests, reviews, comments, check runs, commit statuses and custom properties in
+/// empty lists, like the GitHub API does.
+#[derive(Debug, Default)]
+pub struct FakeGitHub {
+    /// Full rule suites by repository full name, in insertion order
+    rule_suites: HashMap<String, Vec<RuleSuite>>,
+    /// Commits by SHA
+    commits: HashMap<String, RepoCommit>,
+    /// Associated pull requests by commit SHA
+    pull_requests: HashMap<String, Vec<PullRequest>>,
+    /// Pull requests by `owner/repo`
+    repository_pull_requests: HashMap<String, Vec<PullRequest>>,
+    /// Pull request reviews by pull request number
+    reviews: HashMap<u64, Vec<PullRequestReview>>,
+    /// Commit comments by commit SHA
+    commit_comments: HashMap<String, Vec<CommitComment>>,
+    /// Comparisons by `base...head`
+    comparisons: HashMap<String, Comparison>,
+    /// Default branch names by `owner/repo`
+    default_branches: HashMap<String, String>,
+    /// Check runs by commit SHA
+    check_runs: HashMap<String, Vec<CheckRun>>,
+    /// Commit statuses by commit SHA
+    commit_statuses: HashMap<String, Vec<CommitStatus>>,
+    /// Rulesets by repository full name
+    rulesets: HashMap<String, Vec<Ruleset>>,
+    /// Custom properties by `owner/repo`
+    custom_properties: HashMap<String, Vec<CustomProperty>>,
+}
+
+impl FakeGitHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a full rule suite (including rule evaluations) to a repository
+    pub fn with_rule_suite(mut self, repository_full_name: &str, suite: RuleSuite) -> Self {
+        self.rule_suites
+            .entry(repository_full_name.to_string())
+            .or_default()
+            .push(suite);
+        self
+    }
+
+    pub fn with_commit(mut self, commit: RepoCommit) -> Self {
+        self.commits.insert(commit.sha.clone(), commit);
+        self
+    }
+
+    pub fn with_associated_pull_requests(mut self, sha: &str, prs: Vec<PullRequest>) -> Self {
+        self.pull_requests.insert(sha.to_string(), prs);
+        self
+    }
+
+    /// Adds a pull request to a repository, regardless of the commits it is associated with
+    pub fn with_pull_request(mut self, owner: &str, repo: &str, pr: PullRequest) -> Self {
+        self.repository_pull_requests
+            .entry(format!("{owner}/{repo}"))
+            .or_default()
+            .push(pr);
+        self
+    }
+
+    pub fn with_pull_request_reviews(
+        mut self,
+        number: u64,
+        reviews: Vec<PullRequestReview>,
+    ) -> Self {
+        self.reviews.insert(number, reviews);
+        self
+    }
+
+    pub fn with_commit_comments(mut self, sha: &str, comments: Vec<CommitComment>) -> Self {
+        self.commit_comments.insert(sha.to_string(), comments);
+        self
+    }
+
+    pub fn with_comparison(mut self, base: &str, head: &str, comparison: Comparison) -> Self {
+        self.comparisons
+            .insert(format!("{base}...{head}"), comparison);
+        self
+    }
+
+    pub fn with_default_branch(mut self, owner: &str, repo: &str, branch: &str) -> Self {
+        self.default_branches
+            .insert(format!("{owner}/{repo}"), branch.to_string());
+        self
+    }
+
+    pub fn with_check_runs(mut self, sha: &str, check_runs: Vec<CheckRun>) -> Self {
+        self.check_runs.insert(sha.to_string(), check_runs);
+        self
+    }
+
+    pub fn with_commit_statuses(mut self, sha: &str, statuses: Vec<CommitStatus>) -> Self {
+        self.commit_statuses.insert(sha.to_string(), statuses);
+        self
+    }
+
+    /// Adds a ruleset that applies to a repository
+    pub fn with_ruleset(mut self, repository_full_name: &str, ruleset: Ruleset) -> Self {
+        self.rulesets
+            .entry(repository_full_name.to_string())
+            .or_default()
+            .push(ruleset);
+        self
+    }
+
+    pub fn with_custom_properties(
+        mut self,
+        owner: &str,
+        repo: &str,
+        props: Vec<CustomProperty>,
+    ) -> Self {
+        self.custom_properties
+            .insert(format!("{owner}/{repo}"), props);
+        self
+    }
+}
+
+fn matches_query(suite: &RuleSuite, query: &RuleSuiteQuery) -> bool {
+    let period = match query.time_period {
+        TimePeriod::Hour => TimeDelta::hours(1),
+        TimePeriod::Day => TimeDelta::days(1),
+        TimePeriod::Week => TimeDelta::weeks(1),
+        TimePeriod::Month => TimeDelta::days(30),
+    };
+
+    suite.pushed_at >= Utc::now() - period
+        && query
+            .ref_name
+            .as_ref()
+            .is_none_or(|ref_name| &suite.ref_name == ref_name)
+        && query
+            .actor_name
+            .as_ref()
+            .is_none_or(|actor| suite.actor_name.as_ref() == Some(actor))
+        && query
+            .rule_suite_result
+            .is_none_or(|result| suite.result == result)
+}
+
+/// Returns the requested page of the matching rule suites, newest first.
+fn list_page<'a>(
+    suites: impl Iterator<Item = &'a RuleSuite>,
+    query: &RuleSuiteQuery,
+) -> Vec<RuleSuite> {
+    let mut suites: Vec<&RuleSuite> = suites.filter(|suite| matches_query(suite, query)).collect();
+    suites.sort_by_key(|suite| std::cmp::Reverse((suite.pushed_at, suite.id)));
+
+    let per_page = usize::from(query.per_page);
+    let skip = (query.page.max(1) as usize - 1) * per_page;
+
+    suites
+        .into_iter()
+        .skip(skip)
+        .take(per_page)
+        .map(|suite| RuleSuite {
+            // Listed rule suites do not contain the rule evaluations.
+            rule_evaluations: None,
+            ..suite.clone()
+        })
+        .collect()
+}
+
+#[async_trait]
+impl GitHubApi for FakeGitHub {
+    async fn list_rule_suites(
+        &self,
+        repository_full_name: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>> {
+        Ok(list_page(
+            self.rule_suites
+                .get(repository_full_name)
+                .into_iter()
+                .flatten(),
+            query,
+        ))
+    }
+
+    async fn list_org_rule_suites(
+        &self,
+        org: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>> {
+        let prefix = format!("{org}/");
+        Ok(list_page(
+            self.rule_suites
+                .iter()
+                .filter(|(repository_full_name, _)| repository_full_name.starts_with(&prefix))
+                .flat_map(|(_, suites)| suites),
+            query,
+        ))
+    }
+
+    async fn get_rule_suite(
+        &self,
+        repository_full_name: &str,
+        rule_suite_id: i64,
+    ) -> Result<RuleSuite> {
+        self.rule_suites
+            .get(repository_full_name)
+            .into_iter()
+            .flatten()
+            .find(|suite| suite.id == rule_suite_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("rule suite {rule_suite_id} not found"))
+    }
+
+    async fn get_commit(&self, _owner: &str, _repo: &str, sha: &str) -> Result<RepoCommit> {
+        self.commits
+            .get(sha)
+            .cloned()
+            .ok_or_else(|| anyhow!("commit {sha} not found"))
+    }
+
+    async fn list_associated_pull_requests(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        sha: &str,
+    ) -> Result<Vec<PullRequest>> {
+        Ok(self.pull_requests.get(sha).cloned().unwrap_or_default())
+    }
+
+    async fn list_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        created_since: DateTime<Utc>,
+    ) -> Result<Vec<PullRequest>> {
+        Ok(self
+            .repository_pull_requests
+            .get(&format!("{owner}/{repo}"))
+            .into_iter()
+            .flatten()
+            .filter(|pr| pr.created_at.is_none_or(|at| at >= created_since))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_pull_request_reviews(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestReview>> {
+        Ok(self.reviews.get(&number).cloned().unwrap_or_default())
+    }
+
+    async fn list_commit_comments(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitComment>> {
+        Ok(self.commit_comments.get(sha).cloned().unwrap_or_default())
+    }
+
+    async fn compare_commits(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<Comparison> {
+        self.comparisons
+            .get(&format!("{base}...{head}"))
+            .cloned()
+            .ok_or_else(|| anyhow!("no common ancestor of {base} and {head} found"))
+    }
+
+    async fn get_default_branch(&self, owner: &str, repo: &str) -> Result<String> {
+        self.default_branches
+            .get(&format!("{owner}/{repo}"))
+            .cloned()
+            .ok_or_else(|| anyhow!("repository {owner}/{repo} not found"))
+    }
+
+    async fn list_check_runs(&self, _owner: &str, _repo: &str, sha: &str) -> Result<Vec<CheckRun>> {
+        Ok(self.check_runs.get(sha).cloned().unwrap_or_default())
+    }
+
+    async fn list_commit_statuses(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitStatus>> {
+        Ok(self.commit_statuses.get(sha).cloned().unwrap_or_default())
+    }
+
+    async fn get_ruleset(&self, repository_full_name: &str, ruleset_id: i64) -> Result<Ruleset> {
+        self.rulesets
+            .get(repository_full_name)
+            .into_iter()
+            .flatten()
+            .find(|ruleset| ruleset.id == ruleset_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("ruleset {ruleset_id} not found"))
+    }
+
+    async fn list_custom_properties(&self, owner: &str, repo: &str) -> Result<Vec<CustomProperty>> {
+        Ok(self
+            .custom_properties
+            .get(&format!("{owner}/{repo}"))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn list_org_custom_properties(
+        &self,
+        org: &str,
+    ) -> Result<Vec<RepositoryCustomProperties>> {
+        let prefix = format!("{org}/");
+        Ok(self
+            .custom_properties
+            .iter()
+            .filter_map(|(repository_full_name, props)| {
+                let repository_name = repository_full_name.strip_prefix(&prefix)?;
+                Some(RepositoryCustomProperties {
+                    repository_id: 0,
+                    repository_name: repository_name.to_string(),
+                    repository_full_name: repository_full_name.clone(),
+                    properties: props.clone(),
+                })
+            })
+            .collect())
+    }
+}
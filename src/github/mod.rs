@@ -0,0 +1,266 @@
+pub mod fake;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use octocrab::{
+    Octocrab, Page,
+    commits::PullRequestTarget,
+    models::{pulls::PullRequest, repos::RepoCommit},
+};
+
+use crate::{
+    GitHubApi,
+    soc2::{
+        asset_level::{CustomProperty, CustomPropertyExt, RepositoryCustomProperties},
+        ci::{CheckRun, CheckRunList, CombinedStatus, CommitStatus},
+        force_push::Comparison,
+        retroactive_review::{CommitComment, PullRequestReview},
+        rule_suit::{RuleSuite, RuleSuiteQuery},
+        ruleset::Ruleset,
+    },
+};
+
+pub use fake::FakeGitHub;
+
+#[async_trait]
+impl GitHubApi for Octocrab {
+    async fn list_rule_suites(
+        &self,
+        repository_full_name: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>> {
+        // https://docs.github.com/en/rest/repos/rule-suites?apiVersion=2022-11-28#list-repository-rule-suites
+        self.get(
+            format!("/repos/{repository_full_name}/rulesets/rule-suites"),
+            Some(query),
+        )
+        .await
+        .with_context(|| format!("unable to fetch rule suites page {}", query.page))
+    }
+
+    async fn list_org_rule_suites(
+        &self,
+        org: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>> {
+        // https://docs.github.com/en/rest/orgs/rule-suites?apiVersion=2022-11-28#list-organization-rule-suites
+        self.get(format!("/orgs/{org}/rulesets/rule-suites"), Some(query))
+            .await
+            .with_context(|| {
+                format!(
+                    "unable to fetch organization rule suites page {}",
+                    query.page
+                )
+            })
+    }
+
+    async fn get_rule_suite(
+        &self,
+        repository_full_name: &str,
+        rule_suite_id: i64,
+    ) -> Result<RuleSuite> {
+        // https://docs.github.com/en/rest/repos/rule-suites?apiVersion=2022-11-28#get-a-repository-rule-suite
+        self.get(
+            format!("/repos/{repository_full_name}/rulesets/rule-suites/{rule_suite_id}"),
+            None::<&()>,
+        )
+        .await
+        .context("unable to fetch full rule suite data")
+    }
+
+    async fn get_commit(&self, owner: &str, repo: &str, sha: &str) -> Result<RepoCommit> {
+        Ok(self.commits(owner, repo).get(sha).await?)
+    }
+
+    async fn list_associated_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<PullRequest>> {
+        let first_page: Page<PullRequest> = self
+            .commits(owner, repo)
+            .associated_pull_requests(PullRequestTarget::Sha(sha.to_string()))
+            .per_page(100)
+            .send()
+            .await
+            .context("unable to fetch associated pull requests")?;
+        self.all_pages(first_page)
+            .await
+            .context("unable to fetch associated pull requests")
+    }
+
+    async fn list_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        created_since: DateTime<Utc>,
+    ) -> Result<Vec<PullRequest>> {
+        // https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-pull-requests
+        let mut page: Page<PullRequest> = self
+            .get(
+                format!("/repos/{owner}/{repo}/pulls"),
+                Some(&[
+                    ("state", "all"),
+                    ("sort", "created"),
+                    ("direction", "desc"),
+                    ("per_page", "100"),
+                ]),
+            )
+            .await
+            .context("unable to fetch pull requests")?;
+
+        // Pull requests are sorted newest first, so the remaining pages are older.
+        let mut pull_requests = Vec::new();
+        loop {
+            let is_older = |pr: &PullRequest| pr.created_at.is_some_and(|at| at < created_since);
+            let reached_older = page.items.iter().any(is_older);
+            pull_requests.extend(page.items.into_iter().filter(|pr| !is_older(pr)));
+            if reached_older {
+                break;
+            }
+
+            match self
+                .get_page(&page.next)
+                .await
+                .context("unable to fetch pull requests")?
+            {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+        Ok(pull_requests)
+    }
+
+    async fn list_pull_request_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestReview>> {
+        // https://docs.github.com/en/rest/pulls/reviews?apiVersion=2022-11-28#list-reviews-for-a-pull-request
+        let first_page: Page<PullRequestReview> = self
+            .get(
+                format!("/repos/{owner}/{repo}/pulls/{number}/reviews"),
+                Some(&[("per_page", 100)]),
+            )
+            .await
+            .with_context(|| format!("unable to fetch reviews of pull request {number}"))?;
+        self.all_pages(first_page)
+            .await
+            .with_context(|| format!("unable to fetch reviews of pull request {number}"))
+    }
+
+    async fn list_commit_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitComment>> {
+        // https://docs.github.com/en/rest/commits/comments?apiVersion=2022-11-28#list-commit-comments
+        let first_page: Page<CommitComment> = self
+            .get(
+                format!("/repos/{owner}/{repo}/commits/{sha}/comments"),
+                Some(&[("per_page", 100)]),
+            )
+            .await
+            .context("unable to fetch commit comments")?;
+        self.all_pages(first_page)
+            .await
+            .context("unable to fetch commit comments")
+    }
+
+    async fn compare_commits(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<Comparison> {
+        // https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits
+        self.get(
+            format!("/repos/{owner}/{repo}/compare/{base}...{head}"),
+            Some(&[("per_page", 100)]),
+        )
+        .await
+        .with_context(|| format!("unable to compare {base} with {head}"))
+    }
+
+    async fn get_default_branch(&self, owner: &str, repo: &str) -> Result<String> {
+        self.repos(owner, repo)
+            .get()
+            .await
+            .context("unable to fetch repository")?
+            .default_branch
+            .context("repository has no default branch")
+    }
+
+    async fn list_check_runs(&self, owner: &str, repo: &str, sha: &str) -> Result<Vec<CheckRun>> {
+        // https://docs.github.com/en/rest/checks/runs?apiVersion=2022-11-28#list-check-runs-for-a-git-reference
+        let mut check_runs = Vec::new();
+        for page in 1u32.. {
+            let list: CheckRunList = self
+                .get(
+                    format!("/repos/{owner}/{repo}/commits/{sha}/check-runs"),
+                    Some(&[("per_page", 100), ("page", page)]),
+                )
+                .await
+                .context("unable to fetch check runs")?;
+            let is_last_page = list.check_runs.is_empty();
+            check_runs.extend(list.check_runs);
+
+            if is_last_page || check_runs.len() as u64 >= list.total_count {
+                break;
+            }
+        }
+        Ok(check_runs)
+    }
+
+    async fn list_commit_statuses(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitStatus>> {
+        // https://docs.github.com/en/rest/commits/statuses?apiVersion=2022-11-28#get-the-combined-status-for-a-specific-reference
+        let mut statuses = Vec::new();
+        for page in 1u32.. {
+            let combined: CombinedStatus = self
+                .get(
+                    format!("/repos/{owner}/{repo}/commits/{sha}/status"),
+                    Some(&[("per_page", 100), ("page", page)]),
+                )
+                .await
+                .context("unable to fetch commit statuses")?;
+            let is_last_page = combined.statuses.is_empty();
+            statuses.extend(combined.statuses);
+
+            if is_last_page || statuses.len() as u64 >= combined.total_count {
+                break;
+            }
+        }
+        Ok(statuses)
+    }
+
+    async fn get_ruleset(&self, repository_full_name: &str, ruleset_id: i64) -> Result<Ruleset> {
+        // https://docs.github.com/en/rest/repos/rules?apiVersion=2022-11-28#get-a-repository-ruleset
+        self.get(
+            format!("/repos/{repository_full_name}/rulesets/{ruleset_id}"),
+            None::<&()>,
+        )
+        .await
+        .with_context(|| format!("unable to fetch ruleset {ruleset_id}"))
+    }
+
+    async fn list_custom_properties(&self, owner: &str, repo: &str) -> Result<Vec<CustomProperty>> {
+        Ok(CustomPropertyExt::list_custom_properties(self, owner, repo).await?)
+    }
+
+    async fn list_org_custom_properties(
+        &self,
+        org: &str,
+    ) -> Result<Vec<RepositoryCustomProperties>> {
+        Ok(CustomPropertyExt::list_org_custom_properties(self, org).await?)
+    }
+}
@@ -0,0 +1,61 @@
+//! Minimal glob matching for patterns in the configuration, e.g. `refs/heads/release/*` or
+//! `*[bot]`. `*` matches any sequence of characters (including `/`), `?` matches a single
+//! character. There are no character classes, `[` and `]` match literally.
+
+/// Whether `text` matches `pattern` as a whole.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` consume one more character.
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn glob_matches() {
+        assert!(matches("refs/heads/main", "refs/heads/main"));
+        assert!(!matches("refs/heads/main", "refs/heads/main2"));
+        assert!(matches("refs/heads/*", "refs/heads/release/1.0"));
+        assert!(matches("*[bot]", "dependabot[bot]"));
+        assert!(!matches("*[bot]", "dependabot"));
+        assert!(matches("*policy-exception*", "soc2:policy-exception"));
+        assert!(matches("v?.*", "v1.2"));
+        assert!(!matches("v?.*", "v12"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+    }
+}
@@ -1,14 +1,38 @@
+pub mod github;
+mod glob;
+pub mod notify;
 mod null_date_format;
 pub mod soc2;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
-use crate::soc2::asset_level::AssetLevel;
-use anyhow::Result;
+use crate::notify::Notifier;
+use crate::soc2::asset_level::{
+    AssetLevelWarning, AssetTaxonomy, AssetTier, CustomProperty, RepositoryCustomProperties,
+};
+use crate::soc2::ci::{CheckRun, CommitStatus};
+use crate::soc2::escalation::EscalationContacts;
+use crate::soc2::force_push::Comparison;
+use crate::soc2::policy::Policy;
+use crate::soc2::ref_filter::RefFilter;
+use crate::soc2::reminder::ReminderConfig;
+use crate::soc2::retroactive_review::{CommitComment, PullRequestReview, RetroactiveReviewConfig};
+use crate::soc2::rule_suit::{RuleOutcome, RuleSuite, RuleSuiteQuery, TimePeriod};
+use crate::soc2::ruleset::Ruleset;
+use crate::soc2::trusted_actor::TrustedActor;
+use crate::soc2::waiver::{NewWaiver, Waiver};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use octocrab::models::{pulls::PullRequest, repos::RepoCommit};
 use serde::{Deserialize, Serialize};
-use slack_morphism::{SlackChannelId, SlackMessageContent, SlackUser, SlackUserId};
+use slack_morphism::{
+    SlackChannelId, SlackMessageContent, SlackTriggerId, SlackTs, SlackUser, SlackUserId,
+    blocks::SlackView,
+};
 use std::fmt::Debug;
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct BotConfig {
@@ -18,15 +42,100 @@ pub struct BotConfig {
     pub review_requirement_ruleset_id: Option<i64>,
     pub block_force_push_ruleset_id: Option<i64>,
     pub codeowners_ruleset_id: Option<i64>,
+    /// The custom property and ordered levels used to classify repositories
+    pub asset_taxonomy: AssetTaxonomy,
     /// The in-scope asset level repos
-    pub in_scope_asset_level: RangeInclusive<AssetLevel>,
+    pub in_scope_asset_level: RangeInclusive<AssetTier>,
     /// The range of asset levels that can trigger callouts (there are still exceptions)
-    pub callout_asset_level: RangeInclusive<AssetLevel>,
+    pub callout_asset_level: RangeInclusive<AssetTier>,
     /// The asset levels that are considered critical
-    pub critical_asset_levels: RangeInclusive<AssetLevel>,
+    pub critical_asset_levels: RangeInclusive<AssetTier>,
+    /// Decides how bypasses are notified. Defaults to `soc2/default_policy.toml`.
+    pub policy: Policy,
+    /// Accounts trusted to bypass rules. Bypasses fully covered by the scope of the pushing
+    /// actor are not recorded. Usually contains [`TrustedActor::dependabot`], must not be empty.
+    pub trusted_actors: Vec<TrustedActor>,
+    /// Security reviewers receiving a copy of every notification, and the recipient if the actor
+    /// cannot be mapped to a Slack user
+    pub escalation_contacts: EscalationContacts,
+    /// Refs whose bypasses are recorded, e.g. only the default branch and release branches.
+    /// Bypasses on other refs are skipped during ingestion, and the sync cursor still advances
+    /// past them. Widening the filter later does not pick up bypasses skipped before.
+    pub ref_filter: RefFilter,
+    /// Retroactive review of review requirement bypasses, see [`soc2::retroactive_review`].
+    /// `None` disables it.
+    pub retroactive_review: Option<RetroactiveReviewConfig>,
+    /// Reminders and escalation for critical bypasses without a justification, see
+    /// [`soc2::reminder`]. `None` disables them.
+    pub reminders: Option<ReminderConfig>,
+    /// Further channels receiving every violation notified to the SOC2 channel, see [`notify`]
+    pub notifiers: Vec<Arc<dyn Notifier>>,
+    /// Filters applied when listing rule suites from GitHub
+    pub rule_suite_filter: RuleSuiteFilter,
     pub github_auth: GitHubAuth,
 }
 
+impl BotConfig {
+    /// Checks that all asset tier ranges, including those of the escalation overrides, are
+    /// built from [`BotConfig::asset_taxonomy`], see [`AssetTaxonomy::check_range`], and that
+    /// [`BotConfig::trusted_actors`] is not empty.
+    pub fn validate(&self) -> Result<()> {
+        if self.trusted_actors.is_empty() {
+            anyhow::bail!(
+                "`trusted_actors` is empty. Bypasses by Dependabot and other `[bot]` accounts are \
+                 no longer skipped implicitly, add `TrustedActor::dependabot()` to keep skipping \
+                 Dependabot's. To trust no account, list `TrustedActor::default()`, which matches \
+                 nobody."
+            );
+        }
+
+        let ranges = [
+            ("in_scope_asset_level", &self.in_scope_asset_level),
+            ("callout_asset_level", &self.callout_asset_level),
+            ("critical_asset_levels", &self.critical_asset_levels),
+        ]
+        .into_iter()
+        .chain(
+            self.escalation_contacts
+                .overrides
+                .iter()
+                .map(|o| ("escalation_contacts.overrides", &o.asset_levels)),
+        );
+
+        for (field, range) in ranges {
+            self.asset_taxonomy
+                .check_range(range)
+                .with_context(|| format!("invalid `{field}`"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Filters for listing rule suites. All pages matching the filter are fetched.
+#[derive(Debug, Clone)]
+pub struct RuleSuiteFilter {
+    /// How far back to list rule suites. Should cover at least the interval between two syncs.
+    pub time_period: TimePeriod,
+    /// Only list rule suites for this ref, e.g. `refs/heads/main`
+    pub ref_name: Option<String>,
+    /// Only list rule suites pushed by this GitHub user
+    pub actor_name: Option<String>,
+    /// Only list rule suites with this outcome. `None` lists all outcomes.
+    pub rule_suite_result: Option<RuleOutcome>,
+}
+
+impl Default for RuleSuiteFilter {
+    fn default() -> Self {
+        Self {
+            time_period: TimePeriod::default(),
+            ref_name: None,
+            actor_name: None,
+            // Only bypasses are evaluated.
+            rule_suite_result: Some(RuleOutcome::Bypass),
+        }
+    }
+}
+
 /// GitHub App authentication credentials
 #[derive(Clone)]
 pub struct GitHubAppCredentials {
@@ -65,19 +174,159 @@ pub trait SlackClient: Send + Sync {
     /// Get a Slack user by their email address
     async fn get_user_by_email(&self, email: &str) -> Result<SlackUser>;
 
+    /// Get the members of a Slack user group
+    async fn get_user_group_members(&self, user_group_id: &str) -> Result<Vec<SlackUserId>>;
+
     /// Post a message to a Slack channel
     async fn post_message_channel(
         &self,
         channel_id: SlackChannelId,
         content: SlackMessageContent,
-    ) -> Result<()>;
+    ) -> Result<PostedMessage>;
 
     /// Post a message to a Slack user
     async fn post_message_user(
         &self,
         user_id: SlackUserId,
         content: SlackMessageContent,
+    ) -> Result<PostedMessage>;
+
+    /// Replace the content of a posted message
+    async fn update_message(
+        &self,
+        message: &PostedMessage,
+        content: SlackMessageContent,
     ) -> Result<()>;
+
+    /// Post a message to a Slack channel that only `user` sees
+    async fn post_ephemeral(
+        &self,
+        channel_id: &SlackChannelId,
+        user_id: &SlackUserId,
+        content: SlackMessageContent,
+    ) -> Result<()>;
+
+    /// Open a modal in response to an interaction, identified by its trigger id
+    async fn open_view(&self, trigger_id: &SlackTriggerId, view: SlackView) -> Result<()>;
+
+    /// Get the replies in the thread of a posted message, oldest first. May include the message
+    /// itself.
+    async fn get_thread_replies(&self, message: &PostedMessage) -> Result<Vec<SlackThreadReply>>;
+}
+
+/// A message posted to Slack, identifying its thread
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostedMessage {
+    /// The channel the message was posted to. For messages to users, the direct message channel.
+    pub channel: SlackChannelId,
+    pub ts: SlackTs,
+}
+
+/// A message in the thread of a [`PostedMessage`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlackThreadReply {
+    /// The author, `None` for bot messages
+    pub user: Option<SlackUserId>,
+    pub text: String,
+    pub ts: SlackTs,
+}
+
+/// GitHub API abstraction. Implemented for [`octocrab::Octocrab`] and for the in-memory
+/// [`github::FakeGitHub`].
+#[async_trait]
+pub trait GitHubApi: Send + Sync {
+    /// List a single page of rule suites of a repository, newest first
+    async fn list_rule_suites(
+        &self,
+        repository_full_name: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>>;
+
+    /// List a single page of rule suites of all repositories in an organization, newest first
+    async fn list_org_rule_suites(
+        &self,
+        org: &str,
+        query: &RuleSuiteQuery,
+    ) -> Result<Vec<RuleSuite>>;
+
+    /// Get a rule suite including its rule evaluations
+    async fn get_rule_suite(
+        &self,
+        repository_full_name: &str,
+        rule_suite_id: i64,
+    ) -> Result<RuleSuite>;
+
+    /// Get a commit by SHA
+    async fn get_commit(&self, owner: &str, repo: &str, sha: &str) -> Result<RepoCommit>;
+
+    /// List the pull requests associated with a commit
+    async fn list_associated_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<PullRequest>>;
+
+    /// List the pull requests of a repository created at or after `created_since`, open and
+    /// closed, newest first
+    async fn list_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        created_since: DateTime<Utc>,
+    ) -> Result<Vec<PullRequest>>;
+
+    /// List all reviews of a pull request
+    async fn list_pull_request_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<PullRequestReview>>;
+
+    /// List all comments of a commit
+    async fn list_commit_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitComment>>;
+
+    /// Compare two commits. The first 100 commits of `head` that are not reachable from `base`
+    /// are listed.
+    async fn compare_commits(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<Comparison>;
+
+    /// Get the name of the default branch of a repository, e.g. `main`
+    async fn get_default_branch(&self, owner: &str, repo: &str) -> Result<String>;
+
+    /// List all check runs of a commit
+    async fn list_check_runs(&self, owner: &str, repo: &str, sha: &str) -> Result<Vec<CheckRun>>;
+
+    /// List the latest status of each context of a commit
+    async fn list_commit_statuses(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<CommitStatus>>;
+
+    /// Get a ruleset that applies to a repository, including organization rulesets
+    async fn get_ruleset(&self, repository_full_name: &str, ruleset_id: i64) -> Result<Ruleset>;
+
+    /// List the custom property values of a repository
+    async fn list_custom_properties(&self, owner: &str, repo: &str) -> Result<Vec<CustomProperty>>;
+
+    /// List the custom property values of all repositories in an organization
+    async fn list_org_custom_properties(
+        &self,
+        org: &str,
+    ) -> Result<Vec<RepositoryCustomProperties>>;
 }
 
 /// Database operations trait that the library consumer must implement
@@ -101,8 +350,111 @@ pub trait RulesetBot: Send + Sync {
     /// Mark a rule suite event as notified
     async fn mark_rule_suite_notified(&self, id: i32) -> Result<()>;
 
+    /// Store the policy decision for a rule suite event, see [`GithubRuleSuiteEvent::decision`]
+    async fn set_rule_suite_decision(&self, id: i32, decision: String) -> Result<()>;
+
+    /// Store the Slack messages of a rule suite event, see [`GithubRuleSuiteEvent::slack_messages`]
+    async fn set_rule_suite_messages(&self, id: i32, messages: String) -> Result<()>;
+
+    /// Find all notified rule suite events with Slack messages that are not justified yet.
+    /// `repository_full_name` limits the events to a repository, otherwise events across the
+    /// organization are returned.
+    async fn find_unjustified_rule_suites(
+        &self,
+        repository_full_name: Option<&str>,
+    ) -> Result<Vec<GithubRuleSuiteEvent>>;
+
+    /// Store the justification of a rule suite event and mark it justified, see
+    /// [`GithubRuleSuiteEvent::justification`]
+    async fn set_rule_suite_justification(&self, id: i32, justification: String) -> Result<()>;
+
+    /// Append a JSON serialized [`soc2::action::ViolationAction`] to the actions of a rule suite
+    /// event and return the updated JSON serialized array, see [`GithubRuleSuiteEvent::actions`].
+    /// Users may act on the same notification concurrently, so the append must be a single atomic
+    /// update, e.g. in PostgreSQL
+    /// `SET actions = COALESCE(actions, '[]') || jsonb_build_array($2::jsonb) RETURNING actions`.
+    async fn append_rule_suite_action(&self, id: i32, action: String) -> Result<String>;
+
+    /// Store the reminder status of an unjustified rule suite event, see
+    /// [`GithubRuleSuiteEvent::reminder`]
+    async fn set_rule_suite_reminder(&self, id: i32, reminder: String) -> Result<()>;
+
+    /// Store the retroactive review status of a rule suite event, see
+    /// [`GithubRuleSuiteEvent::review`]
+    async fn set_rule_suite_review(&self, id: i32, review: String) -> Result<()>;
+
+    /// Find all rule suite events awaiting a retroactive review, i.e. whose review status is
+    /// `pending`
+    async fn find_pending_reviews(&self) -> Result<Vec<GithubRuleSuiteEvent>>;
+
     /// Get a user by GitHub username
     async fn get_email_by_github_username(&self, github_username: &str) -> Result<Option<String>>;
+
+    /// Get the sync cursor of a repository. `None` if the repository has never been synced.
+    async fn get_sync_cursor(&self, repository_full_name: &str) -> Result<Option<SyncCursor>>;
+
+    /// Store the sync cursor of a repository
+    async fn set_sync_cursor(&self, repository_full_name: &str, cursor: SyncCursor) -> Result<()>;
+
+    /// Get the last warning about the misconfigured asset level of a repository. `None` if the
+    /// repository has never been warned about.
+    async fn get_asset_level_warning(
+        &self,
+        repository_full_name: &str,
+    ) -> Result<Option<AssetLevelWarning>>;
+
+    /// Store the last warning about the misconfigured asset level of a repository
+    async fn set_asset_level_warning(
+        &self,
+        repository_full_name: &str,
+        warning: AssetLevelWarning,
+    ) -> Result<()>;
+
+    /// Create a new policy exception waiver
+    async fn create_waiver(&self, waiver: NewWaiver) -> Result<()>;
+
+    /// Find all waivers of a repository, including expired ones
+    async fn find_waivers(&self, repository_full_name: &str) -> Result<Vec<Waiver>>;
+}
+
+/// How far behind the [`SyncCursor`] rule suites are listed again. GitHub may list a rule suite
+/// only after newer ones, such late suites are picked up as long as they were pushed within this
+/// window. Suites listed again are skipped by their GitHub id.
+pub const SYNC_SAFETY_WINDOW: TimeDelta = TimeDelta::hours(1);
+
+/// High-water mark of the rule suites ingested for a repository. All rule suites up to and
+/// including this one have been processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCursor {
+    /// When the newest ingested rule suite was pushed
+    pub pushed_at: DateTime<Utc>,
+    /// The GitHub id of the newest ingested rule suite
+    pub rule_suite_id: i64,
+    /// When the rule suites were last listed completely. The listing of the next sync reaches back
+    /// to this point in time.
+    pub synced_at: DateTime<Utc>,
+}
+
+impl SyncCursor {
+    pub fn new(suite: &RuleSuite, synced_at: DateTime<Utc>) -> Self {
+        Self {
+            pushed_at: suite.pushed_at,
+            rule_suite_id: suite.id,
+            synced_at,
+        }
+    }
+
+    /// Whether the rule suite has already been ingested. Rule suites are ordered by push time,
+    /// ties are broken by id.
+    pub fn covers(&self, suite: &RuleSuite) -> bool {
+        (suite.pushed_at, suite.id) <= (self.pushed_at, self.rule_suite_id)
+    }
+
+    /// Whether listing can stop at the rule suite, i.e. it was pushed more than
+    /// [`SYNC_SAFETY_WINDOW`] before the newest ingested one.
+    pub fn is_behind_window(&self, suite: &RuleSuite) -> bool {
+        suite.pushed_at < self.pushed_at - SYNC_SAFETY_WINDOW
+    }
 }
 
 /// GitHub rule suite event storage
@@ -118,8 +470,37 @@ pub struct GithubRuleSuiteEvent {
     pub resulting_commit: Option<String>,
     /// JSON serialized array of [`PullRequest`]
     pub prs: Option<String>,
+    /// JSON serialized [`soc2::ci::CiChecks`] of the resulting commit, only set for CI bypasses
+    #[serde(default)]
+    pub ci_checks: Option<String>,
+    /// JSON serialized [`soc2::force_push::DiscardedCommits`], only set for force push bypasses
+    #[serde(default)]
+    pub discarded_commits: Option<String>,
     /// Whether a notification has been sent for this record (e.g. to Slack).
     pub notified: bool,
+    /// JSON serialized [`soc2::policy::PolicyDecision`], explaining how the record was notified
+    #[serde(default)]
+    pub decision: Option<String>,
+    /// JSON serialized [`soc2::retroactive_review::ReviewStatus`], only set for bypasses that
+    /// need a retroactive review
+    #[serde(default)]
+    pub review: Option<String>,
+    /// JSON serialized array of [`PostedMessage`], every posted copy of the notification. The
+    /// actor may reply to their threads.
+    #[serde(default)]
+    pub slack_messages: Option<String>,
+    /// JSON serialized [`soc2::justification::Justification`] given by the actor
+    #[serde(default)]
+    pub justification: Option<String>,
+    /// Whether the actor justified the bypass
+    #[serde(default)]
+    pub justified: bool,
+    /// JSON serialized [`soc2::reminder::ReminderStatus`], set once the actor was reminded
+    #[serde(default)]
+    pub reminder: Option<String>,
+    /// JSON serialized array of [`soc2::action::ViolationAction`] taken on the notification
+    #[serde(default)]
+    pub actions: Option<String>,
     /// When the record was created.
     #[serde(deserialize_with = "crate::null_date_format::deserialize")]
     pub created_at: DateTime<Utc>,
@@ -136,6 +517,8 @@ pub struct NewGithubRuleSuiteEvent {
     pub event_data: String,
     pub resulting_commit: Option<String>,
     pub prs: Option<String>,
+    pub ci_checks: Option<String>,
+    pub discarded_commits: Option<String>,
     pub notified: bool,
 }
 
@@ -0,0 +1,136 @@
+//! Sending violations by email via SMTP.
+//!
+//! The client speaks plain SMTP without TLS or authentication, so it is meant for a relay on the
+//! local network, e.g. a sidecar forwarding to the mail provider.
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::notify::{Notifier, Violation};
+
+/// Emails every violation as plain text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpNotifier {
+    /// The host of the SMTP relay
+    pub host: String,
+    /// Usually 25
+    pub port: u16,
+    /// The name announced in `EHLO`
+    pub hello_name: String,
+    /// The sender address, e.g. `ruleset-policy-bot@zoo.dev`
+    pub from: String,
+    /// The recipient addresses
+    pub to: Vec<String>,
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    async fn notify(&self, violation: &Violation) -> Result<()> {
+        let subject = format!("{} in {}", violation.title(), violation.repository);
+        self.send(&subject, &violation.to_plain_text())
+            .await
+            .with_context(|| format!("sending email via {}:{} failed", self.host, self.port))
+    }
+}
+
+impl SmtpNotifier {
+    async fn send(&self, subject: &str, body: &str) -> Result<()> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let mut smtp = SmtpConnection {
+            stream: BufReader::new(stream),
+        };
+
+        smtp.expect(220).await?;
+        smtp.command(&format!("EHLO {}", self.hello_name), 250)
+            .await?;
+        smtp.command(&format!("MAIL FROM:<{}>", self.from), 250)
+            .await?;
+        for to in &self.to {
+            smtp.command(&format!("RCPT TO:<{to}>"), 250).await?;
+        }
+        smtp.command("DATA", 354).await?;
+
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}",
+            self.from,
+            self.to.join(", "),
+            header_value(subject),
+            Utc::now().to_rfc2822(),
+            dot_stuff(body),
+        );
+        smtp.stream.write_all(message.as_bytes()).await?;
+        // The stuffed body ends with a line break
+        smtp.command(".", 250).await?;
+        smtp.command("QUIT", 221).await
+    }
+}
+
+struct SmtpConnection {
+    stream: BufReader<TcpStream>,
+}
+
+impl SmtpConnection {
+    async fn command(&mut self, command: &str, code: u16) -> Result<()> {
+        self.stream
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await?;
+        self.expect(code).await
+    }
+
+    /// Reads a reply, which may span multiple lines like `250-...`, and checks its code
+    async fn expect(&mut self, code: u16) -> Result<()> {
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                bail!("the SMTP server closed the connection");
+            }
+            if !line.starts_with(&code.to_string()) {
+                bail!(
+                    "unexpected SMTP reply, expected {code}: {}",
+                    line.trim_end()
+                );
+            }
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Removes line breaks, which would start another header
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// Normalizes line endings to CRLF and escapes lines starting with a dot, which would otherwise
+/// end the message early
+fn dot_stuff(body: &str) -> String {
+    body.lines()
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{line}\r\n")
+            } else {
+                format!("{line}\r\n")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dot_stuff;
+
+    #[test]
+    fn dot_stuffing() {
+        assert_eq!(
+            dot_stuff("Critical\n.\n..hidden\r\nlast"),
+            "Critical\r\n..\r\n...hidden\r\nlast\r\n"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Posting JSON to webhooks.
+
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result, anyhow, bail};
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, body::Bytes, header};
+use hyper_rustls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use serde::Serialize;
+
+type HttpsClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// The client shared by all notifiers, built on first use. Loading the native root certificates
+/// is slow, and connections are reused.
+fn client() -> Result<&'static HttpsClient> {
+    static CLIENT: OnceLock<Result<HttpsClient, String>> = OnceLock::new();
+
+    CLIENT
+        .get_or_init(|| {
+            let connector = hyper_rustls::HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .map_err(|e| format!("loading the native root certificates failed: {e}"))?
+                .https_or_http()
+                .enable_http1()
+                .build();
+            Ok(Client::builder(TokioExecutor::new()).build(connector))
+        })
+        .as_ref()
+        .map_err(|e| anyhow!("{e}"))
+}
+
+/// Posts `body` as JSON to `url`, which may be HTTP or HTTPS. A status other than 2xx is an
+/// error, including the response body.
+pub(crate) async fn post_json<T: Serialize + ?Sized>(url: &str, body: &T) -> Result<()> {
+    let client = client()?;
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(serde_json::to_vec(body)?)))?;
+    let response = client
+        .request(request)
+        .await
+        .with_context(|| format!("posting to {url} failed"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.into_body().collect().await?.to_bytes();
+        bail!(
+            "posting to {url} failed with {status}: {}",
+            String::from_utf8_lossy(&body)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! Posting violations to generic JSON webhooks.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::notify::{Notifier, Violation, http::post_json};
+
+/// Posts every violation, serialized as [`Violation`], to a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonWebhookNotifier {
+    pub url: String,
+}
+
+#[async_trait]
+impl Notifier for JsonWebhookNotifier {
+    async fn notify(&self, violation: &Violation) -> Result<()> {
+        post_json(&self.url, violation).await
+    }
+}
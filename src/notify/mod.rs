@@ -0,0 +1,182 @@
+//! Channel-neutral notifications about violations.
+//!
+//! A [`Violation`] describes a bypass independent of where it is sent. Text uses Markdown with
+//! `[text](url)` links and `**bold**` text, which each backend converts to its own format. Slack is the primary
+//! channel, see [`slack`], and receives the notification for the actor. Every [`Notifier`] in
+//! [`BotConfig::notifiers`] receives a copy of every notified violation. With the `notify`
+//! feature, backends for generic JSON webhooks ([`json_webhook`]), email via SMTP ([`email`])
+//! and Microsoft Teams incoming webhooks ([`teams`]) are available.
+//!
+//! [`BotConfig::notifiers`]: crate::BotConfig::notifiers
+
+#[cfg(feature = "notify")]
+pub mod email;
+#[cfg(feature = "notify")]
+mod http;
+#[cfg(feature = "notify")]
+pub mod json_webhook;
+pub mod slack;
+#[cfg(feature = "notify")]
+pub mod teams;
+
+use std::{fmt::Debug, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A bypass as notified, see [`crate::soc2::rule_suit::RuleSuite::violation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// The GitHub id of the rule suite
+    pub rule_suite_id: i64,
+    pub critical: bool,
+    /// The GitHub login of the actor
+    pub actor: Option<String>,
+    /// The repository name, without the organization
+    pub repository: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub commit_sha: String,
+    pub commit_url: String,
+    /// The violated rules, one line each
+    pub description: String,
+    /// How the policy decided to notify, see [`crate::soc2::policy::PolicyDecision::explanation`]
+    pub explanation: String,
+    /// The reported rules
+    pub rules: Vec<ViolatedRule>,
+}
+
+/// A reported rule of a [`Violation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViolatedRule {
+    pub rule_type: String,
+    /// Whether bypassing the rule is a critical violation
+    pub critical: bool,
+    /// e.g. the commit, the pull requests and the ruleset
+    pub fields: Vec<ViolationField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViolationField {
+    pub title: String,
+    /// Markdown
+    pub value: String,
+    /// Whether the field fits next to another one
+    pub short: bool,
+}
+
+impl Violation {
+    /// e.g. "Critical GitHub Policy Violation"
+    pub fn title(&self) -> String {
+        format!(
+            "{}GitHub Policy Violation",
+            if self.critical {
+                "Critical "
+            } else {
+                "Potential "
+            }
+        )
+    }
+
+    /// Plain text, with links written out, e.g. for email bodies
+    pub fn to_plain_text(&self) -> String {
+        let mut text = format!(
+            "{}\n\nActor: {}\nRepository: {}\nRef: {}\nCommit: {}\n\n{}\n",
+            self.title(),
+            self.actor.as_deref().unwrap_or("Unknown"),
+            self.repository,
+            self.ref_name,
+            self.commit_url,
+            self.description.trim_end(),
+        );
+        for rule in &self.rules {
+            text.push_str(&format!("\n{}\n", rule.rule_type));
+            for field in &rule.fields {
+                text.push_str(&format!(
+                    "  {}: {}\n",
+                    field.title,
+                    markdown_to_plain_text(&field.value)
+                ));
+            }
+        }
+        text.push_str(&format!(
+            "\n{}\n",
+            markdown_to_plain_text(&self.explanation)
+        ));
+        text
+    }
+}
+
+/// How long a [`Notifier`] may take to notify, so that a hanging channel does not stall
+/// processing. Requires a Tokio runtime with the time driver enabled.
+pub const NOTIFIER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends violations to a channel
+#[async_trait]
+pub trait Notifier: Debug + Send + Sync {
+    /// Notifies about the violation. Failures and timeouts, see [`NOTIFIER_TIMEOUT`], are logged
+    /// and do not fail processing.
+    async fn notify(&self, violation: &Violation) -> Result<()>;
+}
+
+/// Calls `convert(text, url)` for every Markdown link and keeps the text in between
+pub(crate) fn convert_markdown_links(
+    markdown: &str,
+    convert: impl Fn(&str, &str) -> String,
+) -> String {
+    let mut converted = String::new();
+    let mut rest = markdown;
+    while let Some(start) = rest.find('[') {
+        let link = rest[start + 1..].split_once("](").and_then(|(text, tail)| {
+            let (url, tail) = tail.split_once(')')?;
+            (!text.contains(']') && !url.is_empty() && !url.contains(char::is_whitespace))
+                .then_some((text, url, tail))
+        });
+        match link {
+            Some((text, url, tail)) => {
+                converted.push_str(&rest[..start]);
+                converted.push_str(&convert(text, url));
+                rest = tail;
+            }
+            None => {
+                converted.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    converted.push_str(rest);
+    converted
+}
+
+/// Replaces Markdown links by `text (url)`
+pub fn markdown_links_to_plain_text(markdown: &str) -> String {
+    convert_markdown_links(markdown, |text, url| format!("{text} ({url})"))
+}
+
+/// Replaces Markdown links by `text (url)` and removes the emphasis of `**bold**` text
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    markdown_links_to_plain_text(markdown).replace("**", "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown_links_to_plain_text, markdown_to_plain_text};
+
+    #[test]
+    fn markdown_links() {
+        assert_eq!(
+            markdown_links_to_plain_text(
+                "[`4a1f6e2`](https://github.com/KittyCAD/modeling-app/commit/4a1f6e2) by [bot], \
+                 [#7140](https://github.com/KittyCAD/modeling-app/pull/7140)"
+            ),
+            "`4a1f6e2` (https://github.com/KittyCAD/modeling-app/commit/4a1f6e2) by [bot], \
+             #7140 (https://github.com/KittyCAD/modeling-app/pull/7140)"
+        );
+        assert_eq!(markdown_links_to_plain_text("[a] (b)"), "[a] (b)");
+        assert_eq!(
+            markdown_to_plain_text("**Sub-type:** [`push`](https://zoo.dev)"),
+            "Sub-type: `push` (https://zoo.dev)"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Slack notifications. The notifications of the SOC2 channel, the actor and the escalation
+//! contacts additionally carry the buttons of [`crate::soc2::action`].
+
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use slack_morphism::prelude::*;
+
+use crate::{
+    SlackClient,
+    notify::{Notifier, Violation, convert_markdown_links},
+};
+
+/// Posts violations to a Slack channel, in addition to the SOC2 channel. The notifications carry
+/// no buttons, actions are only taken on the notifications of the SOC2 channel and the actor.
+#[derive(Clone)]
+pub struct SlackNotifier {
+    pub client: Arc<dyn SlackClient>,
+    pub channel: SlackChannelId,
+}
+
+impl Debug for SlackNotifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlackNotifier")
+            .field("channel", &self.channel)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn notify(&self, violation: &Violation) -> Result<()> {
+        self.client
+            .post_message_channel(self.channel.clone(), build_slack_message(violation, None))
+            .await
+            .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Replaces Markdown links by Slack links, e.g. `<https://github.com|GitHub>`
+pub fn markdown_links_to_slack(markdown: &str) -> String {
+    convert_markdown_links(markdown, |text, url| format!("<{url}|{text}>"))
+}
+
+/// Converts Markdown links and `**bold**` text to Slack markup
+pub fn markdown_to_slack(markdown: &str) -> String {
+    markdown_links_to_slack(markdown).replace("**", "*")
+}
+
+fn attachment_color(critical: bool) -> &'static str {
+    if critical {
+        // red
+        "#E01E5A"
+    } else {
+        // orange
+        "#ECB22E"
+    }
+}
+
+/// Builds the notification addressed to `addressee`, a Slack mention of the actor or the fallback
+/// recipient, see [`crate::soc2::ActorRecipients::mention`]. Without an addressee, the actor is
+/// named by login.
+pub fn build_slack_message(violation: &Violation, addressee: Option<&str>) -> SlackMessageContent {
+    let mut blocks: Vec<SlackBlock> = Vec::new();
+    blocks.push(
+        SlackHeaderBlock {
+            block_id: None,
+            text: SlackBlockPlainText::from(violation.title()).into(),
+        }
+        .into(),
+    );
+
+    let addressee = match addressee {
+        Some(addressee) => addressee.to_string(),
+        None => format!(
+            "*{}*",
+            violation.actor.as_deref().unwrap_or("Unknown actor")
+        ),
+    };
+    let summary = if violation.critical {
+        format!(
+            "{addressee}, please leave a comment in the thread why the below rules were violated.",
+        )
+    } else {
+        format!(
+            "{addressee}, please make sure no security policy has been violated. No need to comment.",
+        )
+    };
+
+    blocks.push(
+        SlackSectionBlock {
+            block_id: None,
+            text: Some(SlackBlockText::MarkDown(SlackBlockMarkDownText::from(
+                summary.clone(),
+            ))),
+            fields: None,
+            accessory: None,
+        }
+        .into(),
+    );
+
+    let actor = format!(
+        "*Actor*\n{}",
+        violation.actor.as_deref().unwrap_or("Unknown")
+    );
+
+    blocks.push(
+        SlackSectionBlock {
+            block_id: None,
+            text: Some(SlackBlockText::MarkDown(SlackBlockMarkDownText::from(
+                actor,
+            ))),
+            fields: None,
+            accessory: None,
+        }
+        .into(),
+    );
+
+    blocks.push(
+        SlackContextBlock {
+            block_id: None,
+            elements: vec![SlackContextBlockElement::MarkDown(
+                SlackBlockMarkDownText::from(markdown_to_slack(&violation.explanation)),
+            )],
+        }
+        .into(),
+    );
+
+    let attachments = violation
+        .rules
+        .iter()
+        .map(|rule| SlackMessageAttachment {
+            id: None,
+            color: Some(attachment_color(rule.critical).to_string()),
+            fallback: Some("no fallback".to_string()),
+            title: None,
+            fields: Some(
+                rule.fields
+                    .iter()
+                    .map(|field| SlackMessageAttachmentFieldObject {
+                        title: Some(field.title.clone()),
+                        value: Some(markdown_to_slack(&field.value)),
+                        short: Some(field.short),
+                    })
+                    .collect(),
+            ),
+            mrkdwn_in: Some(vec!["fields".to_string()]),
+            text: None,
+            blocks: None,
+        })
+        .collect();
+
+    let fallback = format!("{summary}\n\n{}", markdown_to_slack(&violation.description));
+
+    SlackMessageContent {
+        text: Some(fallback),
+        blocks: Some(blocks),
+        attachments: Some(attachments),
+        upload: None,
+        files: None,
+        reactions: None,
+        metadata: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown_links_to_slack, markdown_to_slack};
+
+    #[test]
+    fn slack_links() {
+        assert_eq!(
+            markdown_links_to_slack(
+                "[`4a1f6e2`](https://github.com/KittyCAD/modeling-app/commit/4a1f6e2) in \
+                 `modeling-app`."
+            ),
+            "<https://github.com/KittyCAD/modeling-app/commit/4a1f6e2|`4a1f6e2`> in \
+             `modeling-app`."
+        );
+        assert_eq!(
+            markdown_to_slack("**Policy decision:** DM, [critical](https://zoo.dev)"),
+            "*Policy decision:* DM, <https://zoo.dev|critical>"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Posting violations to Microsoft Teams incoming webhooks as Adaptive Cards.
+//!
+//! https://learn.microsoft.com/en-us/microsoftteams/platform/webhooks-and-connectors/how-to/connectors-using#send-adaptive-cards-using-an-incoming-webhook
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use crate::notify::{Notifier, Violation, http::post_json};
+
+/// Posts every violation to the incoming webhook of a Teams channel, or a Workflows webhook
+/// accepting Adaptive Cards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeamsNotifier {
+    pub url: String,
+}
+
+#[async_trait]
+impl Notifier for TeamsNotifier {
+    async fn notify(&self, violation: &Violation) -> Result<()> {
+        post_json(&self.url, &build_teams_message(violation)).await
+    }
+}
+
+/// Builds the message carrying the Adaptive Card of the violation. Card text supports Markdown
+/// links, so field values are used as is.
+pub fn build_teams_message(violation: &Violation) -> Value {
+    let mut body = vec![
+        json!({
+            "type": "TextBlock",
+            "text": violation.title(),
+            "size": "Large",
+            "weight": "Bolder",
+            "color": if violation.critical { "Attention" } else { "Warning" },
+            "wrap": true,
+        }),
+        json!({
+            "type": "FactSet",
+            "facts": [
+                {
+                    "title": "Actor",
+                    "value": violation.actor.as_deref().unwrap_or("Unknown"),
+                },
+                { "title": "Repository", "value": violation.repository },
+                { "title": "Ref", "value": violation.ref_name },
+            ],
+        }),
+    ];
+
+    for rule in &violation.rules {
+        body.push(json!({
+            "type": "TextBlock",
+            "text": rule.rule_type,
+            "weight": "Bolder",
+            "separator": true,
+            "wrap": true,
+        }));
+        body.push(json!({
+            "type": "FactSet",
+            "facts": rule
+                .fields
+                .iter()
+                .map(|field| json!({ "title": field.title, "value": field.value }))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    body.push(json!({
+        "type": "TextBlock",
+        "text": violation.explanation,
+        "isSubtle": true,
+        "size": "Small",
+        "wrap": true,
+    }));
+
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "msteams": { "width": "Full" },
+                "body": body,
+                "actions": [{
+                    "type": "Action.OpenUrl",
+                    "title": "View commit",
+                    "url": violation.commit_url,
+                }],
+            },
+        }],
+    })
+}
@@ -0,0 +1,361 @@
+//! Actions taken on a notification via its buttons.
+//!
+//! Every notification carries buttons to acknowledge the bypass, to mark it as a false positive
+//! and to request a waiver. The latter two ask for a reason in a modal. Each action is stored on
+//! the rule suite event, and the notification is edited to show who did what. See
+//! `webhook::interaction` for receiving the interactions from Slack.
+//!
+//! Bypasses marked as false positive are neither reminded, escalated nor awaited a justification
+//! or a retroactive review for. Acknowledged bypasses are not reminded.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use octocrab::models::pulls::PullRequest;
+use serde::{Deserialize, Serialize};
+use slack_morphism::prelude::*;
+
+use crate::{
+    BotConfig, GithubRuleSuiteEvent, RulesetBot, SlackClient,
+    notify::slack::build_slack_message,
+    soc2::{
+        actor_recipients, ci::CiChecks, force_push::DiscardedCommits, policy::PolicyDecision,
+        rule_suit::RuleSuite,
+    },
+};
+
+/// The block and action id of the reason input in the modal
+pub const REASON_INPUT: &str = "reason";
+
+/// What a button of a notification does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    /// The actor acknowledges the bypass
+    Acknowledge,
+    /// Security marks the bypass as a false positive, with a reason
+    FalsePositive,
+    /// The actor requests a waiver, with a reason
+    RequestWaiver,
+}
+
+impl ActionKind {
+    pub const ALL: [ActionKind; 3] = [
+        ActionKind::Acknowledge,
+        ActionKind::FalsePositive,
+        ActionKind::RequestWaiver,
+    ];
+
+    /// The `action_id` of the button, also the `callback_id` of the modal
+    pub fn action_id(self) -> &'static str {
+        match self {
+            ActionKind::Acknowledge => "acknowledge",
+            ActionKind::FalsePositive => "false_positive",
+            ActionKind::RequestWaiver => "request_waiver",
+        }
+    }
+
+    pub fn from_action_id(action_id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.action_id() == action_id)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ActionKind::Acknowledge => "Acknowledge",
+            ActionKind::FalsePositive => "False positive",
+            ActionKind::RequestWaiver => "Request waiver",
+        }
+    }
+
+    /// Whether a reason is asked for in a modal
+    pub fn requires_reason(self) -> bool {
+        self != ActionKind::Acknowledge
+    }
+
+    /// Whether only the escalation contacts may take the action, otherwise only the notified
+    /// actor may
+    pub fn requires_security(self) -> bool {
+        self == ActionKind::FalsePositive
+    }
+}
+
+/// An action taken on a notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ViolationAction {
+    Acknowledged {
+        user: SlackUserId,
+        at: DateTime<Utc>,
+    },
+    FalsePositive {
+        user: SlackUserId,
+        reason: String,
+        at: DateTime<Utc>,
+    },
+    WaiverRequested {
+        user: SlackUserId,
+        reason: String,
+        at: DateTime<Utc>,
+    },
+}
+
+impl ViolationAction {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Self::Acknowledged { .. } => ActionKind::Acknowledge,
+            Self::FalsePositive { .. } => ActionKind::FalsePositive,
+            Self::WaiverRequested { .. } => ActionKind::RequestWaiver,
+        }
+    }
+
+    /// The action of `kind` by `user`. The reason is ignored for acknowledgements.
+    pub fn new(kind: ActionKind, user: SlackUserId, reason: String, at: DateTime<Utc>) -> Self {
+        match kind {
+            ActionKind::Acknowledge => Self::Acknowledged { user, at },
+            ActionKind::FalsePositive => Self::FalsePositive { user, reason, at },
+            ActionKind::RequestWaiver => Self::WaiverRequested { user, reason, at },
+        }
+    }
+}
+
+impl Display for ViolationAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let at = |at: &DateTime<Utc>| at.format("%Y-%m-%d %H:%M UTC").to_string();
+        match self {
+            Self::Acknowledged { user, at: time } => {
+                write!(
+                    f,
+                    ":white_check_mark: Acknowledged by <@{user}> at {}",
+                    at(time)
+                )
+            }
+            Self::FalsePositive {
+                user,
+                reason,
+                at: time,
+            } => write!(
+                f,
+                ":no_entry_sign: Marked as false positive by <@{user}> at {}: {reason}",
+                at(time)
+            ),
+            Self::WaiverRequested {
+                user,
+                reason,
+                at: time,
+            } => write!(
+                f,
+                ":memo: Waiver requested by <@{user}> at {}: {reason}",
+                at(time)
+            ),
+        }
+    }
+}
+
+/// The actions taken on the notification of a stored rule suite event
+pub fn event_actions(event: &GithubRuleSuiteEvent) -> Result<Vec<ViolationAction>> {
+    Ok(event
+        .actions
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?
+        .unwrap_or_default())
+}
+
+/// Whether an action of `kind` was taken on the notification of the event
+pub fn has_action(event: &GithubRuleSuiteEvent, kind: ActionKind) -> Result<bool> {
+    Ok(event_actions(event)?
+        .iter()
+        .any(|action| action.kind() == kind))
+}
+
+/// The buttons of the notification of a rule suite
+pub fn action_buttons(github_id: &str) -> SlackBlock {
+    SlackActionsBlock {
+        block_id: None,
+        elements: ActionKind::ALL
+            .into_iter()
+            .map(|kind| {
+                SlackBlockButtonElement {
+                    action_id: SlackActionId(kind.action_id().to_string()),
+                    text: SlackBlockPlainText::from(kind.label()).into(),
+                    url: None,
+                    value: Some(github_id.to_string()),
+                    style: (kind == ActionKind::Acknowledge).then(|| "primary".to_string()),
+                    confirm: None,
+                }
+                .into()
+            })
+            .collect(),
+    }
+    .into()
+}
+
+/// Passed through the reason modal, as it is not part of the submission otherwise
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReasonModalMetadata {
+    /// The GitHub id of the rule suite
+    pub github_id: String,
+    /// The channel of the clicked notification, to tell a denied user
+    pub channel: Option<SlackChannelId>,
+}
+
+/// The modal asking for the reason of an action on the rule suite
+pub fn reason_modal(kind: ActionKind, metadata: &ReasonModalMetadata) -> SlackView {
+    SlackView::Modal(SlackModalView {
+        title: SlackBlockPlainText::from(kind.label()).into(),
+        blocks: vec![
+            SlackInputBlock {
+                block_id: Some(SlackBlockId(REASON_INPUT.to_string())),
+                label: SlackBlockPlainText::from("Reason").into(),
+                element: SlackBlockPlainTextInputElement {
+                    action_id: SlackActionId(REASON_INPUT.to_string()),
+                    placeholder: None,
+                    initial_value: None,
+                    multiline: Some(true),
+                    min_length: None,
+                    max_length: None,
+                    focus_on_load: Some(true),
+                }
+                .into(),
+                hint: None,
+                optional: None,
+                dispatch_action: None,
+            }
+            .into(),
+        ],
+        close: Some(SlackBlockPlainText::from("Cancel").into()),
+        submit: Some(SlackBlockPlainText::from("Submit").into()),
+        private_metadata: serde_json::to_string(metadata).ok(),
+        callback_id: Some(SlackCallbackId(kind.action_id().to_string())),
+        clear_on_close: None,
+        notify_on_close: None,
+        hash: None,
+        external_id: None,
+    })
+}
+
+/// Appends the buttons of the rule suite to a notification, see [`action_buttons`]
+pub fn with_action_buttons(
+    mut content: SlackMessageContent,
+    github_id: &str,
+) -> SlackMessageContent {
+    content
+        .blocks
+        .get_or_insert_with(Vec::new)
+        .push(action_buttons(github_id));
+    content
+}
+
+/// Appends the actions taken to a notification
+pub fn with_actions(
+    mut content: SlackMessageContent,
+    actions: &[ViolationAction],
+) -> SlackMessageContent {
+    if actions.is_empty() {
+        return content;
+    }
+
+    content.blocks.get_or_insert_with(Vec::new).push(
+        SlackContextBlock {
+            block_id: None,
+            elements: actions
+                .iter()
+                .map(|action| {
+                    SlackContextBlockElement::MarkDown(SlackBlockMarkDownText::from(
+                        action.to_string(),
+                    ))
+                })
+                .collect(),
+        }
+        .into(),
+    );
+    content
+}
+
+/// Builds the notification of a stored rule suite event again, including the actions taken
+pub async fn rebuild_notification(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    event: &GithubRuleSuiteEvent,
+    actions: &[ViolationAction],
+) -> Result<SlackMessageContent> {
+    let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+    let mut prs: Vec<PullRequest> = event
+        .prs
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?
+        .unwrap_or_default();
+    suite.sort_pull_requests(&mut prs);
+    let ci_checks: Option<CiChecks> = event
+        .ci_checks
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?;
+    let discarded_commits: Option<DiscardedCommits> = event
+        .discarded_commits
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?;
+    let decision: PolicyDecision = event
+        .decision
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?
+        .unwrap_or_default();
+
+    let recipients = actor_recipients(&suite, slack, bot, config).await;
+    let violation = suite.violation(
+        &prs,
+        ci_checks.as_ref(),
+        discarded_commits.as_ref(),
+        &decision,
+        config,
+    );
+    let content = with_action_buttons(
+        build_slack_message(&violation, recipients.mention.as_deref()),
+        &event.github_id,
+    );
+
+    Ok(with_actions(content, actions))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use slack_morphism::SlackUserId;
+
+    use super::{ActionKind, ViolationAction};
+
+    #[test]
+    fn violation_actions() {
+        for kind in ActionKind::ALL {
+            assert_eq!(ActionKind::from_action_id(kind.action_id()), Some(kind));
+        }
+        assert_eq!(ActionKind::from_action_id("approve"), None);
+
+        let at = DateTime::from_timestamp(1_700_000_000, 0).expect("valid timestamp");
+        let user = SlackUserId("U0SEC0001".to_string());
+        let false_positive = ViolationAction::new(
+            ActionKind::FalsePositive,
+            user.clone(),
+            "The ruleset was misconfigured".to_string(),
+            at,
+        );
+        assert_eq!(
+            false_positive.to_string(),
+            ":no_entry_sign: Marked as false positive by <@U0SEC0001> at 2023-11-14 22:13 UTC: \
+             The ruleset was misconfigured"
+        );
+        assert_eq!(
+            ViolationAction::new(ActionKind::Acknowledge, user, "ignored".to_string(), at),
+            ViolationAction::Acknowledged {
+                user: SlackUserId("U0SEC0001".to_string()),
+                at
+            }
+        );
+    }
+}
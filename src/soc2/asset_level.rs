@@ -1,25 +1,52 @@
-use octocrab::Octocrab;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    ops::RangeInclusive,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use octocrab::{Octocrab, Page};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{BotConfig, GitHubApi};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomProperty {
     pub property_name: String,
     pub value: Option<CustomPropertyValue>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(untagged)]
 pub enum CustomPropertyValue {
     String(String),
     Array(Vec<String>),
 }
 
+/// The custom property values of a repository, as listed for a whole organization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryCustomProperties {
+    pub repository_id: i64,
+    pub repository_name: String,
+    pub repository_full_name: String,
+    pub properties: Vec<CustomProperty>,
+}
+
 pub trait CustomPropertyExt {
     fn list_custom_properties(
         &self,
         owner: &str,
         repo: &str,
     ) -> impl std::future::Future<Output = octocrab::Result<Vec<CustomProperty>>> + Send;
+
+    /// Lists the custom property values of all repositories in an organization, following
+    /// pagination until the last page.
+    fn list_org_custom_properties(
+        &self,
+        org: &str,
+    ) -> impl std::future::Future<Output = octocrab::Result<Vec<RepositoryCustomProperties>>> + Send;
 }
 
 impl CustomPropertyExt for Octocrab {
@@ -34,6 +61,20 @@ impl CustomPropertyExt for Octocrab {
         )
         .await
     }
+
+    async fn list_org_custom_properties(
+        &self,
+        org: &str,
+    ) -> anyhow::Result<Vec<RepositoryCustomProperties>, octocrab::Error> {
+        // https://docs.github.com/en/rest/orgs/custom-properties?apiVersion=2022-11-28#list-custom-property-values-for-organization-repositories
+        let first_page: Page<RepositoryCustomProperties> = self
+            .get(
+                format!("/orgs/{org}/properties/values"),
+                Some(&[("per_page", 100)]),
+            )
+            .await?;
+        self.all_pages(first_page).await
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -64,25 +105,308 @@ impl AssetLevel {
         }
     }
 
-    pub fn get_from_props(props: &[CustomProperty]) -> Option<AssetLevel> {
-        props
+    /// The value of the custom property for this level
+    pub fn name(&self) -> &'static str {
+        match self {
+            AssetLevel::Production => "Production",
+            AssetLevel::Playground => "Playground",
+            AssetLevel::ResearchNDevelopment => "Research & Development",
+            AssetLevel::Corporate => "Corporate",
+            AssetLevel::NonEssentialProduction => "Non-essential Production",
+        }
+    }
+
+    /// Reads the asset level using the default [`AssetTaxonomy`].
+    pub fn get_from_props(props: &[CustomProperty]) -> Result<AssetLevel, AssetLevelError> {
+        let tier = AssetTaxonomy::default().get_from_props(props)?;
+        Ok(AssetLevel::ALL[tier.rank])
+    }
+
+    /// All levels from least critical to most critical
+    pub const ALL: [AssetLevel; 5] = [
+        AssetLevel::Playground,
+        AssetLevel::ResearchNDevelopment,
+        AssetLevel::Corporate,
+        AssetLevel::NonEssentialProduction,
+        AssetLevel::Production,
+    ];
+}
+
+/// An ordered set of asset levels, read from a custom property of each repository.
+///
+/// The default taxonomy consists of the [`AssetLevel`]s in the `repository-level` property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetTaxonomy {
+    /// The custom property holding the asset level of a repository
+    pub property_name: String,
+    /// The level names, from least critical to most critical
+    pub levels: Vec<String>,
+}
+
+impl Default for AssetTaxonomy {
+    fn default() -> Self {
+        Self {
+            property_name: "repository-level".to_string(),
+            levels: AssetLevel::ALL
+                .iter()
+                .map(|level| level.name().to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AssetTaxonomy {
+    /// Returns the tier with the given name, if it is part of the taxonomy
+    pub fn tier(&self, name: &str) -> Option<AssetTier> {
+        self.levels
             .iter()
-            .find(|prop| prop.property_name == "repository-level")
-            .and_then(|prop| match &prop.value {
-                None => None,
-                Some(CustomPropertyValue::Array(_array)) => {
-                    panic!("Array not supported for repository-level")
-                }
-                Some(CustomPropertyValue::String(str)) => match str.as_str() {
-                    "Production" => Some(AssetLevel::Production),
-                    "Playground" => Some(AssetLevel::Playground),
-                    "Research & Development" => Some(AssetLevel::ResearchNDevelopment),
-                    "Corporate" => Some(AssetLevel::Corporate),
-                    "Non-essential Production" => Some(AssetLevel::NonEssentialProduction),
-                    _ => None,
-                },
+            .position(|level| level == name)
+            .map(|rank| AssetTier {
+                rank,
+                name: name.to_string(),
             })
     }
+
+    /// Returns the range of tiers from `least` to `most` critical, e.g. for
+    /// [`crate::BotConfig::in_scope_asset_level`]
+    pub fn range(&self, least: &str, most: &str) -> Option<RangeInclusive<AssetTier>> {
+        Some(self.tier(least)?..=self.tier(most)?)
+    }
+
+    /// Checks that both ends of the range are tiers of this taxonomy, matching in rank and name.
+    /// Tiers of another taxonomy would silently compare against the wrong levels.
+    pub fn check_range(&self, range: &RangeInclusive<AssetTier>) -> anyhow::Result<()> {
+        for tier in [range.start(), range.end()] {
+            if self.tier(&tier.name).as_ref() != Some(tier) {
+                anyhow::bail!(
+                    "asset tier `{}` with rank {} is not part of the taxonomy `{}`",
+                    tier.name,
+                    tier.rank,
+                    self.property_name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_from_props(&self, props: &[CustomProperty]) -> Result<AssetTier, AssetLevelError> {
+        let prop = props
+            .iter()
+            .find(|prop| prop.property_name == self.property_name)
+            .ok_or(AssetLevelError::Missing)?;
+
+        match &prop.value {
+            None => Err(AssetLevelError::Missing),
+            Some(CustomPropertyValue::Array(values)) => {
+                Err(AssetLevelError::MultiValued(values.clone()))
+            }
+            Some(CustomPropertyValue::String(str)) => self
+                .tier(str)
+                .ok_or_else(|| AssetLevelError::Unrecognized(str.clone())),
+        }
+    }
+}
+
+/// A level of an [`AssetTaxonomy`]. Tiers are ordered from least to most critical.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct AssetTier {
+    /// The position in [`AssetTaxonomy::levels`]
+    pub rank: usize,
+    pub name: String,
+}
+
+impl From<AssetLevel> for AssetTier {
+    fn from(level: AssetLevel) -> Self {
+        Self {
+            rank: AssetLevel::ALL
+                .iter()
+                .position(|other| *other == level)
+                .expect("all levels are listed"),
+            name: level.name().to_string(),
+        }
+    }
+}
+
+impl Display for AssetTier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Why the asset level of a repository could not be determined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetLevelError {
+    /// The property is not set. The repository has not been classified.
+    Missing,
+    /// The property holds multiple values
+    MultiValued(Vec<String>),
+    /// The property holds a value that is not part of the taxonomy
+    Unrecognized(String),
+}
+
+impl Display for AssetLevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetLevelError::Missing => f.write_str("the asset level is not set"),
+            AssetLevelError::MultiValued(values) => write!(
+                f,
+                "the asset level has multiple values: {}",
+                values.join(", ")
+            ),
+            AssetLevelError::Unrecognized(value) => write!(f, "unknown asset level `{value}`"),
+        }
+    }
+}
+
+impl std::error::Error for AssetLevelError {}
+
+/// How often the SOC2 channel is warned again about an unchanged misconfiguration
+pub const ASSET_LEVEL_WARNING_INTERVAL: TimeDelta = TimeDelta::days(1);
+
+/// The last warning about the misconfigured asset level of a repository, stored via
+/// [`crate::RulesetBot::set_asset_level_warning`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetLevelWarning {
+    /// The error that was warned about, e.g. ``unknown asset level `Prodution` ``
+    pub error: String,
+    pub warned_at: DateTime<Utc>,
+}
+
+impl AssetLevelWarning {
+    pub fn new(error: &AssetLevelError, warned_at: DateTime<Utc>) -> Self {
+        Self {
+            error: error.to_string(),
+            warned_at,
+        }
+    }
+
+    /// Whether this warning still covers `error` at `now`, i.e. the error is unchanged and the
+    /// warning is younger than [`ASSET_LEVEL_WARNING_INTERVAL`]
+    pub fn covers(&self, error: &AssetLevelError, now: DateTime<Utc>) -> bool {
+        self.error == error.to_string() && now - self.warned_at < ASSET_LEVEL_WARNING_INTERVAL
+    }
+}
+
+/// Caches the asset level of repositories for a fixed time to live.
+///
+/// [`AssetLevelCache::refresh_org`] loads the asset levels of all repositories of an
+/// organization with one request per page. Repositories missing from the cache or with an
+/// expired entry are looked up individually by [`AssetLevelCache::get`].
+#[derive(Debug)]
+pub struct AssetLevelCache {
+    ttl: Duration,
+    /// Asset tiers by repository name, or why a repository has none
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    /// When the whole organization was last fetched
+    org_fetched_at: Mutex<Option<Instant>>,
+}
+
+impl AssetLevelCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            org_fetched_at: Mutex::new(None),
+        }
+    }
+
+    fn is_fresh(&self, fetched_at: Instant) -> bool {
+        fetched_at.elapsed() < self.ttl
+    }
+
+    /// Returns the asset tier of a repository in [`BotConfig::github_org`], fetching its custom
+    /// properties if the cache has no fresh entry. The outer error is a failure to fetch the
+    /// custom properties.
+    pub async fn get(
+        &self,
+        github: &dyn GitHubApi,
+        config: &BotConfig,
+        repository_name: &str,
+    ) -> anyhow::Result<Result<AssetTier, AssetLevelError>> {
+        let cached = self
+            .entries
+            .lock()
+            .expect("asset level cache poisoned")
+            .get(repository_name)
+            .cloned();
+        if let Some(entry) = cached
+            && self.is_fresh(entry.fetched_at)
+        {
+            return Ok(entry.asset_level);
+        }
+
+        let props = github
+            .list_custom_properties(&config.github_org, repository_name)
+            .await?;
+        let asset_level = config.asset_taxonomy.get_from_props(&props);
+
+        self.entries
+            .lock()
+            .expect("asset level cache poisoned")
+            .insert(
+                repository_name.to_string(),
+                CacheEntry {
+                    fetched_at: Instant::now(),
+                    asset_level: asset_level.clone(),
+                },
+            );
+
+        Ok(asset_level)
+    }
+
+    /// Loads the asset tiers of all repositories in [`BotConfig::github_org`], unless that has
+    /// been done within the time to live.
+    pub async fn refresh_org(
+        &self,
+        github: &dyn GitHubApi,
+        config: &BotConfig,
+    ) -> anyhow::Result<()> {
+        let org_fetched_at = *self
+            .org_fetched_at
+            .lock()
+            .expect("asset level cache poisoned");
+        if org_fetched_at.is_some_and(|fetched_at| self.is_fresh(fetched_at)) {
+            return Ok(());
+        }
+
+        let repositories = github
+            .list_org_custom_properties(&config.github_org)
+            .await?;
+        let fetched_at = Instant::now();
+
+        self.entries
+            .lock()
+            .expect("asset level cache poisoned")
+            .extend(repositories.into_iter().map(|repository| {
+                let asset_level = config.asset_taxonomy.get_from_props(&repository.properties);
+                (
+                    repository.repository_name,
+                    CacheEntry {
+                        fetched_at,
+                        asset_level,
+                    },
+                )
+            }));
+        *self
+            .org_fetched_at
+            .lock()
+            .expect("asset level cache poisoned") = Some(fetched_at);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    fetched_at: Instant,
+    asset_level: Result<AssetTier, AssetLevelError>,
+}
+
+impl Default for AssetLevelCache {
+    /// Custom properties rarely change, so a few minutes of staleness are acceptable.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(15 * 60))
+    }
 }
 
 impl Ord for AssetLevel {
@@ -99,7 +423,50 @@ impl PartialOrd for AssetLevel {
 
 #[cfg(test)]
 mod tests {
-    use super::AssetLevel;
+    use std::time::Duration;
+
+    use chrono::{DateTime, TimeDelta};
+
+    use super::{
+        AssetLevel, AssetLevelCache, AssetLevelError, AssetLevelWarning, AssetTaxonomy, AssetTier,
+        CustomProperty, CustomPropertyValue,
+    };
+    use crate::{
+        BotConfig, GitHubAuth, RuleSuiteFilter,
+        github::FakeGitHub,
+        soc2::{escalation::EscalationContacts, policy::Policy, ref_filter::RefFilter},
+    };
+
+    fn config(asset_taxonomy: AssetTaxonomy) -> BotConfig {
+        BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com".to_string(),
+            slack_soc2_channel: "C0000000000".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy,
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        }
+    }
+
+    fn level_props(level: &str) -> Vec<CustomProperty> {
+        vec![CustomProperty {
+            property_name: "repository-level".to_string(),
+            value: Some(CustomPropertyValue::String(level.to_string())),
+        }]
+    }
 
     #[test]
     fn asset_level_custom_ordering() {
@@ -136,4 +503,183 @@ mod tests {
             (AssetLevel::Playground..=AssetLevel::Production).contains(&AssetLevel::Production)
         );
     }
+
+    #[tokio::test]
+    async fn asset_level_cache_refreshes_whole_org() {
+        let github = FakeGitHub::new()
+            .with_custom_properties("KittyCAD", "api", level_props("Production"))
+            .with_custom_properties("KittyCAD", "playground", level_props("Playground"))
+            .with_custom_properties("KittyCAD", "unlabeled", vec![])
+            .with_custom_properties("OtherOrg", "api", level_props("Corporate"));
+        let cache = AssetLevelCache::default();
+
+        cache
+            .refresh_org(&github, &config(AssetTaxonomy::default()))
+            .await
+            .unwrap();
+
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries["api"].asset_level,
+            Ok(AssetLevel::Production.into())
+        );
+        assert_eq!(
+            entries["playground"].asset_level,
+            Ok(AssetLevel::Playground.into())
+        );
+        assert_eq!(
+            entries["unlabeled"].asset_level,
+            Err(AssetLevelError::Missing)
+        );
+    }
+
+    #[tokio::test]
+    async fn asset_level_cache_fetches_missing_and_expired_entries() {
+        let github =
+            FakeGitHub::new().with_custom_properties("KittyCAD", "api", level_props("Production"));
+        let cache = AssetLevelCache::new(Duration::ZERO);
+        let config = config(AssetTaxonomy::default());
+
+        assert_eq!(
+            cache.get(&github, &config, "api").await.unwrap(),
+            Ok(AssetLevel::Production.into())
+        );
+        // Expired entries are fetched again.
+        let github =
+            FakeGitHub::new().with_custom_properties("KittyCAD", "api", level_props("Corporate"));
+        assert_eq!(
+            cache.get(&github, &config, "api").await.unwrap(),
+            Ok(AssetLevel::Corporate.into())
+        );
+    }
+
+    #[test]
+    fn default_taxonomy_matches_asset_levels() {
+        let taxonomy = AssetTaxonomy::default();
+        for level in AssetLevel::ALL {
+            assert_eq!(taxonomy.tier(level.name()), Some(AssetTier::from(level)));
+        }
+
+        let in_scope = taxonomy.range("Corporate", "Production").unwrap();
+        assert!(in_scope.contains(&AssetLevel::NonEssentialProduction.into()));
+        assert!(!in_scope.contains(&AssetLevel::ResearchNDevelopment.into()));
+    }
+
+    #[tokio::test]
+    async fn custom_taxonomy_orders_by_position() {
+        let taxonomy = AssetTaxonomy {
+            property_name: "tier".to_string(),
+            levels: vec![
+                "tier-3".to_string(),
+                "tier-2".to_string(),
+                "tier-1".to_string(),
+            ],
+        };
+        let props = |level: &str| {
+            vec![CustomProperty {
+                property_name: "tier".to_string(),
+                value: Some(CustomPropertyValue::String(level.to_string())),
+            }]
+        };
+
+        let tier_1 = taxonomy.get_from_props(&props("tier-1")).unwrap();
+        assert!(tier_1 > taxonomy.get_from_props(&props("tier-3")).unwrap());
+        assert!(
+            taxonomy
+                .range("tier-2", "tier-1")
+                .unwrap()
+                .contains(&tier_1)
+        );
+        assert_eq!(taxonomy.range("tier-2", "tier-0"), None);
+        assert!(
+            taxonomy
+                .check_range(&taxonomy.range("tier-2", "tier-1").unwrap())
+                .is_ok()
+        );
+        // The default levels are not part of the taxonomy, even where the ranks exist.
+        assert!(
+            taxonomy
+                .check_range(&(AssetLevel::Playground.into()..=AssetLevel::Corporate.into()))
+                .is_err()
+        );
+        let renamed = AssetTier {
+            rank: 0,
+            name: "tier-1".to_string(),
+        };
+        assert!(
+            taxonomy
+                .check_range(&(renamed..=taxonomy.tier("tier-1").unwrap()))
+                .is_err()
+        );
+        // The default property is not consulted.
+        assert_eq!(
+            taxonomy.get_from_props(&level_props("Production")),
+            Err(AssetLevelError::Missing)
+        );
+        assert_eq!(
+            taxonomy.get_from_props(&props("Production")),
+            Err(AssetLevelError::Unrecognized("Production".to_string()))
+        );
+
+        let github = FakeGitHub::new().with_custom_properties("KittyCAD", "api", props("tier-2"));
+        let cache = AssetLevelCache::default();
+        assert_eq!(
+            cache
+                .get(&github, &config(taxonomy.clone()), "api")
+                .await
+                .unwrap(),
+            Ok(taxonomy.tier("tier-2").unwrap())
+        );
+    }
+
+    #[test]
+    fn get_from_props_distinguishes_errors() {
+        assert_eq!(
+            AssetLevel::get_from_props(&level_props("Research & Development")),
+            Ok(AssetLevel::ResearchNDevelopment)
+        );
+        assert_eq!(
+            AssetLevel::get_from_props(&[]),
+            Err(AssetLevelError::Missing)
+        );
+        assert_eq!(
+            AssetLevel::get_from_props(&[CustomProperty {
+                property_name: "repository-level".to_string(),
+                value: None,
+            }]),
+            Err(AssetLevelError::Missing)
+        );
+        assert_eq!(
+            AssetLevel::get_from_props(&[CustomProperty {
+                property_name: "repository-level".to_string(),
+                value: Some(CustomPropertyValue::Array(vec![
+                    "Production".to_string(),
+                    "Corporate".to_string()
+                ])),
+            }]),
+            Err(AssetLevelError::MultiValued(vec![
+                "Production".to_string(),
+                "Corporate".to_string()
+            ]))
+        );
+        assert_eq!(
+            AssetLevel::get_from_props(&level_props("Prodution")),
+            Err(AssetLevelError::Unrecognized("Prodution".to_string()))
+        );
+    }
+
+    #[test]
+    fn asset_level_warning_is_repeated_daily_or_on_change() {
+        let warned_at = DateTime::from_timestamp(1700000000, 0).expect("valid timestamp");
+        let error = AssetLevelError::Unrecognized("Prodution".to_string());
+        let warning = AssetLevelWarning::new(&error, warned_at);
+
+        assert!(warning.covers(&error, warned_at + TimeDelta::hours(23)));
+        assert!(!warning.covers(&error, warned_at + TimeDelta::days(1)));
+        assert!(!warning.covers(
+            &AssetLevelError::Unrecognized("Prod".to_string()),
+            warned_at + TimeDelta::hours(1)
+        ));
+    }
 }
@@ -0,0 +1,207 @@
+//! Status checks of the commit pushed with a CI bypass.
+//!
+//! Bypassing `required_status_checks`, `required_deployments` or `workflows` rules lands code
+//! that CI has not vetted. The check runs of the resulting commit show which checks were failing
+//! or had not completed when the rules were bypassed.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// Rule types enforced by CI
+pub const CI_RULE_TYPES: [&str; 3] = [
+    "required_status_checks",
+    "required_deployments",
+    "workflows",
+];
+
+/// A check run of a commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckRun {
+    pub name: String,
+    /// `queued`, `in_progress` or `completed`, among others
+    pub status: String,
+    /// Only set once completed, e.g. `success` or `failure`
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub html_url: Option<String>,
+}
+
+impl CheckRun {
+    pub fn is_completed(&self) -> bool {
+        self.status == "completed"
+    }
+
+    /// Whether the check run completed without blocking a merge
+    pub fn is_successful(&self) -> bool {
+        self.is_completed()
+            && matches!(
+                self.conclusion.as_deref(),
+                Some("success" | "neutral" | "skipped")
+            )
+    }
+}
+
+/// A page of check runs, see
+/// https://docs.github.com/en/rest/checks/runs?apiVersion=2022-11-28#list-check-runs-for-a-git-reference
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckRunList {
+    pub total_count: u64,
+    pub check_runs: Vec<CheckRun>,
+}
+
+/// A commit status, reported by external CI systems instead of a check run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitStatus {
+    /// The name of the status, matched against required checks like a check run name
+    pub context: String,
+    /// `error`, `failure`, `pending` or `success`
+    pub state: String,
+    #[serde(default)]
+    pub target_url: Option<String>,
+}
+
+impl From<&CommitStatus> for CheckRun {
+    /// Pending statuses are not completed, all others are completed with their state as
+    /// conclusion.
+    fn from(status: &CommitStatus) -> Self {
+        let completed = status.state != "pending";
+        Self {
+            name: status.context.clone(),
+            status: if completed {
+                "completed"
+            } else {
+                "in_progress"
+            }
+            .to_string(),
+            conclusion: completed.then(|| status.state.clone()),
+            html_url: status.target_url.clone(),
+        }
+    }
+}
+
+/// A page of the combined status of a commit, see
+/// https://docs.github.com/en/rest/commits/statuses?apiVersion=2022-11-28#get-the-combined-status-for-a-specific-reference
+#[derive(Debug, Clone, Deserialize)]
+pub struct CombinedStatus {
+    pub total_count: u64,
+    pub statuses: Vec<CommitStatus>,
+}
+
+/// The checks that did not pass on the commit of a CI bypass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiChecks {
+    /// Check runs that completed unsuccessfully, e.g. `build (failure)`
+    pub failing: Vec<String>,
+    /// Required checks without a completed check run, e.g. still running or never started
+    pub missing: Vec<String>,
+}
+
+impl CiChecks {
+    /// Summarizes the check runs of a commit against the names of the required checks. Check
+    /// runs that are not required are only reported if they failed.
+    pub fn new(check_runs: &[CheckRun], required: &[String]) -> Self {
+        let mut failing = Vec::new();
+        for run in check_runs {
+            if run.is_completed() && !run.is_successful() {
+                let entry = format!(
+                    "{} ({})",
+                    run.name,
+                    run.conclusion.as_deref().unwrap_or("unknown")
+                );
+                if !failing.contains(&entry) {
+                    failing.push(entry);
+                }
+            }
+        }
+
+        let mut missing = Vec::new();
+        for name in required {
+            let completed = check_runs
+                .iter()
+                .any(|run| &run.name == name && run.is_completed());
+            if !completed && !missing.contains(name) {
+                missing.push(name.clone());
+            }
+        }
+
+        Self { failing, missing }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failing.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl Display for CiChecks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut parts = Vec::new();
+        if !self.failing.is_empty() {
+            parts.push(format!("Failing: {}", list(&self.failing)));
+        }
+        if !self.missing.is_empty() {
+            parts.push(format!("Missing: {}", list(&self.missing)));
+        }
+        if parts.is_empty() {
+            return f.write_str("All checks passed");
+        }
+        f.write_str(&parts.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{CheckRun, CheckRunList, CiChecks, CombinedStatus};
+
+    #[test]
+    fn ci_checks_from_check_runs() {
+        let json = fs::read_to_string("tests/fixtures/check_runs.json").expect("fixture exists");
+        let list: CheckRunList = serde_json::from_str(&json).expect("valid check runs");
+
+        let checks = CiChecks::new(
+            &list.check_runs,
+            &["build".to_string(), "e2e".to_string(), "lint".to_string()],
+        );
+        assert_eq!(checks.failing, ["build (failure)", "audit (timed_out)"]);
+        // `e2e` is still running and `lint` never started.
+        assert_eq!(checks.missing, ["e2e", "lint"]);
+        assert_eq!(
+            checks.to_string(),
+            "Failing: `build (failure)`, `audit (timed_out)`\nMissing: `e2e`, `lint`"
+        );
+
+        assert!(CiChecks::new(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn ci_checks_include_commit_statuses() {
+        let json = fs::read_to_string("tests/fixtures/check_runs.json").expect("fixture exists");
+        let list: CheckRunList = serde_json::from_str(&json).expect("valid check runs");
+        let json = fs::read_to_string("tests/fixtures/commit_status.json").expect("fixture exists");
+        let status: CombinedStatus = serde_json::from_str(&json).expect("valid commit status");
+
+        let mut check_runs = list.check_runs;
+        check_runs.extend(status.statuses.iter().map(CheckRun::from));
+        let checks = CiChecks::new(&check_runs, &["e2e".to_string(), "lint".to_string()]);
+        assert_eq!(
+            checks.failing,
+            [
+                "build (failure)",
+                "audit (timed_out)",
+                "netlify/deploy-preview (error)"
+            ]
+        );
+        // `lint` is reported as a commit status.
+        assert_eq!(checks.missing, ["e2e"]);
+    }
+}
@@ -0,0 +1,168 @@
+//! Who receives notifications besides the actor.
+//!
+//! Escalation contacts, e.g. the security reviewers, receive a copy of every notification. The
+//! fallback recipient is notified instead of the actor if the actor cannot be mapped to a Slack
+//! user. Contacts are Slack user ids, Slack user group ids or emails, which are looked up. A
+//! contact that cannot be resolved is skipped with a warning.
+
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use slack_morphism::SlackUserId;
+
+use crate::{SlackClient, soc2::asset_level::AssetTier};
+
+/// A Slack recipient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EscalationContact {
+    /// Looked up with [`SlackClient::get_user_by_email`]
+    Email(String),
+    /// A Slack user id, e.g. `U024BE7LH`
+    User(SlackUserId),
+    /// A Slack user group id, e.g. `S0614TZR7`. All members are notified.
+    UserGroup(String),
+}
+
+impl FromStr for EscalationContact {
+    type Err = anyhow::Error;
+
+    /// Parses an email, a user id (`U…` or `W…`) or a user group id (`S…`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('@') {
+            Ok(Self::Email(s.to_string()))
+        } else if s.starts_with('U') || s.starts_with('W') {
+            Ok(Self::User(SlackUserId(s.to_string())))
+        } else if s.starts_with('S') {
+            Ok(Self::UserGroup(s.to_string()))
+        } else {
+            Err(anyhow!(
+                "`{s}` is neither an email nor a Slack user or user group id"
+            ))
+        }
+    }
+}
+
+impl EscalationContact {
+    /// The Slack users to notify
+    pub async fn resolve(&self, slack: &dyn SlackClient) -> Result<Vec<SlackUserId>> {
+        Ok(match self {
+            Self::Email(email) => vec![slack.get_user_by_email(email).await?.id],
+            Self::User(id) => vec![id.clone()],
+            Self::UserGroup(id) => slack.get_user_group_members(id).await?,
+        })
+    }
+}
+
+/// Contacts replacing [`EscalationContacts::contacts`] for repositories of some asset levels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscalationOverride {
+    pub asset_levels: RangeInclusive<AssetTier>,
+    pub contacts: Vec<EscalationContact>,
+}
+
+/// Recipients of notifications besides the actor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EscalationContacts {
+    /// Receive a copy of every notification
+    pub contacts: Vec<EscalationContact>,
+    /// The first override containing the asset level of the repository replaces `contacts`
+    pub overrides: Vec<EscalationOverride>,
+    /// Notified instead of the actor if the actor cannot be mapped to a Slack user. Without a
+    /// fallback, only the contacts and the SOC2 channel are notified.
+    pub fallback: Option<EscalationContact>,
+}
+
+impl EscalationContacts {
+    /// The contacts of all asset levels, e.g. the security reviewers, without duplicates
+    pub fn all(&self) -> Vec<EscalationContact> {
+        let mut all = Vec::new();
+        for contact in self
+            .contacts
+            .iter()
+            .chain(self.overrides.iter().flat_map(|o| &o.contacts))
+        {
+            if !all.contains(contact) {
+                all.push(contact.clone());
+            }
+        }
+        all
+    }
+
+    /// The contacts for a repository of the asset level
+    pub fn for_asset_level(&self, asset_level: &AssetTier) -> &[EscalationContact] {
+        self.overrides
+            .iter()
+            .find(|o| o.asset_levels.contains(asset_level))
+            .map_or(&self.contacts, |o| &o.contacts)
+    }
+}
+
+/// Resolves the contacts to Slack users, in order and without duplicates. Contacts that cannot be
+/// resolved are skipped.
+pub async fn resolve_contacts(
+    slack: &dyn SlackClient,
+    contacts: &[EscalationContact],
+) -> Vec<SlackUserId> {
+    let mut users = Vec::new();
+    for contact in contacts {
+        match contact.resolve(slack).await {
+            Ok(resolved) => {
+                for user in resolved {
+                    if !users.contains(&user) {
+                        users.push(user);
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to resolve escalation contact {contact:?}: {e:#}"),
+        }
+    }
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use slack_morphism::SlackUserId;
+
+    use super::{EscalationContact, EscalationContacts, EscalationOverride};
+    use crate::soc2::asset_level::{AssetLevel, AssetTier};
+
+    #[test]
+    fn escalation_contacts_per_asset_level() {
+        let security: EscalationContact = "security@zoo.dev".parse().expect("valid contact");
+        assert_eq!(
+            security,
+            EscalationContact::Email("security@zoo.dev".to_string())
+        );
+        let reviewers: EscalationContact = "S0614TZR7".parse().expect("valid contact");
+        assert_eq!(
+            reviewers,
+            EscalationContact::UserGroup("S0614TZR7".to_string())
+        );
+        assert_eq!(
+            "U024BE7LH"
+                .parse::<EscalationContact>()
+                .expect("valid contact"),
+            EscalationContact::User(SlackUserId("U024BE7LH".to_string()))
+        );
+        assert!("#soc2".parse::<EscalationContact>().is_err());
+
+        let contacts = EscalationContacts {
+            contacts: vec![security.clone()],
+            overrides: vec![EscalationOverride {
+                asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+                contacts: vec![security.clone(), reviewers.clone()],
+            }],
+            fallback: None,
+        };
+        assert_eq!(contacts.all(), [security.clone(), reviewers.clone()]);
+        assert_eq!(
+            contacts.for_asset_level(&AssetTier::from(AssetLevel::Production)),
+            [security.clone(), reviewers]
+        );
+        assert_eq!(
+            contacts.for_asset_level(&AssetTier::from(AssetLevel::Playground)),
+            [security]
+        );
+    }
+}
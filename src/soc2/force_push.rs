@@ -0,0 +1,178 @@
+//! Commits discarded by force pushes.
+//!
+//! Bypassing a `non_fast_forward` rule rewrites the history of the ref. Comparing the pushed
+//! commit (`after_sha`) with the previous head (`before_sha`) lists the commits that were reachable
+//! before the push but no longer are.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::soc2::retroactive_review::GitHubAccount;
+
+/// The most commits linked in a notification. The count covers all discarded commits, the
+/// authors only the listed ones.
+const MAX_LINKED_COMMITS: usize = 10;
+
+/// A comparison of two commits, see
+/// https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comparison {
+    /// `ahead`, `behind`, `diverged` or `identical`
+    pub status: String,
+    /// Commits of the head that are not reachable from the base
+    pub ahead_by: u64,
+    /// The first page of the commits of the head that are not reachable from the base, oldest
+    /// first. [`crate::GitHubApi::compare_commits`] requests up to 100 of them.
+    #[serde(default)]
+    pub commits: Vec<ComparedCommit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComparedCommit {
+    pub sha: String,
+    pub html_url: String,
+    pub commit: GitCommit,
+    /// The GitHub account of the author, if the author email belongs to one
+    #[serde(default)]
+    pub author: Option<GitHubAccount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitCommit {
+    #[serde(default)]
+    pub author: Option<GitAuthor>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitAuthor {
+    pub name: String,
+}
+
+/// A commit that is no longer part of the history of the ref
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscardedCommit {
+    pub sha: String,
+    /// The GitHub login of the author, or the git author name
+    pub author: String,
+    pub url: String,
+}
+
+/// The commits discarded by a force push
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscardedCommits {
+    /// The number of discarded commits, may exceed the listed commits
+    pub count: u64,
+    /// The listed discarded commits, oldest first
+    pub commits: Vec<DiscardedCommit>,
+}
+
+impl DiscardedCommits {
+    /// Takes the discarded commits from the comparison of the pushed commit as base and the
+    /// previous head as head.
+    pub fn from_comparison(comparison: &Comparison) -> Self {
+        let commits = comparison
+            .commits
+            .iter()
+            .map(|commit| DiscardedCommit {
+                sha: commit.sha.clone(),
+                author: commit
+                    .author
+                    .as_ref()
+                    .map(|account| account.login.clone())
+                    .or_else(|| commit.commit.author.as_ref().map(|git| git.name.clone()))
+                    .unwrap_or_else(|| "unknown".to_string()),
+                url: commit.html_url.clone(),
+            })
+            .collect();
+
+        Self {
+            count: comparison.ahead_by,
+            commits,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The unique authors of the listed commits, in order of first occurrence
+    pub fn authors(&self) -> Vec<&str> {
+        let mut authors = Vec::new();
+        for commit in &self.commits {
+            if !authors.contains(&commit.author.as_str()) {
+                authors.push(commit.author.as_str());
+            }
+        }
+        authors
+    }
+}
+
+impl Display for DiscardedCommits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("No commits were discarded");
+        }
+
+        let authors = self
+            .authors()
+            .iter()
+            .map(|author| format!("`{author}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut links: Vec<String> = self
+            .commits
+            .iter()
+            .take(MAX_LINKED_COMMITS)
+            .map(|commit| {
+                format!(
+                    "[`{}`]({})",
+                    commit.sha.get(..7).unwrap_or(&commit.sha),
+                    commit.url
+                )
+            })
+            .collect();
+        let unlinked = self.count.saturating_sub(links.len() as u64);
+        if unlinked > 0 {
+            links.push(format!("and {unlinked} more"));
+        }
+
+        write!(
+            f,
+            "{} commit(s) by {authors}: {}",
+            self.count,
+            links.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Comparison, DiscardedCommits};
+
+    #[test]
+    fn discarded_commits_from_comparison() {
+        let json = fs::read_to_string("tests/fixtures/compare_force_push.json").expect("fixture");
+        let comparison: Comparison = serde_json::from_str(&json).expect("valid comparison");
+
+        let discarded = DiscardedCommits::from_comparison(&comparison);
+        assert_eq!(discarded.count, 3);
+        // The last commit has no GitHub account.
+        assert_eq!(discarded.authors(), ["jessfraz", "Jane Doe"]);
+        assert_eq!(
+            discarded.to_string(),
+            "3 commit(s) by `jessfraz`, `Jane Doe`: \
+             [`4a1f6e2`](https://github.com/KittyCAD/modeling-app/commit/4a1f6e2c9b0d8e7f6a5b4c3d2e1f0a9b8c7d6e5f), \
+             [`7b2c8d3`](https://github.com/KittyCAD/modeling-app/commit/7b2c8d3e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c), \
+             [`9e3d7c1`](https://github.com/KittyCAD/modeling-app/commit/9e3d7c1b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e0d)"
+        );
+
+        assert_eq!(
+            DiscardedCommits::default().to_string(),
+            "No commits were discarded"
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Justifications of bypasses given in Slack threads.
+//!
+//! The actor is asked to explain a critical bypass by replying in the thread of the notification,
+//! or the fallback recipients if the actor is unknown to Slack. The Slack messages of each
+//! notified rule suite event are stored, and their threads are read until one of them replied,
+//! the bypass was escalated, or [`JUSTIFICATION_WINDOW`] passed. The first reply is recorded as
+//! the justification.
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use slack_morphism::{SlackTs, SlackUserId};
+
+use crate::{
+    BotConfig, GithubRuleSuiteEvent, PostedMessage, RulesetBot, SlackClient, SlackThreadReply,
+    soc2::{
+        action::{ActionKind, has_action},
+        actor_recipients,
+        policy::PolicyDecision,
+        reminder::{ReminderConfig, ReminderStatus},
+        rule_suit::RuleSuite,
+    },
+};
+
+/// How long after the notification the threads are read if [`BotConfig::reminders`] are disabled
+pub const JUSTIFICATION_WINDOW: TimeDelta = TimeDelta::days(7);
+
+/// The explanation of a bypass by its actor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Justification {
+    pub text: String,
+    pub user: SlackUserId,
+    /// The reply in the thread of the notification
+    pub message: PostedMessage,
+    /// When the reply was posted
+    pub justified_at: DateTime<Utc>,
+}
+
+/// Records the justifications of critical rule suite events whose actor, or fallback recipient,
+/// replied to a notification. `repository_full_name` limits the events to a repository, otherwise events
+/// across the organization are reconciled.
+pub async fn reconcile_justifications(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    repository_full_name: Option<&str>,
+) -> Result<()> {
+    let unjustified = bot
+        .find_unjustified_rule_suites(repository_full_name)
+        .await?;
+    for event in unjustified {
+        if let Err(e) = reconcile_justification(bot, config, slack, &event).await {
+            tracing::warn!(
+                "Failed to reconcile the justification of rule suite ID {}: {e:#}",
+                event.github_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn reconcile_justification(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    event: &GithubRuleSuiteEvent,
+) -> Result<()> {
+    // Only the actors of critical bypasses are asked for a justification, unless security marked
+    // the bypass as false positive
+    let decision: PolicyDecision = serde_json::from_str(
+        event
+            .decision
+            .as_deref()
+            .context("rule suite event has no decision")?,
+    )?;
+    if !decision.critical || has_action(event, ActionKind::FalsePositive)? {
+        return Ok(());
+    }
+
+    let messages: Vec<PostedMessage> = serde_json::from_str(
+        event
+            .slack_messages
+            .as_deref()
+            .context("rule suite event has no Slack messages")?,
+    )?;
+    let Some(first) = messages.first() else {
+        return Ok(());
+    };
+    let status = event
+        .reminder
+        .as_deref()
+        .map(serde_json::from_str::<ReminderStatus>)
+        .transpose()?;
+    if !awaits_justification(
+        config.reminders.as_ref(),
+        ts_to_datetime(&first.ts)?,
+        status.as_ref(),
+        Utc::now(),
+    ) {
+        return Ok(());
+    }
+
+    let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+    // Like the notification, the request for a justification goes to the fallback recipient if
+    // the actor is unknown to Slack. Without any recipient, nobody was asked.
+    let recipients = actor_recipients(&suite, slack, bot, config).await.users;
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let mut justification: Option<Justification> = None;
+    for message in &messages {
+        let replies = slack.get_thread_replies(message).await?;
+        let Some(reply) = first_reply_by(&replies, message, &recipients) else {
+            continue;
+        };
+        let Some(user) = &reply.user else {
+            continue;
+        };
+        let justified_at = ts_to_datetime(&reply.ts)?;
+        if justification
+            .as_ref()
+            .is_none_or(|earlier| justified_at < earlier.justified_at)
+        {
+            justification = Some(Justification {
+                text: reply.text.clone(),
+                user: user.clone(),
+                message: PostedMessage {
+                    channel: message.channel.clone(),
+                    ts: reply.ts.clone(),
+                },
+                justified_at,
+            });
+        }
+    }
+
+    let Some(justification) = justification else {
+        return Ok(());
+    };
+    tracing::info!(
+        "Rule suite {} was justified by {} at {}",
+        suite.id,
+        justification.user,
+        justification.justified_at
+    );
+    bot.set_rule_suite_justification(event.id, serde_json::to_string(&justification)?)
+        .await
+}
+
+/// Whether the justification of a bypass notified at `notified_at` is still awaited at `now`. With
+/// reminders, it is awaited until the bypass is escalated, otherwise for [`JUSTIFICATION_WINDOW`].
+pub fn awaits_justification(
+    reminders: Option<&ReminderConfig>,
+    notified_at: DateTime<Utc>,
+    status: Option<&ReminderStatus>,
+    now: DateTime<Utc>,
+) -> bool {
+    match reminders {
+        Some(_) => !matches!(status, Some(ReminderStatus::Escalated { .. })),
+        None => now < notified_at + JUSTIFICATION_WINDOW,
+    }
+}
+
+/// The first non-empty reply of one of `users` in the thread, excluding the message itself
+pub fn first_reply_by<'a>(
+    replies: &'a [SlackThreadReply],
+    message: &PostedMessage,
+    users: &[SlackUserId],
+) -> Option<&'a SlackThreadReply> {
+    replies.iter().find(|reply| {
+        reply.ts != message.ts
+            && reply.user.as_ref().is_some_and(|user| users.contains(user))
+            && !reply.text.trim().is_empty()
+    })
+}
+
+/// Converts a Slack message timestamp, e.g. `1700000000.123456`, to the time it was posted
+pub fn ts_to_datetime(ts: &SlackTs) -> Result<DateTime<Utc>> {
+    let (secs, micros) = ts.0.split_once('.').unwrap_or((&ts.0, "0"));
+    let secs: i64 = secs
+        .parse()
+        .with_context(|| format!("invalid Slack timestamp `{ts}`"))?;
+    let micros: u32 = format!("{micros:0<6}")
+        .get(..6)
+        .and_then(|micros| micros.parse().ok())
+        .with_context(|| format!("invalid Slack timestamp `{ts}`"))?;
+    DateTime::from_timestamp(secs, micros * 1000)
+        .ok_or_else(|| anyhow!("Slack timestamp `{ts}` is out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta};
+    use slack_morphism::{SlackChannelId, SlackTs, SlackUserId};
+
+    use super::{JUSTIFICATION_WINDOW, awaits_justification, first_reply_by, ts_to_datetime};
+    use crate::{
+        PostedMessage, SlackThreadReply,
+        soc2::reminder::{ReminderConfig, ReminderStatus},
+    };
+
+    #[test]
+    fn first_reply_of_the_actor() {
+        let actor = [SlackUserId("U024BE7LH".to_string())];
+        let message = PostedMessage {
+            channel: SlackChannelId("D024BFF1M".to_string()),
+            ts: SlackTs("1700000000.000100".to_string()),
+        };
+        let reply = |user: Option<&str>, text: &str, ts: &str| SlackThreadReply {
+            user: user.map(|user| SlackUserId(user.to_string())),
+            text: text.to_string(),
+            ts: SlackTs(ts.to_string()),
+        };
+        let replies = [
+            // The notification itself
+            reply(Some("U024BE7LH"), "Bypass Detected", "1700000000.000100"),
+            reply(None, "Please reply", "1700000060.000000"),
+            reply(Some("U0SEC0001"), "Why?", "1700000120.000000"),
+            reply(Some("U024BE7LH"), " ", "1700000180.000000"),
+            reply(
+                Some("U024BE7LH"),
+                "Hotfix for the outage",
+                "1700000240.500000",
+            ),
+            reply(Some("U024BE7LH"), "See INC-42", "1700000300.000000"),
+        ];
+
+        let first = first_reply_by(&replies, &message, &actor).expect("reply of the actor");
+        assert_eq!(first.text, "Hotfix for the outage");
+        assert_eq!(
+            ts_to_datetime(&first.ts).expect("valid ts").to_rfc3339(),
+            "2023-11-14T22:17:20.500+00:00"
+        );
+        assert!(first_reply_by(&replies[..4], &message, &actor).is_none());
+
+        // The reply of any of the fallback recipients
+        let fallback = [
+            SlackUserId("U0SEC0001".to_string()),
+            SlackUserId("U0SEC0002".to_string()),
+        ];
+        let first = first_reply_by(&replies, &message, &fallback).expect("reply of the fallback");
+        assert_eq!(first.text, "Why?");
+
+        assert!(ts_to_datetime(&SlackTs("yesterday".to_string())).is_err());
+        assert_eq!(
+            ts_to_datetime(&SlackTs("1700000000".to_string()))
+                .expect("valid ts")
+                .timestamp(),
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn justification_is_awaited_until_escalation_or_window() {
+        let notified_at = DateTime::from_timestamp(1_700_000_000, 0).expect("valid timestamp");
+        let reminded_at = notified_at + TimeDelta::days(1);
+        let reminded = ReminderStatus::Reminded { reminded_at };
+        let escalated = ReminderStatus::Escalated {
+            reminded_at,
+            escalated_at: reminded_at + TimeDelta::days(2),
+        };
+        let reminders = ReminderConfig::default();
+
+        // With reminders, until the bypass is escalated
+        let later = notified_at + TimeDelta::days(30);
+        assert!(awaits_justification(
+            Some(&reminders),
+            notified_at,
+            None,
+            later
+        ));
+        assert!(awaits_justification(
+            Some(&reminders),
+            notified_at,
+            Some(&reminded),
+            later
+        ));
+        assert!(!awaits_justification(
+            Some(&reminders),
+            notified_at,
+            Some(&escalated),
+            later
+        ));
+
+        // Without reminders, within the window
+        let within = notified_at + JUSTIFICATION_WINDOW - TimeDelta::seconds(1);
+        assert!(awaits_justification(None, notified_at, None, within));
+        assert!(!awaits_justification(
+            None,
+            notified_at,
+            None,
+            notified_at + JUSTIFICATION_WINDOW
+        ));
+    }
+}
@@ -1,22 +1,53 @@
+pub mod action;
 pub mod asset_level;
+pub mod ci;
+pub mod escalation;
+pub mod force_push;
+pub mod justification;
+pub mod policy;
+pub mod ref_filter;
+pub mod reminder;
+pub mod retroactive_review;
 pub mod rule_suit;
+pub mod ruleset;
+pub mod shadow;
+pub mod trusted_actor;
+pub mod waiver;
+
+use std::collections::BTreeMap;
 
 use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
 use octocrab::{
-    Octocrab, Page,
-    commits::PullRequestTarget,
+    Octocrab,
     models::{AppId, InstallationId, pulls::PullRequest, repos::RepoCommit},
 };
-use slack_morphism::SlackChannelId;
+use slack_morphism::prelude::*;
 
 use crate::{
-    BotConfig, GitHubAuth, NewGithubRuleSuiteEvent, RulesetBot, SlackClient,
+    BotConfig, GitHubApi, GitHubAuth, NewGithubRuleSuiteEvent, PostedMessage, RuleSuiteFilter,
+    RulesetBot, SYNC_SAFETY_WINDOW, SlackClient, SyncCursor,
+    notify::{NOTIFIER_TIMEOUT, slack::build_slack_message},
     soc2::{
-        asset_level::{AssetLevel, CustomPropertyExt},
-        rule_suit::{RuleOutcome, RuleSuite},
+        action::with_action_buttons,
+        asset_level::{AssetLevelCache, AssetLevelError, AssetLevelWarning, AssetTier},
+        ci::{CheckRun, CiChecks},
+        escalation::{EscalationContact, resolve_contacts},
+        force_push::DiscardedCommits,
+        justification::reconcile_justifications,
+        policy::{PolicyDecision, PolicyInput},
+        reminder::remind_unjustified,
+        retroactive_review::{
+            ReviewStatus, check_retroactive_reviews, requires_retroactive_review,
+        },
+        rule_suit::{RuleOutcome, RuleSuite, RuleSuiteQuery, TimePeriod},
+        ruleset::DeletedRuleset,
+        trusted_actor::is_trusted,
     },
 };
 
+/// Fetches and evaluates the rule suites of a repository using an [`Octocrab`] client created
+/// from [`BotConfig::github_auth`].
 #[tracing::instrument(skip(bot, config, slack))]
 pub async fn process_rule_suites(
     bot: &dyn RulesetBot,
@@ -27,26 +58,197 @@ pub async fn process_rule_suites(
 ) -> anyhow::Result<()> {
     let octocrab = create_octocrab(config)?;
 
-    update_rule_suites(
+    process_rule_suites_with_github(
         bot,
         config,
+        slack,
         &octocrab,
+        &AssetLevelCache::default(),
         repository_full_name,
         repository_name,
     )
-    .await?;
+    .await
+}
+
+/// Like [`process_rule_suites`], but talks to GitHub through the given [`GitHubApi`] and keeps
+/// asset levels in the given cache across calls. Fails if the config is invalid, see
+/// [`BotConfig::validate`].
+///
+/// Afterwards, bypasses of the repository awaiting a retroactive review are checked.
+#[tracing::instrument(skip(bot, config, slack, github, asset_levels))]
+pub async fn process_rule_suites_with_github(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    repository_full_name: &str,
+    repository_name: &str,
+) -> anyhow::Result<()> {
+    config.validate()?;
+    update_rule_suites(bot, config, github, repository_full_name, repository_name).await?;
     evaluate_rule_suites(
         bot,
         config,
         slack,
-        &octocrab,
+        github,
+        asset_levels,
         repository_full_name,
         repository_name,
     )
     .await?;
+    check_retroactive_reviews(bot, config, slack, github, Some(repository_full_name)).await?;
+    reconcile_justifications(bot, config, slack, Some(repository_full_name)).await?;
+    remind_unjustified(bot, config, slack, Some(repository_full_name)).await?;
     Ok(())
 }
 
+/// Fetches the rule suites of all repositories in [`BotConfig::github_org`] in one pass and
+/// evaluates them per repository. Uses an [`Octocrab`] client created from
+/// [`BotConfig::github_auth`].
+#[tracing::instrument(skip(bot, config, slack))]
+pub async fn process_org_rule_suites(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+) -> anyhow::Result<()> {
+    let octocrab = create_octocrab(config)?;
+
+    process_org_rule_suites_with_github(bot, config, slack, &octocrab, &AssetLevelCache::default())
+        .await
+}
+
+/// Like [`process_org_rule_suites`], but talks to GitHub through the given [`GitHubApi`] and keeps
+/// asset levels in the given cache across calls. Fails if the config is invalid, see
+/// [`BotConfig::validate`].
+///
+/// Only repositories with rule suites newer than their sync cursor are evaluated. Their asset
+/// levels are fetched for the whole organization at once. A failure in one repository does not
+/// stop the sweep, failures are reported once all repositories have been processed. Bypasses of
+/// all repositories awaiting a retroactive review are checked afterwards.
+#[tracing::instrument(skip(bot, config, slack, github, asset_levels))]
+pub async fn process_org_rule_suites_with_github(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+) -> anyhow::Result<()> {
+    config.validate()?;
+    let github_org = &config.github_org;
+
+    let listed_at = Utc::now();
+    let rule_suites = list_rule_suites(
+        github,
+        &config.rule_suite_filter,
+        RuleSuiteSource::Organization(github_org),
+        None,
+    )
+    .await?;
+
+    // Group by repository, keeping the newest first order.
+    let mut rule_suites_by_repo: BTreeMap<String, Vec<RuleSuite>> = BTreeMap::new();
+    for suite in rule_suites {
+        rule_suites_by_repo
+            .entry(suite.repository_name.clone())
+            .or_default()
+            .push(suite);
+    }
+
+    if !rule_suites_by_repo.is_empty() {
+        asset_levels.refresh_org(github, config).await?;
+    }
+
+    let mut failed_repositories = Vec::new();
+
+    for (repository_name, rule_suites) in rule_suites_by_repo {
+        let repository_full_name = format!("{github_org}/{repository_name}");
+
+        if let Err(e) = process_listed_rule_suites(
+            bot,
+            config,
+            slack,
+            github,
+            asset_levels,
+            &repository_full_name,
+            &repository_name,
+            listed_at,
+            rule_suites,
+        )
+        .await
+        {
+            tracing::warn!("Failed to process rule suites of {repository_full_name}: {e:#}");
+            failed_repositories.push(repository_full_name);
+        }
+    }
+
+    check_retroactive_reviews(bot, config, slack, github, None).await?;
+    reconcile_justifications(bot, config, slack, None).await?;
+    remind_unjustified(bot, config, slack, None).await?;
+
+    if !failed_repositories.is_empty() {
+        return Err(anyhow!(
+            "processing rule suites failed for {}",
+            failed_repositories.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Ingests the already listed rule suites of a repository that are not behind the safety window of
+/// its sync cursor and evaluates the repository. Rule suites left unnotified by an earlier run, e.g.
+/// because Slack failed, are evaluated even if no new rule suite was listed.
+#[allow(clippy::too_many_arguments)]
+async fn process_listed_rule_suites(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    repository_full_name: &str,
+    repository_name: &str,
+    listed_at: DateTime<Utc>,
+    rule_suites: Vec<RuleSuite>,
+) -> anyhow::Result<()> {
+    let cursor = bot.get_sync_cursor(repository_full_name).await?;
+    let rule_suites: Vec<RuleSuite> = rule_suites
+        .into_iter()
+        .filter(|suite| cursor.is_none_or(|cursor| !cursor.is_behind_window(suite)))
+        .collect();
+
+    ingest_rule_suites(
+        bot,
+        config,
+        github,
+        repository_full_name,
+        repository_name,
+        cursor,
+        listed_at,
+        rule_suites,
+    )
+    .await?;
+
+    if bot
+        .find_unnotified_rule_suites(repository_full_name)
+        .await?
+        .is_empty()
+    {
+        return Ok(());
+    }
+
+    evaluate_rule_suites(
+        bot,
+        config,
+        slack,
+        github,
+        asset_levels,
+        repository_full_name,
+        repository_name,
+    )
+    .await
+}
+
 pub fn create_octocrab(config: &BotConfig) -> Result<Octocrab> {
     let octocrab = match &config.github_auth {
         GitHubAuth::Installation(auth_context) => {
@@ -69,119 +271,428 @@ pub fn create_octocrab(config: &BotConfig) -> Result<Octocrab> {
     Ok(octocrab)
 }
 
-#[tracing::instrument(skip(bot, config, octocrab))]
+#[tracing::instrument(skip(bot, config, github))]
 async fn update_rule_suites(
     bot: &dyn RulesetBot,
     config: &BotConfig,
-    octocrab: &Octocrab,
+    github: &dyn GitHubApi,
     repository_full_name: &str,
     repository_name: &str,
 ) -> anyhow::Result<()> {
     // Update rule suites in the DB
     // We are hoping here that the rule suites are already available via the API. If not they will get fetched with the next repo event.
 
+    let cursor = bot.get_sync_cursor(repository_full_name).await?;
+    let listed_at = Utc::now();
+    let rule_suites = list_rule_suites(
+        github,
+        &config.rule_suite_filter,
+        RuleSuiteSource::Repository(repository_full_name),
+        cursor.as_ref(),
+    )
+    .await?;
+
+    ingest_rule_suites(
+        bot,
+        config,
+        github,
+        repository_full_name,
+        repository_name,
+        cursor,
+        listed_at,
+        rule_suites,
+    )
+    .await
+}
+
+/// Stores the given rule suites (newest first) and advances the sync cursor of the repository.
+/// `listed_at` is when listing the rule suites started, it becomes the [`SyncCursor::synced_at`]
+/// if all of them were ingested.
+#[allow(clippy::too_many_arguments)]
+async fn ingest_rule_suites(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    github: &dyn GitHubApi,
+    repository_full_name: &str,
+    repository_name: &str,
+    cursor: Option<SyncCursor>,
+    listed_at: DateTime<Utc>,
+    rule_suites: Vec<RuleSuite>,
+) -> anyhow::Result<()> {
+    let mut high_water_mark = cursor;
+    let mut ingestion_failed = false;
+
+    if !rule_suites.is_empty() {
+        let default_branch = resolve_default_branch(
+            github,
+            config,
+            repository_name,
+            config.ref_filter.uses_default_branch(),
+        )
+        .await?;
+
+        // Process each rule suite, oldest first. The cursor must not move past a suite that failed
+        // to be ingested, otherwise it would never be retried. Suites listed again within the
+        // safety window are older than the cursor and leave it in place.
+        for suite in rule_suites.into_iter().rev() {
+            let suite_cursor = SyncCursor::new(&suite, listed_at);
+            let is_newer = high_water_mark.is_none_or(|mark| !mark.covers(&suite));
+            let suite_id = suite.id;
+
+            if let Err(e) = ingest_rule_suite(
+                bot,
+                config,
+                github,
+                repository_full_name,
+                repository_name,
+                default_branch.as_deref(),
+                suite,
+            )
+            .await
+            {
+                tracing::warn!("Failed to ingest rule suite ID {suite_id}: {e:#}");
+                ingestion_failed = true;
+                continue;
+            }
+
+            if !ingestion_failed && is_newer {
+                high_water_mark = Some(suite_cursor);
+            }
+        }
+    }
+
+    let Some(mut high_water_mark) = high_water_mark else {
+        return Ok(());
+    };
+    // An incomplete sync must be listed again from the previous one on.
+    high_water_mark.synced_at = match cursor {
+        _ if !ingestion_failed => listed_at,
+        Some(cursor) => cursor.synced_at,
+        None => high_water_mark.pushed_at,
+    };
+
+    if cursor != Some(high_water_mark) {
+        bot.set_sync_cursor(repository_full_name, high_water_mark)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Stores a single bypass rule suite together with its resulting commit and pull requests.
+async fn ingest_rule_suite(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    github: &dyn GitHubApi,
+    repository_full_name: &str,
+    repository_name: &str,
+    default_branch: Option<&str>,
+    suite: RuleSuite,
+) -> anyhow::Result<()> {
     let github_org = &config.github_org;
 
-    // https://docs.github.com/en/rest/repos/rule-suites?apiVersion=2022-11-28#list-repository-rule-suites
-    let url = format!("/repos/{repository_full_name}/rulesets/rule-suites");
-    let rule_suites: Vec<RuleSuite> = octocrab
-        .get(url, None::<&()>)
+    if suite.result != RuleOutcome::Bypass {
+        return Ok(());
+    }
+
+    if !config.ref_filter.matches(&suite.ref_name, default_branch) {
+        tracing::debug!(
+            "Skipping rule suite {} on filtered ref {}",
+            suite.id,
+            suite.ref_name
+        );
+        return Ok(());
+    }
+
+    // Insert rule suite if id does not yet exist.
+    let lookup = bot
+        .find_rule_suite_by_github_id(&suite.id.to_string())
+        .await?;
+    if lookup.is_some() {
+        return Ok(());
+    }
+
+    let full_result = github
+        .get_rule_suite(repository_full_name, suite.id)
+        .await?;
+
+    // Skip bypasses by trusted automation within its scope. Bypasses by other bots are recorded
+    // like any other bypass.
+    if is_trusted(
+        &config.trusted_actors,
+        full_result.actor_id,
+        full_result.actor_name.as_deref(),
+        &full_result,
+    ) {
+        tracing::debug!(
+            "Skipping rule suite {} bypassed by trusted actor {:?}",
+            full_result.id,
+            full_result.actor_name
+        );
+        return Ok(());
+    }
+
+    let resulting_commit = github
+        .get_commit(github_org, repository_name, &full_result.after_sha)
         .await
-        .context("unable to fetch rule suites")?;
-    // Process each rule suite.
-    for suite in rule_suites {
-        if suite.result != RuleOutcome::Bypass {
-            continue;
+        .ok();
+
+    let prs = github
+        .list_associated_pull_requests(github_org, repository_name, &full_result.after_sha)
+        .await
+        .ok();
+
+    let ci_checks = if full_result.any(|eval| eval.is_failed() && eval.is_ci_rule()) {
+        fetch_ci_checks(
+            github,
+            repository_full_name,
+            github_org,
+            repository_name,
+            &full_result,
+        )
+        .await
+    } else {
+        None
+    };
+
+    let discarded_commits = if full_result.any(|eval| eval.is_failed() && eval.is_force_push_rule())
+    {
+        fetch_discarded_commits(
+            github,
+            repository_full_name,
+            github_org,
+            repository_name,
+            &full_result,
+        )
+        .await
+    } else {
+        None
+    };
+
+    bot.create_rule_suite_event(NewGithubRuleSuiteEvent {
+        github_id: suite.id.to_string(),
+        repository_full_name: repository_full_name.to_string(),
+        event_data: serde_json::to_string(&full_result)?,
+        resulting_commit: resulting_commit
+            .and_then(|repo_commit| serde_json::to_string(&repo_commit).ok()),
+        prs: prs.and_then(|prs| serde_json::to_string(&prs).ok()),
+        ci_checks: ci_checks.and_then(|ci_checks| serde_json::to_string(&ci_checks).ok()),
+        discarded_commits: discarded_commits
+            .and_then(|discarded_commits| serde_json::to_string(&discarded_commits).ok()),
+        notified: false,
+    })
+    .await
+    .context("unable to create rule suite event")
+}
+
+/// Collects the failing and missing checks of the commit pushed with a CI bypass. The required
+/// checks are taken from the rulesets of the bypassed `required_status_checks` rules. Like the
+/// resulting commit, the checks are best effort and `None` if the check runs cannot be fetched.
+async fn fetch_ci_checks(
+    github: &dyn GitHubApi,
+    repository_full_name: &str,
+    owner: &str,
+    repo: &str,
+    suite: &RuleSuite,
+) -> Option<CiChecks> {
+    let mut check_runs = match github.list_check_runs(owner, repo, &suite.after_sha).await {
+        Ok(check_runs) => check_runs,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to fetch check runs of {} in {repository_full_name}: {e:#}",
+                suite.after_sha
+            );
+            return None;
         }
+    };
+    // Required checks can also be satisfied by commit statuses of external CI systems.
+    match github
+        .list_commit_statuses(owner, repo, &suite.after_sha)
+        .await
+    {
+        Ok(statuses) => check_runs.extend(statuses.iter().map(CheckRun::from)),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to fetch commit statuses of {} in {repository_full_name}: {e:#}",
+                suite.after_sha
+            );
+            return None;
+        }
+    }
 
-        // Skip rule suites created by bots. Some bots in our org can bypass and commit directly to main.
-        if let Some(actor) = suite.actor_name
-            && actor.contains("[bot]")
-        {
+    let mut required = Vec::new();
+    for eval in suite.rule_evaluations.iter().flatten() {
+        if !(eval.is_failed() && eval.rule_type == "required_status_checks") {
             continue;
         }
+        let Some(ruleset_id) = eval.rule_source.id else {
+            continue;
+        };
+        match github.get_ruleset(repository_full_name, ruleset_id).await {
+            Ok(ruleset) => required.extend(ruleset.required_status_checks()),
+            Err(e) => tracing::warn!("Failed to fetch ruleset {ruleset_id}: {e:#}"),
+        }
+    }
 
-        let Ok(full_result): octocrab::Result<RuleSuite> = octocrab
-            .get(
-                format!(
-                    "/repos/{}/rulesets/rule-suites/{}",
-                    repository_full_name, suite.id
-                ),
-                None::<&()>,
-            )
-            .await
-        else {
+    Some(CiChecks::new(&check_runs, &required))
+}
+
+/// Lists the commits discarded by a force push by comparing the pushed commit with the previous
+/// head. Best effort like the CI checks, `None` if the comparison fails, e.g. because the
+/// discarded commits have been garbage collected.
+async fn fetch_discarded_commits(
+    github: &dyn GitHubApi,
+    repository_full_name: &str,
+    owner: &str,
+    repo: &str,
+    suite: &RuleSuite,
+) -> Option<DiscardedCommits> {
+    // The ref did not exist before the push.
+    if suite.before_sha.chars().all(|c| c == '0') {
+        return None;
+    }
+
+    match github
+        .compare_commits(owner, repo, &suite.after_sha, &suite.before_sha)
+        .await
+    {
+        Ok(comparison) => Some(DiscardedCommits::from_comparison(&comparison)),
+        Err(e) => {
             tracing::warn!(
-                "Failed to fetch full rule suite data for suite ID {}",
-                suite.id
+                "Failed to compare {} with {} in {repository_full_name}: {e:#}",
+                suite.after_sha,
+                suite.before_sha
             );
-            continue;
-        };
+            None
+        }
+    }
+}
 
-        let resulting_commit = octocrab
-            .commits(github_org, repository_name)
-            .get(&full_result.after_sha)
-            .await
-            .ok();
+/// Resolves the default branch of a repository if `needed`. Fails rather than skipping bypasses
+/// on the default branch, so the rule suites are retried.
+async fn resolve_default_branch(
+    github: &dyn GitHubApi,
+    config: &BotConfig,
+    repository_name: &str,
+    needed: bool,
+) -> Result<Option<String>> {
+    if !needed {
+        return Ok(None);
+    }
 
-        let prs: Option<Vec<PullRequest>> = octocrab
-            .commits(github_org, repository_name)
-            .associated_pull_requests(PullRequestTarget::Sha(full_result.after_sha.clone()))
-            .send()
-            .await
-            .map(|page: Page<PullRequest>| page.items)
-            .ok();
+    github
+        .get_default_branch(&config.github_org, repository_name)
+        .await
+        .map(Some)
+        .with_context(|| format!("unable to resolve the default branch of {repository_name}"))
+}
 
-        // Insert rule suite if id does not yet exist.
-        let Ok(lookup) = bot
-            .find_rule_suite_by_github_id(&suite.id.to_string())
-            .await
-        else {
-            continue;
-        };
+/// Where to list rule suites from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RuleSuiteSource<'a> {
+    /// A single repository, by full name
+    Repository(&'a str),
+    /// All repositories of an organization
+    Organization(&'a str),
+}
 
-        if lookup.is_none()
-            && let Err(e) = bot
-                .create_rule_suite_event(NewGithubRuleSuiteEvent {
-                    github_id: suite.id.to_string(),
-                    repository_full_name: repository_full_name.to_string(),
-                    event_data: serde_json::to_string(&full_result)?,
-                    resulting_commit: resulting_commit
-                        .and_then(|repo_commit| serde_json::to_string(&repo_commit).ok()),
-                    prs: prs.and_then(|prs| serde_json::to_string(&prs).ok()),
-                    notified: false,
-                })
-                .await
-        {
+/// Lists the rule suites matching the filter, usually [`BotConfig::rule_suite_filter`], newest
+/// first.
+///
+/// Pagination is followed until the last page or until a suite more than [`SYNC_SAFETY_WINDOW`]
+/// behind `cursor` is reached. If a cursor is given, the time period is narrowed to the smallest
+/// one covering the last sync and the safety window.
+#[tracing::instrument(skip(github))]
+pub(crate) async fn list_rule_suites(
+    github: &dyn GitHubApi,
+    filter: &RuleSuiteFilter,
+    source: RuleSuiteSource<'_>,
+    cursor: Option<&SyncCursor>,
+) -> anyhow::Result<Vec<RuleSuite>> {
+    let mut query = RuleSuiteQuery::first_page(filter);
+    if let Some(cursor) = cursor {
+        let since = cursor.synced_at - SYNC_SAFETY_WINDOW;
+        query.time_period = TimePeriod::covering(since, Utc::now());
+        if query.time_period == TimePeriod::Month && since < Utc::now() - TimeDelta::days(30) {
             tracing::warn!(
-                "Failed to create rule suite event for suite ID {}: {}",
-                suite.id,
-                e
+                "Last sync at {} is older than the longest time period, rule suites may have been missed",
+                cursor.synced_at
             );
-            continue;
         }
     }
+    let mut rule_suites = Vec::new();
 
-    Ok(())
+    loop {
+        let page = match source {
+            RuleSuiteSource::Repository(repository_full_name) => {
+                github
+                    .list_rule_suites(repository_full_name, &query)
+                    .await?
+            }
+            RuleSuiteSource::Organization(org) => github.list_org_rule_suites(org, &query).await?,
+        };
+        let page_len = page.len();
+        let is_last_page = page_len < usize::from(query.per_page);
+
+        let new_suites: Vec<RuleSuite> = page
+            .into_iter()
+            .take_while(|suite| cursor.is_none_or(|cursor| !cursor.is_behind_window(suite)))
+            .collect();
+        let reached_cursor = new_suites.len() < page_len;
+        rule_suites.extend(new_suites);
+
+        if is_last_page || reached_cursor {
+            break;
+        }
+        query.page += 1;
+    }
+
+    Ok(rule_suites)
 }
 
-#[tracing::instrument(skip(bot, config, slack, octocrab))]
+#[tracing::instrument(skip(bot, config, slack, github, asset_levels))]
 pub async fn evaluate_rule_suites(
     bot: &dyn RulesetBot,
     config: &BotConfig,
     slack: &dyn SlackClient,
-    octocrab: &Octocrab,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
     repository_full_name: &str,
     repository_name: &str,
 ) -> anyhow::Result<()> {
-    let github_org = &config.github_org;
-    let props = octocrab
-        .list_custom_properties(github_org, repository_name)
-        .await?;
-
-    let Some(asset_level) = AssetLevel::get_from_props(&props) else {
+    let asset_level = match asset_levels.get(github, config, repository_name).await? {
+        Ok(asset_level) => asset_level,
         // Ignore repositories without asset level.
-        return Ok(());
+        Err(AssetLevelError::Missing) => return Ok(()),
+        Err(error) => {
+            // A misconfigured asset level must not hide bypasses. The suites stay unnotified,
+            // so the warning is repeated daily until the custom property is fixed.
+            let now = Utc::now();
+            let warned = bot.get_asset_level_warning(repository_full_name).await?;
+            if warned.is_some_and(|warning| warning.covers(&error, now)) {
+                return Ok(());
+            }
+
+            let unnotified = bot
+                .find_unnotified_rule_suites(repository_full_name)
+                .await?;
+            if !unnotified.is_empty() {
+                send_asset_level_warning(
+                    slack,
+                    config,
+                    repository_full_name,
+                    &error,
+                    unnotified.len(),
+                )
+                .await?;
+                bot.set_asset_level_warning(
+                    repository_full_name,
+                    AssetLevelWarning::new(&error, now),
+                )
+                .await?;
+            }
+            return Ok(());
+        }
     };
 
     if !config.in_scope_asset_level.contains(&asset_level) {
@@ -198,91 +709,337 @@ pub async fn evaluate_rule_suites(
         return Ok(());
     }
 
+    let default_branch = resolve_default_branch(
+        github,
+        config,
+        repository_name,
+        config.policy.uses_default_branch(),
+    )
+    .await?;
+
     for suite in rule_suites {
         let suite_data: RuleSuite = serde_json::from_str(&suite.event_data)?;
         let resulting_commit = suite
             .resulting_commit
             .and_then(|json| serde_json::from_str::<RepoCommit>(&json).ok());
-        let pr = suite
+        let mut prs = suite
             .prs
             .and_then(|json| serde_json::from_str::<Vec<PullRequest>>(&json).ok())
-            .and_then(|prs| prs.first().cloned());
+            .unwrap_or_default();
+        suite_data.sort_pull_requests(&mut prs);
+        let ci_checks = suite
+            .ci_checks
+            .and_then(|json| serde_json::from_str::<CiChecks>(&json).ok());
+        let discarded_commits = suite
+            .discarded_commits
+            .and_then(|json| serde_json::from_str::<DiscardedCommits>(&json).ok());
 
-        send_violation_slack_message(
+        let (decision, messages) = send_violation_notifications(
             slack,
             &suite_data,
             resulting_commit,
-            pr,
-            asset_level,
+            &prs,
+            ci_checks,
+            discarded_commits,
+            &asset_level,
+            default_branch.as_deref(),
             bot,
             config,
         )
         .await?;
 
         // Update the evaluation result in the DB.
+        bot.set_rule_suite_decision(suite.id, serde_json::to_string(&decision)?)
+            .await?;
+        bot.set_rule_suite_messages(suite.id, serde_json::to_string(&messages)?)
+            .await?;
         bot.mark_rule_suite_notified(suite.id).await?;
+
+        // Bypasses of the review requirement can still be approved after the fact.
+        if let Some(review_config) = &config.retroactive_review
+            && requires_retroactive_review(&suite_data)
+        {
+            let review = ReviewStatus::Pending {
+                deadline: suite_data.pushed_at + review_config.window,
+            };
+            bot.set_rule_suite_review(suite.id, serde_json::to_string(&review)?)
+                .await?;
+        }
     }
 
     Ok(())
 }
 
-pub async fn send_violation_slack_message(
+/// Warns the SOC2 channel that bypasses of a repository cannot be evaluated because its asset
+/// level is misconfigured.
+pub async fn send_asset_level_warning(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    repository_full_name: &str,
+    error: &AssetLevelError,
+    unnotified_rule_suites: usize,
+) -> Result<()> {
+    let text = format!(
+        "{unnotified_rule_suites} bypass(es) in `{repository_full_name}` cannot be evaluated: {error}. \
+         Please fix the `{}` custom property of the repository.",
+        config.asset_taxonomy.property_name
+    );
+
+    let content = build_text_message("Misconfigured Repository Asset Level", text);
+
+    slack
+        .post_message_channel(
+            SlackChannelId::new(config.slack_soc2_channel.to_string()),
+            content,
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(())
+}
+
+/// Alerts the SOC2 channel that a ruleset was deleted, since deleting a ruleset is a SOC2-relevant
+/// change. Organization rulesets always alert. Repository rulesets only alert if the repository
+/// is in scope, or if its asset level is misconfigured. Returns whether an alert was sent.
+pub async fn alert_deleted_ruleset(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    ruleset: &DeletedRuleset,
+) -> Result<bool> {
+    let scope = match &ruleset.repository {
+        Some(repository_name) => match asset_levels.get(github, config, repository_name).await? {
+            Ok(asset_level) if !config.in_scope_asset_level.contains(&asset_level) => {
+                return Ok(false);
+            }
+            Err(AssetLevelError::Missing) => return Ok(false),
+            _ => format!("repository `{}/{repository_name}`", config.github_org),
+        },
+        None => format!("organization `{}`", config.github_org),
+    };
+
+    let text = format!(
+        "Ruleset *{}* ({}) of {scope} was deleted by `{}`. Its rules are no longer enforced, \
+         later bypasses of it are reported as bypasses of a deleted ruleset.",
+        ruleset.name,
+        ruleset.id,
+        ruleset.deleted_by.as_deref().unwrap_or("unknown"),
+    );
+
+    let content = build_text_message("Ruleset Deleted", text);
+
+    slack
+        .post_message_channel(
+            SlackChannelId::new(config.slack_soc2_channel.to_string()),
+            content,
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(true)
+}
+
+/// A message with a header and a Markdown section. The text is also the notification text.
+pub(crate) fn build_text_message(header: &str, text: String) -> SlackMessageContent {
+    SlackMessageContent {
+        text: Some(text.clone()),
+        blocks: Some(vec![
+            SlackHeaderBlock {
+                block_id: None,
+                text: SlackBlockPlainText::from(header).into(),
+            }
+            .into(),
+            SlackSectionBlock {
+                block_id: None,
+                text: Some(SlackBlockText::MarkDown(SlackBlockMarkDownText::from(text))),
+                fields: None,
+                accessory: None,
+            }
+            .into(),
+        ]),
+        attachments: None,
+        upload: None,
+        files: None,
+        reactions: None,
+        metadata: None,
+    }
+}
+
+/// Who is addressed by the notification of a bypass, see [`actor_recipients`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActorRecipients {
+    /// The Slack users receiving the notification as direct message
+    pub users: Vec<SlackUserId>,
+    /// How the notification addresses them, e.g. `<@U024BE7LH>`, or `<!subteam^S0614TZR7>` if
+    /// the fallback recipient is a user group
+    pub mention: Option<String>,
+}
+
+/// The Slack user of the actor, or the fallback recipients if the actor is unknown to Slack
+pub async fn actor_recipients(
+    suite_data: &RuleSuite,
+    slack: &dyn SlackClient,
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+) -> ActorRecipients {
+    // A failed lookup must not hide the bypass, the fallback recipient is notified instead.
+    let slack_actor = match suite_data.get_slack_actor(slack, bot).await {
+        Ok(slack_actor) => slack_actor.map(|user| user.id),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to look up the Slack user of {:?}: {e:#}",
+                suite_data.actor_name
+            );
+            None
+        }
+    };
+    if let Some(slack_actor) = slack_actor {
+        return ActorRecipients {
+            mention: Some(format!("<@{}>", slack_actor.0)),
+            users: vec![slack_actor],
+        };
+    }
+
+    let fallback = config.escalation_contacts.fallback.as_slice();
+    let users = resolve_contacts(slack, fallback).await;
+    let mention = match fallback.first() {
+        Some(EscalationContact::UserGroup(id)) => Some(format!("<!subteam^{id}>")),
+        _ => users.first().map(|user| format!("<@{}>", user.0)),
+    };
+    ActorRecipients { users, mention }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn send_violation_notifications(
     slack: &dyn SlackClient,
     suite_data: &RuleSuite,
     resulting_commit: Option<RepoCommit>,
-    pr: Option<PullRequest>,
-    asset_level: AssetLevel,
+    prs: &[PullRequest],
+    ci_checks: Option<CiChecks>,
+    discarded_commits: Option<DiscardedCommits>,
+    asset_level: &AssetTier,
+    default_branch: Option<&str>,
     bot: &dyn RulesetBot,
     config: &BotConfig,
-) -> Result<()> {
-    let max_ammann = slack.get_user_by_email("max.ammann@zoo.dev").await?;
+) -> Result<(PolicyDecision, Vec<PostedMessage>)> {
+    let escalation = &config.escalation_contacts;
+    let actor_recipients = actor_recipients(suite_data, slack, bot, config).await;
 
-    let slack_actor = suite_data
-        .get_slack_actor(slack, bot)
-        .await?
-        .unwrap_or(max_ammann.clone());
+    let waivers = bot
+        .find_waivers(&format!(
+            "{}/{}",
+            config.github_org, suite_data.repository_name
+        ))
+        .await?;
 
-    let content = suite_data.build_soc2_notification(&slack_actor, &pr, asset_level, config);
+    let decision = config.policy.decide(&PolicyInput {
+        suite: suite_data,
+        asset_level,
+        resulting_commit: resulting_commit.as_ref(),
+        pr: suite_data.landing_pull_request(prs),
+        config,
+        waivers: &waivers,
+        default_branch,
+    });
+    if let Some(waiver) = &decision.waiver {
+        tracing::info!(
+            "Suppressed callout of rule suite {} by waiver {} approved by {}: {}",
+            suite_data.id,
+            waiver.id,
+            waiver.approver,
+            waiver.reason
+        );
+    }
+    tracing::info!(
+        "Policy decision for rule suite {}: {}",
+        suite_data.id,
+        decision.explanation()
+    );
 
-    // Send as DM or to channel based on level
-    let call_out = suite_data.call_out_violation(asset_level, resulting_commit, pr, config);
+    let violation = suite_data.violation(
+        prs,
+        ci_checks.as_ref(),
+        discarded_commits.as_ref(),
+        &decision,
+        config,
+    );
+    let content = with_action_buttons(
+        build_slack_message(&violation, actor_recipients.mention.as_deref()),
+        &suite_data.id.to_string(),
+    );
 
     let soc2_channel = &config.slack_soc2_channel;
 
-    if call_out
-        && let Err(e) = slack
+    // Every posted copy, stored so actions can edit them. The actor may reply to them in a thread.
+    let mut messages = Vec::new();
+
+    if decision.channel {
+        match slack
             .post_message_channel(
                 SlackChannelId::new(soc2_channel.to_string()),
                 content.clone(),
             )
             .await
-    {
-        return Err(anyhow!("posting a slack message failed: {e}"));
+        {
+            Ok(message) => messages.push(message),
+            Err(e) => return Err(anyhow!("posting a slack message failed: {e}")),
+        }
     }
 
-    // Send to actor
-    if let Err(e) = slack
-        .post_message_user(slack_actor.id, content.clone())
-        .await
-    {
-        return Err(anyhow!("posting a slack message failed: {e}"));
+    // Send to actor, or the fallback recipient
+    if decision.dm {
+        for recipient in &actor_recipients.users {
+            match slack
+                .post_message_user(recipient.clone(), content.clone())
+                .await
+            {
+                Ok(message) => messages.push(message),
+                Err(e) => return Err(anyhow!("posting a slack message failed: {e}")),
+            }
+        }
     }
 
-    // Also send to Max Ammann
-    if let Err(e) = slack.post_message_user(max_ammann.id, content).await {
-        return Err(anyhow!("posting a slack message failed: {e}"));
+    // Also send a copy to the escalation contacts, unless they already received it as recipient
+    let contacts = resolve_contacts(slack, escalation.for_asset_level(asset_level)).await;
+    for contact in contacts {
+        if decision.dm && actor_recipients.users.contains(&contact) {
+            continue;
+        }
+        match slack.post_message_user(contact, content.clone()).await {
+            Ok(message) => messages.push(message),
+            Err(e) => return Err(anyhow!("posting a slack message failed: {e}")),
+        }
     }
 
-    Ok(())
+    // The further channels mirror the SOC2 channel. They are not required for the notification
+    // to count as sent.
+    if decision.channel {
+        for notifier in &config.notifiers {
+            let result = tokio::time::timeout(NOTIFIER_TIMEOUT, notifier.notify(&violation))
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("timed out after {NOTIFIER_TIMEOUT:?}")));
+            if let Err(e) = result {
+                tracing::warn!(
+                    "Failed to notify {notifier:?} of rule suite {}: {e:#}",
+                    suite_data.id
+                );
+            }
+        }
+    }
+
+    Ok((decision, messages))
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs;
 
+    use chrono::{TimeDelta, Utc};
     use serde_json;
 
-    use crate::soc2::rule_suit::RuleSuite;
+    use crate::soc2::rule_suit::{RuleSuite, TimePeriod};
+    use crate::{SYNC_SAFETY_WINDOW, SyncCursor};
 
     /// Load JSON fixture from the `tests/fixtures` directory.
     fn load_fixture(name: &str) -> String {
@@ -323,4 +1080,59 @@ mod tests {
         let _parsed: Vec<RuleSuite> =
             serde_json::from_str(&json_str).expect("Failed to deserialize RuleSuite fixture");
     }
+
+    #[test]
+    fn test_sync_cursor_covers_older_suites() {
+        let json_str = load_fixture("rulesuites.json");
+        let parsed: Vec<RuleSuite> =
+            serde_json::from_str(&json_str).expect("Failed to deserialize RuleSuite fixture");
+
+        // Suites are listed newest first.
+        let cursor = SyncCursor::new(&parsed[1], Utc::now());
+        assert!(!cursor.covers(&parsed[0]));
+        assert!(cursor.covers(&parsed[1]));
+        assert!(parsed[2..].iter().all(|suite| cursor.covers(suite)));
+    }
+
+    #[test]
+    fn test_sync_cursor_window_reaches_behind_newest_suite() {
+        let json_str = load_fixture("rulesuite2.json");
+        let suite: RuleSuite =
+            serde_json::from_str(&json_str).expect("Failed to deserialize RuleSuite fixture");
+        let cursor = SyncCursor::new(&suite, Utc::now());
+
+        let late = RuleSuite {
+            pushed_at: suite.pushed_at - TimeDelta::minutes(10),
+            ..suite.clone()
+        };
+        assert!(cursor.covers(&late));
+        assert!(!cursor.is_behind_window(&late));
+
+        let old = RuleSuite {
+            pushed_at: suite.pushed_at - SYNC_SAFETY_WINDOW - TimeDelta::seconds(1),
+            ..suite
+        };
+        assert!(cursor.is_behind_window(&old));
+    }
+
+    #[test]
+    fn test_time_period_covering() {
+        let now = Utc::now();
+        assert_eq!(
+            TimePeriod::covering(now - TimeDelta::minutes(5), now),
+            TimePeriod::Hour
+        );
+        assert_eq!(
+            TimePeriod::covering(now - TimeDelta::hours(5), now),
+            TimePeriod::Day
+        );
+        assert_eq!(
+            TimePeriod::covering(now - TimeDelta::days(5), now),
+            TimePeriod::Week
+        );
+        assert_eq!(
+            TimePeriod::covering(now - TimeDelta::days(90), now),
+            TimePeriod::Month
+        );
+    }
 }
@@ -0,0 +1,1261 @@
+//! Declarative escalation policy deciding how a bypass is notified.
+//!
+//! A [`Policy`] is a list of rules, each with conditions and actions. Every rule matching a
+//! bypass contributes its actions to the [`PolicyDecision`]. Policies are loaded from TOML or
+//! JSON, see `default_policy.toml` for the policy used unless configured otherwise.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::Context;
+use octocrab::models::{pulls::PullRequest, repos::RepoCommit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BotConfig, glob,
+    soc2::{
+        asset_level::AssetTier,
+        ref_filter::{matches_ref, uses_default_branch},
+        rule_suit::{
+            Enforcement, EvaluatedRuleSource, RuleEvalResult, RuleEvaluation, RuleOutcome,
+            RuleSuite, ViolationCategory,
+        },
+        trusted_actor::is_trusted,
+        waiver::Waiver,
+    },
+};
+
+const DEFAULT_POLICY: &str = include_str!("default_policy.toml");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Identifies the rule in logs
+    pub name: String,
+    /// All conditions must hold for the rule to apply. Empty conditions always hold.
+    #[serde(default)]
+    pub when: Conditions,
+    /// The rule does not apply if any of these conditions hold
+    #[serde(default)]
+    pub unless: Vec<Conditions>,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Send the notification to the actor
+    Dm,
+    /// Call out the bypass in [`BotConfig::slack_soc2_channel`]
+    Channel,
+    /// Mark the bypass as critical, asking the actor for a justification
+    Critical,
+}
+
+/// Conditions on a bypass. Unset conditions and empty lists always hold; a list holds if any
+/// of its entries matches.
+///
+/// The conditions on rule evaluations (`rule_types`, `rule_sources`, `rule_source_ids`,
+/// `rulesets`, `enforcement` and `categories`) must all hold for the same failed rule evaluation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Conditions {
+    /// The asset level of the repository
+    pub asset_level: Option<AssetLevelCondition>,
+    /// Glob patterns for the ref, e.g. `refs/heads/main`, or `~DEFAULT_BRANCH`
+    pub refs: Vec<String>,
+    /// Glob patterns for the login of the actor who pushed
+    pub actors: Vec<String>,
+    /// The author of the resulting commit
+    pub commit_authors: Vec<ActorMatcher>,
+    /// Whether the actor who pushed is trusted to bypass the failed rules, see
+    /// [`BotConfig::trusted_actors`]
+    pub trusted_actor: Option<bool>,
+    /// Whether the author of the resulting commit is trusted to bypass the failed rules
+    pub trusted_commit_author: Option<bool>,
+    /// Glob patterns for the labels of the pull request
+    pub labels: Vec<String>,
+    /// The type of the failed rule, e.g. `pull_request` or `non_fast_forward`
+    pub rule_types: Vec<String>,
+    /// The kind of source of the failed rule
+    pub rule_sources: Vec<RuleSourceKind>,
+    /// The id of the ruleset of the failed rule
+    pub rule_source_ids: Vec<i64>,
+    /// The ruleset of the failed rule, by the role it is configured for in [`BotConfig`]
+    pub rulesets: Vec<RulesetRole>,
+    /// The enforcement of the failed rule
+    pub enforcement: Vec<Enforcement>,
+    /// The category of the failed rule, e.g. `codeowners`
+    pub categories: Vec<ViolationCategory>,
+}
+
+/// A range of asset levels, either one of the ranges of [`BotConfig`] or by level names
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AssetLevelCondition {
+    Configured(ConfiguredRange),
+    /// Names of [`BotConfig::asset_taxonomy`]. Unknown names never match.
+    Range {
+        least: String,
+        most: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfiguredRange {
+    /// [`BotConfig::in_scope_asset_level`]
+    InScope,
+    /// [`BotConfig::callout_asset_level`]
+    Callout,
+    /// [`BotConfig::critical_asset_levels`]
+    Critical,
+}
+
+/// Matches a GitHub account. Unset fields match any account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActorMatcher {
+    pub id: Option<i64>,
+    /// Glob pattern for the login, e.g. `*[bot]`
+    pub login: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleSourceKind {
+    Ruleset,
+    ProtectedBranch,
+    /// Sources we cannot classify, which may be branch protection rules
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RulesetRole {
+    /// [`BotConfig::review_requirement_ruleset_id`]
+    ReviewRequirement,
+    /// [`BotConfig::block_force_push_ruleset_id`]
+    BlockForcePush,
+    /// [`BotConfig::codeowners_ruleset_id`]
+    Codeowners,
+}
+
+/// Everything a policy decides on
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyInput<'a> {
+    pub suite: &'a RuleSuite,
+    pub asset_level: &'a AssetTier,
+    pub resulting_commit: Option<&'a RepoCommit>,
+    /// The pull request that landed the commit, see [`RuleSuite::landing_pull_request`]
+    pub pr: Option<&'a PullRequest>,
+    pub config: &'a BotConfig,
+    /// The waivers of the repository, including expired ones
+    pub waivers: &'a [Waiver],
+    /// The default branch of the repository, e.g. `main`. Only resolved if the policy uses
+    /// `~DEFAULT_BRANCH`, see [`Policy::uses_default_branch`].
+    pub default_branch: Option<&'a str>,
+}
+
+/// How to notify about a bypass, and why
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub dm: bool,
+    pub channel: bool,
+    pub critical: bool,
+    /// Every rule of the policy, in order
+    pub trace: Vec<RuleTrace>,
+    /// The waiver that suppressed the channel callout and critical status
+    pub waiver: Option<Waiver>,
+    /// The categories of the bypassed rules
+    #[serde(default)]
+    pub categories: Vec<ViolationCategory>,
+}
+
+/// Whether a rule applied, and the outcome of each of its conditions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleTrace {
+    pub rule: String,
+    pub applied: bool,
+    pub conditions: Vec<ConditionTrace>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    /// e.g. "asset level `Production` in callout range"
+    pub condition: String,
+    pub holds: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::from_toml(DEFAULT_POLICY).expect("default policy is valid")
+    }
+}
+
+impl Policy {
+    pub fn from_toml(policy: &str) -> anyhow::Result<Self> {
+        toml::from_str(policy).context("invalid TOML policy")
+    }
+
+    pub fn from_json(policy: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(policy).context("invalid JSON policy")
+    }
+
+    /// Whether any rule matches refs against the default branch of the repository
+    pub fn uses_default_branch(&self) -> bool {
+        self.rules.iter().any(|rule| {
+            uses_default_branch(&rule.when.refs)
+                || rule
+                    .unless
+                    .iter()
+                    .any(|unless| uses_default_branch(&unless.refs))
+        })
+    }
+
+    pub fn decide(&self, input: &PolicyInput<'_>) -> PolicyDecision {
+        let mut decision = PolicyDecision {
+            categories: input.suite.categories(input.config),
+            ..Default::default()
+        };
+
+        for rule in &self.rules {
+            let mut conditions = rule.when.explain(input);
+            conditions.extend(rule.unless.iter().map(|unless| {
+                let trace = unless.explain(input);
+                ConditionTrace {
+                    condition: format!(
+                        "not {}",
+                        trace
+                            .iter()
+                            .map(|c| c.condition.as_str())
+                            .collect::<Vec<_>>()
+                            .join(" and ")
+                    ),
+                    holds: !trace.iter().all(|c| c.holds),
+                }
+            }));
+            let applied = conditions.iter().all(|c| c.holds);
+
+            if applied {
+                for action in &rule.actions {
+                    match action {
+                        Action::Dm => decision.dm = true,
+                        Action::Channel => decision.channel = true,
+                        Action::Critical => decision.critical = true,
+                    }
+                }
+            }
+
+            decision.trace.push(RuleTrace {
+                rule: rule.name.clone(),
+                applied,
+                conditions,
+            });
+        }
+
+        if decision.channel || decision.critical {
+            let repository_full_name = format!(
+                "{}/{}",
+                input.config.github_org, input.suite.repository_name
+            );
+            if let Some(waiver) = input
+                .waivers
+                .iter()
+                .find(|waiver| waiver.covers(&repository_full_name, input.suite))
+            {
+                decision.channel = false;
+                decision.critical = false;
+                decision.waiver = Some(waiver.clone());
+            }
+        }
+
+        decision
+    }
+}
+
+impl PolicyDecision {
+    /// The names of the rules that applied
+    pub fn matched_rules(&self) -> Vec<&str> {
+        self.trace
+            .iter()
+            .filter(|rule| rule.applied)
+            .map(|rule| rule.rule.as_str())
+            .collect()
+    }
+
+    /// Explains the decision in Markdown: the outcome, all conditions of the rules that
+    /// applied and the conditions that did not hold for the other rules.
+    pub fn explanation(&self) -> String {
+        let mut outcome = Vec::new();
+        if self.dm {
+            outcome.push("DM");
+        }
+        if self.channel {
+            outcome.push("channel callout");
+        }
+        if self.critical {
+            outcome.push("critical");
+        }
+        if outcome.is_empty() {
+            outcome.push("no notification");
+        }
+
+        let mut lines = vec![format!("**Policy decision:** {}", outcome.join(", "))];
+        if !self.categories.is_empty() {
+            let categories: Vec<String> = self.categories.iter().map(ToString::to_string).collect();
+            lines.push(format!("**Categories:** {}", categories.join(", ")));
+        }
+        for rule in &self.trace {
+            let conditions: Vec<String> = rule
+                .conditions
+                .iter()
+                .filter(|c| rule.applied || !c.holds)
+                .map(|c| format!("{} {}", if c.holds { "✓" } else { "✗" }, c.condition))
+                .collect();
+
+            let mut line = format!(
+                "• `{}` {}",
+                rule.rule,
+                if rule.applied { "applied" } else { "skipped" }
+            );
+            if !conditions.is_empty() {
+                line.push_str(": ");
+                line.push_str(&conditions.join("; "));
+            }
+            lines.push(line);
+        }
+
+        if let Some(waiver) = &self.waiver {
+            lines.push(format!(
+                "• Waived by waiver {} (approved by {}, expires {}): {}",
+                waiver.id,
+                waiver.approver,
+                waiver.expires_at.format("%Y-%m-%d %H:%M UTC"),
+                waiver.reason
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl Conditions {
+    pub fn matches(&self, input: &PolicyInput<'_>) -> bool {
+        self.explain(input).iter().all(|c| c.holds)
+    }
+
+    /// Evaluates each condition that is set
+    pub fn explain(&self, input: &PolicyInput<'_>) -> Vec<ConditionTrace> {
+        let suite = input.suite;
+        let mut trace = Vec::new();
+
+        if let Some(condition) = &self.asset_level {
+            trace.push(ConditionTrace {
+                condition: condition.describe(input.asset_level),
+                holds: condition.matches(input.asset_level, input.config),
+            });
+        }
+
+        if !self.refs.is_empty() {
+            trace.push(ConditionTrace {
+                condition: format!("ref `{}` matches {}", suite.ref_name, patterns(&self.refs)),
+                holds: self
+                    .refs
+                    .iter()
+                    .any(|pattern| matches_ref(pattern, &suite.ref_name, input.default_branch)),
+            });
+        }
+
+        if !self.actors.is_empty() {
+            let actor = suite.actor_name.as_deref();
+            trace.push(ConditionTrace {
+                condition: format!(
+                    "actor `{}` matches {}",
+                    actor.unwrap_or("unknown"),
+                    patterns(&self.actors)
+                ),
+                holds: actor.is_some_and(|actor| any_glob(&self.actors, actor)),
+            });
+        }
+
+        if !self.commit_authors.is_empty() {
+            let author = input
+                .resulting_commit
+                .and_then(|commit| commit.author.as_ref());
+            trace.push(ConditionTrace {
+                condition: format!(
+                    "commit author `{}` is {}",
+                    author.map_or("unknown", |author| author.login.as_str()),
+                    self.commit_authors
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                ),
+                holds: author.is_some_and(|author| {
+                    self.commit_authors
+                        .iter()
+                        .any(|matcher| matcher.matches(author.id.0 as i64, &author.login))
+                }),
+            });
+        }
+
+        if let Some(expected) = self.trusted_actor {
+            let actor = suite.actor_name.as_deref();
+            trace.push(ConditionTrace {
+                condition: format!(
+                    "actor `{}` is {}trusted",
+                    actor.unwrap_or("unknown"),
+                    if expected { "" } else { "not " }
+                ),
+                holds: is_trusted(&input.config.trusted_actors, suite.actor_id, actor, suite)
+                    == expected,
+            });
+        }
+
+        if let Some(expected) = self.trusted_commit_author {
+            let author = input
+                .resulting_commit
+                .and_then(|commit| commit.author.as_ref());
+            trace.push(ConditionTrace {
+                condition: format!(
+                    "commit author `{}` is {}trusted",
+                    author.map_or("unknown", |author| author.login.as_str()),
+                    if expected { "" } else { "not " }
+                ),
+                holds: author.is_some_and(|author| {
+                    is_trusted(
+                        &input.config.trusted_actors,
+                        Some(author.id.0 as i64),
+                        Some(&author.login),
+                        suite,
+                    )
+                }) == expected,
+            });
+        }
+
+        if !self.labels.is_empty() {
+            trace.push(ConditionTrace {
+                condition: format!("pull request label matches {}", patterns(&self.labels)),
+                holds: input
+                    .pr
+                    .and_then(|pr| pr.labels.as_ref())
+                    .is_some_and(|labels| {
+                        labels
+                            .iter()
+                            .any(|label| any_glob(&self.labels, &label.name))
+                    }),
+            });
+        }
+
+        if self.has_evaluation_conditions() {
+            trace.push(ConditionTrace {
+                condition: format!(
+                    "failed rule with {}",
+                    self.describe_evaluation(input.config)
+                ),
+                holds: suite.result == RuleOutcome::Bypass
+                    && suite.rule_evaluations.iter().flatten().any(|eval| {
+                        eval.result == RuleEvalResult::Fail
+                            && self.matches_evaluation(eval, input.config)
+                    }),
+            });
+        }
+
+        trace
+    }
+
+    fn has_evaluation_conditions(&self) -> bool {
+        !self.rule_types.is_empty()
+            || !self.rule_sources.is_empty()
+            || !self.rule_source_ids.is_empty()
+            || !self.rulesets.is_empty()
+            || !self.enforcement.is_empty()
+            || !self.categories.is_empty()
+    }
+
+    fn describe_evaluation(&self, config: &BotConfig) -> String {
+        let mut parts = Vec::new();
+
+        if !self.rule_types.is_empty() {
+            parts.push(format!("type {}", patterns(&self.rule_types)));
+        }
+        if !self.rule_sources.is_empty() {
+            parts.push(format!("source {}", names(&self.rule_sources)));
+        }
+        if !self.rule_source_ids.is_empty() {
+            let ids: Vec<String> = self.rule_source_ids.iter().map(i64::to_string).collect();
+            parts.push(format!("ruleset {}", ids.join(" or ")));
+        }
+        if !self.rulesets.is_empty() {
+            let rulesets: Vec<String> = self
+                .rulesets
+                .iter()
+                .map(|role| match role.ruleset_id(config) {
+                    Some(id) => format!("{} ({id})", name(role)),
+                    None => format!("{} (not configured)", name(role)),
+                })
+                .collect();
+            parts.push(format!("ruleset {}", rulesets.join(" or ")));
+        }
+        if !self.enforcement.is_empty() {
+            parts.push(format!("enforcement {}", names(&self.enforcement)));
+        }
+        if !self.categories.is_empty() {
+            parts.push(format!("category {}", names(&self.categories)));
+        }
+
+        parts.join(", ")
+    }
+
+    fn matches_evaluation(&self, eval: &RuleEvaluation, config: &BotConfig) -> bool {
+        let kind = match eval.rule_source.evaluated_rule_source() {
+            EvaluatedRuleSource::Ruleset { .. } => RuleSourceKind::Ruleset,
+            EvaluatedRuleSource::ProtectedBranch => RuleSourceKind::ProtectedBranch,
+            EvaluatedRuleSource::Unknown { .. } => RuleSourceKind::Unknown,
+        };
+
+        (self.rule_types.is_empty() || self.rule_types.contains(&eval.rule_type))
+            && (self.rule_sources.is_empty() || self.rule_sources.contains(&kind))
+            && (self.rule_source_ids.is_empty()
+                || eval
+                    .rule_source
+                    .id
+                    .is_some_and(|id| self.rule_source_ids.contains(&id)))
+            && (self.rulesets.is_empty()
+                || self.rulesets.iter().any(|role| {
+                    role.ruleset_id(config)
+                        .is_some_and(|id| eval.rule_source.id == Some(id))
+                }))
+            && (self.enforcement.is_empty() || self.enforcement.contains(&eval.enforcement))
+            && (self.categories.is_empty() || self.categories.contains(&eval.category(config)))
+    }
+}
+
+impl AssetLevelCondition {
+    fn describe(&self, asset_level: &AssetTier) -> String {
+        match self {
+            AssetLevelCondition::Configured(range) => {
+                format!("asset level `{asset_level}` in {} range", name(range))
+            }
+            AssetLevelCondition::Range { least, most } => {
+                format!("asset level `{asset_level}` in `{least}`..=`{most}`")
+            }
+        }
+    }
+
+    fn matches(&self, asset_level: &AssetTier, config: &BotConfig) -> bool {
+        match self {
+            AssetLevelCondition::Configured(ConfiguredRange::InScope) => {
+                config.in_scope_asset_level.contains(asset_level)
+            }
+            AssetLevelCondition::Configured(ConfiguredRange::Callout) => {
+                config.callout_asset_level.contains(asset_level)
+            }
+            AssetLevelCondition::Configured(ConfiguredRange::Critical) => {
+                config.critical_asset_levels.contains(asset_level)
+            }
+            AssetLevelCondition::Range { least, most } => config
+                .asset_taxonomy
+                .range(least, most)
+                .is_some_and(|range| range.contains(asset_level)),
+        }
+    }
+}
+
+impl ActorMatcher {
+    pub fn matches(&self, id: i64, login: &str) -> bool {
+        self.id.is_none_or(|expected| expected == id)
+            && self
+                .login
+                .as_ref()
+                .is_none_or(|pattern| glob::matches(pattern, login))
+    }
+}
+
+impl Display for ActorMatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.login, self.id) {
+            (Some(login), Some(id)) => write!(f, "`{login}` ({id})"),
+            (Some(login), None) => write!(f, "`{login}`"),
+            (None, Some(id)) => write!(f, "id {id}"),
+            (None, None) => f.write_str("anyone"),
+        }
+    }
+}
+
+impl RulesetRole {
+    fn ruleset_id(&self, config: &BotConfig) -> Option<i64> {
+        match self {
+            RulesetRole::ReviewRequirement => config.review_requirement_ruleset_id,
+            RulesetRole::BlockForcePush => config.block_force_push_ruleset_id,
+            RulesetRole::Codeowners => config.codeowners_ruleset_id,
+        }
+    }
+}
+
+/// The name of a unit variant in the policy document, e.g. `block_force_push`
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "?".to_string(),
+    }
+}
+
+fn names<T: Serialize>(values: &[T]) -> String {
+    values.iter().map(name).collect::<Vec<_>>().join(" or ")
+}
+
+fn patterns(patterns: &[String]) -> String {
+    patterns
+        .iter()
+        .map(|pattern| format!("`{pattern}`"))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+/// Whether any pattern matches. No patterns match anything.
+fn any_glob(patterns: &[String], text: &str) -> bool {
+    patterns.is_empty() || patterns.iter().any(|pattern| glob::matches(pattern, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{DateTime, Utc};
+    use octocrab::models::{pulls::PullRequest, repos::RepoCommit};
+    use serde::de::DeserializeOwned;
+
+    use super::{Policy, PolicyDecision, PolicyInput};
+    use crate::{
+        BotConfig, GitHubAuth, RuleSuiteFilter,
+        soc2::{
+            asset_level::{AssetLevel, AssetTaxonomy, AssetTier},
+            escalation::EscalationContacts,
+            ref_filter::RefFilter,
+            rule_suit::{RuleSuite, ViolationCategory},
+            trusted_actor::TrustedActor,
+            waiver::Waiver,
+        },
+    };
+
+    fn load_fixture<T: DeserializeOwned>(name: &str) -> T {
+        let path = format!("tests/fixtures/{name}");
+        let json = fs::read_to_string(path).unwrap_or_else(|_| panic!("Fixture {name} not found"));
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("Fixture {name} is invalid: {e}"))
+    }
+
+    fn config() -> BotConfig {
+        BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com".to_string(),
+            slack_soc2_channel: "C0000000000".to_string(),
+            review_requirement_ruleset_id: Some(3973005),
+            block_force_push_ruleset_id: Some(4047011),
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        }
+    }
+
+    fn decide(
+        config: &BotConfig,
+        fixture: &str,
+        asset_level: AssetLevel,
+        resulting_commit: Option<&RepoCommit>,
+        pr: Option<&PullRequest>,
+    ) -> PolicyDecision {
+        let suite: RuleSuite = load_fixture(fixture);
+        config.policy.decide(&PolicyInput {
+            suite: &suite,
+            asset_level: &AssetTier::from(asset_level),
+            resulting_commit,
+            pr,
+            config,
+            waivers: &[],
+            default_branch: None,
+        })
+    }
+
+    /// (dm, channel, critical)
+    fn actions(decision: PolicyDecision) -> (bool, bool, bool) {
+        (decision.dm, decision.channel, decision.critical)
+    }
+
+    #[test]
+    fn default_policy_review_requirement_bypass() {
+        let config = config();
+        let fixture = "bypass_review_requirement.json";
+
+        assert_eq!(
+            actions(decide(&config, fixture, AssetLevel::Production, None, None)),
+            (true, true, true)
+        );
+        assert_eq!(
+            actions(decide(&config, fixture, AssetLevel::Corporate, None, None)),
+            (true, false, false)
+        );
+
+        // Dependabot and policy exceptions are not called out, but stay critical.
+        let commit: RepoCommit = load_fixture("commit_dependabot.json");
+        assert_eq!(
+            actions(decide(
+                &config,
+                fixture,
+                AssetLevel::Production,
+                Some(&commit),
+                None
+            )),
+            (true, false, true)
+        );
+        let pr: PullRequest = load_fixture("pull_request_policy_exception.json");
+        assert_eq!(
+            actions(decide(
+                &config,
+                fixture,
+                AssetLevel::Production,
+                None,
+                Some(&pr)
+            )),
+            (true, false, true)
+        );
+    }
+
+    #[test]
+    fn policy_exception_only_from_landing_pull_request() {
+        let config = config();
+        let suite: RuleSuite = load_fixture("bypass_review_requirement.json");
+        let landing: PullRequest = load_fixture("pull_request_policy_exception.json");
+        // The same exception label on a pull request merged into another branch.
+        let mut backport = landing.clone();
+        backport.number = 7135;
+        backport.base.ref_field = "release/1.0".to_string();
+        let follow_up: PullRequest = load_fixture("pull_request_post_merge_review.json");
+
+        let mut prs = vec![follow_up, backport, landing];
+        suite.sort_pull_requests(&mut prs);
+        assert_eq!(
+            prs.iter().map(|pr| pr.number).collect::<Vec<_>>(),
+            [7131, 7140, 7135]
+        );
+        assert_eq!(
+            suite.landing_pull_request(&prs).map(|pr| pr.number),
+            Some(7131)
+        );
+        assert_eq!(suite.landing_pull_request(&prs[1..]), None);
+
+        let decision = |pr| {
+            actions(decide(
+                &config,
+                "bypass_review_requirement.json",
+                AssetLevel::Production,
+                None,
+                pr,
+            ))
+        };
+        assert_eq!(
+            decision(suite.landing_pull_request(&prs)),
+            (true, false, true)
+        );
+        assert_eq!(
+            decision(suite.landing_pull_request(&prs[1..])),
+            (true, true, true)
+        );
+    }
+
+    #[test]
+    fn default_policy_force_push_bypass() {
+        let config = config();
+        let fixture = "bypass_force_push.json";
+
+        let decision = decide(&config, fixture, AssetLevel::Production, None, None);
+        assert_eq!(
+            decision.matched_rules(),
+            ["notify-actor", "critical-violation", "force-push-bypass"]
+        );
+        assert_eq!(actions(decision), (true, true, true));
+
+        // Dependabot is only exempt from review requirements.
+        let commit: RepoCommit = load_fixture("commit_dependabot.json");
+        assert_eq!(
+            actions(decide(
+                &config,
+                fixture,
+                AssetLevel::Production,
+                Some(&commit),
+                None
+            )),
+            (true, true, true)
+        );
+        assert_eq!(
+            actions(decide(
+                &config,
+                fixture,
+                AssetLevel::NonEssentialProduction,
+                None,
+                None
+            )),
+            (true, false, false)
+        );
+    }
+
+    #[test]
+    fn decision_explains_conditions() {
+        let config = config();
+        let commit: RepoCommit = load_fixture("commit_dependabot.json");
+
+        let decision = decide(
+            &config,
+            "bypass_review_requirement.json",
+            AssetLevel::Production,
+            Some(&commit),
+            None,
+        );
+
+        let review = decision
+            .trace
+            .iter()
+            .find(|rule| rule.rule == "review-requirement-bypass")
+            .expect("rule is traced");
+        assert!(!review.applied);
+        assert_eq!(
+            review
+                .conditions
+                .iter()
+                .map(|c| (c.condition.as_str(), c.holds))
+                .collect::<Vec<_>>(),
+            [
+                ("asset level `Production` in callout range", true),
+                (
+                    "failed rule with ruleset review_requirement (3973005), enforcement active",
+                    true
+                ),
+                ("not commit author `dependabot[bot]` is trusted", false),
+                ("not pull request label matches `*policy-exception*`", true),
+            ]
+        );
+
+        assert_eq!(
+            decision.explanation(),
+            "**Policy decision:** DM, critical\n\
+             **Categories:** Review requirement bypass\n\
+             • `notify-actor` applied\n\
+             • `critical-violation` applied: ✓ asset level `Production` in critical range; \
+             ✓ failed rule with ruleset review_requirement (3973005) or block_force_push (4047011), enforcement active\n\
+             • `force-push-bypass` skipped: ✗ failed rule with ruleset block_force_push (4047011), enforcement active\n\
+             • `branch-protection-bypass` skipped: ✗ failed rule with source protected_branch or unknown\n\
+             • `review-requirement-bypass` skipped: ✗ not commit author `dependabot[bot]` is trusted\n\
+             • `untrusted-bot-bypass` skipped: ✗ actor `maxammann` matches `*[bot]`\n\
+             • `codeowners-bypass` skipped: ✗ failed rule with enforcement active, category codeowners\n\
+             • `ci-bypass` skipped: ✗ failed rule with enforcement active, category ci\n\
+             • `deleted-ruleset-bypass` skipped: ✗ failed rule with category deleted_ruleset"
+        );
+    }
+
+    #[test]
+    fn default_policy_untrusted_bot_bypass() {
+        let mut config = config();
+        let mut suite: RuleSuite = load_fixture("bypass_protected_branch.json");
+        suite.actor_id = Some(155849648);
+        suite.actor_name = Some("zoo-github-actions-auth[bot]".to_string());
+        let decide = |config: &BotConfig, asset_level: AssetLevel| {
+            config.policy.decide(&PolicyInput {
+                suite: &suite,
+                asset_level: &AssetTier::from(asset_level),
+                resulting_commit: None,
+                pr: None,
+                config,
+                waivers: &[],
+                default_branch: None,
+            })
+        };
+
+        // Called out even below the callout asset levels.
+        let decision = decide(&config, AssetLevel::Playground);
+        assert_eq!(
+            decision.matched_rules(),
+            ["notify-actor", "untrusted-bot-bypass"]
+        );
+        assert!(decision.channel);
+
+        config.trusted_actors.push(TrustedActor {
+            app_slug: Some("zoo-github-actions-auth".to_string()),
+            rule_types: vec!["pull_request".to_string()],
+            ..Default::default()
+        });
+        assert!(!decide(&config, AssetLevel::Playground).channel);
+    }
+
+    #[test]
+    fn waiver_suppresses_callout() {
+        let config = config();
+        let suite: RuleSuite = load_fixture("bypass_review_requirement.json");
+        let waiver = Waiver {
+            id: 7,
+            repository_full_name: "KittyCAD/modeling-app".to_string(),
+            ref_pattern: None,
+            rule_types: vec!["pull_request".to_string()],
+            actor_name: None,
+            reason: "Hotfix for the login outage".to_string(),
+            approver: "security@zoo.dev".to_string(),
+            expires_at: DateTime::parse_from_rfc3339("2025-05-20T00:00:00Z")
+                .expect("valid datetime")
+                .with_timezone(&Utc),
+            retroactive: false,
+            created_at: DateTime::parse_from_rfc3339("2025-05-19T00:00:00Z")
+                .expect("valid datetime")
+                .with_timezone(&Utc),
+        };
+        let waivers = [waiver.clone()];
+
+        let decision = config.policy.decide(&PolicyInput {
+            suite: &suite,
+            asset_level: &AssetLevel::Production.into(),
+            resulting_commit: None,
+            pr: None,
+            config: &config,
+            waivers: &waivers,
+            default_branch: None,
+        });
+
+        // The actor is still notified.
+        assert_eq!(
+            (decision.dm, decision.channel, decision.critical),
+            (true, false, false)
+        );
+        assert_eq!(decision.waiver, Some(waiver));
+        assert!(decision.explanation().ends_with(
+            "• Waived by waiver 7 (approved by security@zoo.dev, expires 2025-05-20 00:00 UTC): \
+             Hotfix for the login outage"
+        ));
+    }
+
+    #[test]
+    fn default_policy_codeowners_bypass() {
+        let mut config = config();
+        let fixture = "bypass_codeowners.json";
+
+        // Only detected with a configured ruleset.
+        let decision = decide(&config, fixture, AssetLevel::Production, None, None);
+        assert_eq!(decision.categories, [ViolationCategory::Other]);
+        assert_eq!(
+            decision.matched_rules(),
+            ["notify-actor", "branch-protection-bypass"]
+        );
+
+        config.codeowners_ruleset_id = Some(4102385);
+        let decision = decide(&config, fixture, AssetLevel::Production, None, None);
+        // The branch protection rule waiting on code owners is not counted again.
+        assert_eq!(
+            decision.categories,
+            [ViolationCategory::Codeowners, ViolationCategory::Other]
+        );
+        assert!(
+            decision
+                .explanation()
+                .contains("**Categories:** Code owner review bypass, Other\n")
+        );
+
+        // The severity depends on the asset level of the rule.
+        config.callout_asset_level =
+            AssetLevel::NonEssentialProduction.into()..=AssetLevel::Production.into();
+        config.policy = Policy::from_toml(
+            r#"
+            [[rules]]
+            name = "codeowners-bypass"
+            actions = ["channel"]
+            when = { asset_level = "callout", categories = ["codeowners"] }
+            "#,
+        )
+        .expect("valid policy");
+        assert!(
+            decide(
+                &config,
+                fixture,
+                AssetLevel::NonEssentialProduction,
+                None,
+                None
+            )
+            .channel
+        );
+        assert!(!decide(&config, fixture, AssetLevel::Corporate, None, None).channel);
+    }
+
+    #[test]
+    fn review_bypass_mentioning_code_owners_is_not_a_codeowners_bypass() {
+        let mut config = config();
+        config.codeowners_ruleset_id = Some(4102385);
+        let mut suite: RuleSuite = load_fixture("bypass_protected_branch.json");
+        for eval in suite.rule_evaluations.iter_mut().flatten() {
+            eval.details = Some("Waiting on code owner review from KittyCAD/security.".to_string());
+        }
+
+        let decision = config.policy.decide(&PolicyInput {
+            suite: &suite,
+            asset_level: &AssetTier::from(AssetLevel::Production),
+            resulting_commit: None,
+            pr: None,
+            config: &config,
+            waivers: &[],
+            default_branch: None,
+        });
+        assert_eq!(decision.categories, [ViolationCategory::Other]);
+        assert!(!decision.matched_rules().contains(&"codeowners-bypass"));
+    }
+
+    #[test]
+    fn default_policy_deleted_ruleset_bypass() {
+        let config = config();
+        let fixture = "bypass_deleted_ruleset.json";
+
+        // Neither a force push bypass nor critical, the ruleset no longer exists.
+        let decision = decide(&config, fixture, AssetLevel::Production, None, None);
+        assert_eq!(decision.categories, [ViolationCategory::DeletedRuleset]);
+        assert_eq!(
+            decision.matched_rules(),
+            ["notify-actor", "deleted-ruleset-bypass"]
+        );
+        assert_eq!(actions(decision), (true, true, false));
+
+        assert_eq!(
+            actions(decide(&config, fixture, AssetLevel::Corporate, None, None)),
+            (true, false, false)
+        );
+    }
+
+    #[test]
+    fn default_policy_branch_protection_bypass() {
+        let config = config();
+        let fixture = "bypass_protected_branch.json";
+
+        assert_eq!(
+            actions(decide(&config, fixture, AssetLevel::Production, None, None)),
+            (true, true, false)
+        );
+        assert_eq!(
+            actions(decide(&config, fixture, AssetLevel::Playground, None, None)),
+            (true, false, false)
+        );
+    }
+
+    #[test]
+    fn default_policy_ignores_evaluate_mode_failures() {
+        let mut config = config();
+        // Pretend the evaluate-mode ruleset requires reviews.
+        config.review_requirement_ruleset_id = Some(3757757);
+
+        assert_eq!(
+            actions(decide(
+                &config,
+                "bypass_evaluate_only.json",
+                AssetLevel::Production,
+                None,
+                None
+            )),
+            (true, false, false)
+        );
+    }
+
+    #[test]
+    fn default_policy_without_configured_rulesets() {
+        let mut config = config();
+        config.review_requirement_ruleset_id = None;
+        config.block_force_push_ruleset_id = None;
+
+        for fixture in ["bypass_review_requirement.json", "bypass_force_push.json"] {
+            assert_eq!(
+                actions(decide(&config, fixture, AssetLevel::Production, None, None)),
+                (true, false, false)
+            );
+        }
+    }
+
+    #[test]
+    fn custom_toml_policy() {
+        let mut config = config();
+        config.policy = Policy::from_toml(
+            r#"
+            [[rules]]
+            name = "main-pull-request"
+            actions = ["channel", "critical"]
+
+            [rules.when]
+            asset_level = { least = "Corporate", most = "Production" }
+            refs = ["refs/heads/main"]
+            actors = ["max*"]
+            rule_types = ["pull_request"]
+            rule_sources = ["protected_branch"]
+            "#,
+        )
+        .expect("valid policy");
+
+        let decision = decide(
+            &config,
+            "bypass_protected_branch.json",
+            AssetLevel::Corporate,
+            None,
+            None,
+        );
+        assert_eq!(decision.matched_rules(), ["main-pull-request"]);
+        // Nobody is notified directly unless the policy says so.
+        assert_eq!(actions(decision), (false, true, true));
+
+        assert_eq!(
+            actions(decide(
+                &config,
+                "bypass_review_requirement.json",
+                AssetLevel::Corporate,
+                None,
+                None
+            )),
+            (false, false, false)
+        );
+        assert_eq!(
+            actions(decide(
+                &config,
+                "bypass_protected_branch.json",
+                AssetLevel::ResearchNDevelopment,
+                None,
+                None
+            )),
+            (false, false, false)
+        );
+    }
+
+    #[test]
+    fn policy_matches_default_branch() {
+        let mut config = config();
+        config.policy = Policy::from_toml(
+            r#"
+            [[rules]]
+            name = "default-branch"
+            actions = ["channel"]
+            when = { refs = ["~DEFAULT_BRANCH"] }
+            "#,
+        )
+        .expect("valid policy");
+        assert!(config.policy.uses_default_branch());
+        assert!(!Policy::default().uses_default_branch());
+
+        let suite: RuleSuite = load_fixture("bypass_protected_branch.json");
+        let decide = |default_branch| {
+            config.policy.decide(&PolicyInput {
+                suite: &suite,
+                asset_level: &AssetLevel::Production.into(),
+                resulting_commit: None,
+                pr: None,
+                config: &config,
+                waivers: &[],
+                default_branch,
+            })
+        };
+
+        // The fixture pushed to `refs/heads/main`.
+        assert!(decide(Some("main")).channel);
+        assert!(!decide(Some("trunk")).channel);
+        assert!(!decide(None).channel);
+    }
+
+    #[test]
+    fn json_policy_matches_toml_policy() {
+        let json = Policy::from_json(
+            r#"{
+                "rules": [{
+                    "name": "force-push",
+                    "when": { "asset_level": "critical", "rule_source_ids": [4047011] },
+                    "unless": [{ "commit_authors": [{ "login": "*[bot]" }] }],
+                    "actions": ["channel"]
+                }]
+            }"#,
+        )
+        .expect("valid policy");
+        let toml = Policy::from_toml(
+            r#"
+            [[rules]]
+            name = "force-push"
+            actions = ["channel"]
+            when = { asset_level = "critical", rule_source_ids = [4047011] }
+            unless = [{ commit_authors = [{ login = "*[bot]" }] }]
+            "#,
+        )
+        .expect("valid policy");
+
+        assert_eq!(json, toml);
+    }
+
+    #[test]
+    fn policy_rejects_unknown_conditions() {
+        assert!(
+            Policy::from_toml(
+                r#"
+                [[rules]]
+                name = "typo"
+                actions = ["channel"]
+                when = { rule_type = ["pull_request"] }
+                "#,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn violation_links_point_to_configured_org() {
+        let config = BotConfig {
+            github_org: "ExampleOrg".to_string(),
+            // A trailing slash is not doubled.
+            github_web_base_url: "https://github.example.com/".to_string(),
+            ..config()
+        };
+        let suite: RuleSuite = load_fixture("bypass_review_requirement.json");
+        let decision = decide(
+            &config,
+            "bypass_review_requirement.json",
+            AssetLevel::Production,
+            None,
+            None,
+        );
+        let violation = suite.violation(&[], None, None, &decision, &config);
+
+        let commit_url = "https://github.example.com/ExampleOrg/modeling-app/commit/\
+                          85355b55d6da561e94313c17dfadbaf5b62b5f57";
+        assert_eq!(violation.commit_url, commit_url);
+        assert!(
+            violation
+                .description
+                .contains(&format!("[`85355b5`]({commit_url}) in `modeling-app`.")),
+            "{}",
+            violation.description
+        );
+        let rulesets: Vec<_> = violation
+            .rules
+            .iter()
+            .flat_map(|rule| &rule.fields)
+            .filter(|field| field.title == "Ruleset")
+            .map(|field| field.value.as_str())
+            .collect();
+        assert_eq!(
+            rulesets,
+            ["[Default branch - reasonable (admin bypass)]\
+                 (https://github.example.com/organizations/ExampleOrg/settings/rules/3973005)"]
+        );
+        assert!(!format!("{violation:?}").contains("KittyCAD"));
+    }
+}
@@ -0,0 +1,82 @@
+//! Which refs bypasses are evaluated for.
+//!
+//! Ref patterns are globs over the full ref name, e.g. `refs/heads/release/*`. Like in GitHub
+//! rulesets, [`DEFAULT_BRANCH`] stands for the default branch of the repository, which is resolved
+//! via the repository API.
+
+use serde::{Deserialize, Serialize};
+
+use crate::glob;
+
+/// Matches the default branch of the repository
+pub const DEFAULT_BRANCH: &str = "~DEFAULT_BRANCH";
+
+/// Include and exclude patterns for refs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefFilter {
+    /// Refs to evaluate. Empty includes all refs.
+    pub include: Vec<String>,
+    /// Refs not to evaluate, even if included
+    pub exclude: Vec<String>,
+}
+
+impl RefFilter {
+    /// Whether the default branch has to be resolved to apply the filter
+    pub fn uses_default_branch(&self) -> bool {
+        uses_default_branch(&self.include) || uses_default_branch(&self.exclude)
+    }
+
+    /// Whether the ref passes the filter. `default_branch` is the branch name, e.g. `main`.
+    pub fn matches(&self, ref_name: &str, default_branch: Option<&str>) -> bool {
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| matches_ref(pattern, ref_name, default_branch)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| matches_ref(pattern, ref_name, default_branch))
+    }
+}
+
+pub fn uses_default_branch(patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| pattern == DEFAULT_BRANCH)
+}
+
+/// Whether a ref pattern matches the ref. [`DEFAULT_BRANCH`] never matches if the default branch
+/// is unknown.
+pub fn matches_ref(pattern: &str, ref_name: &str, default_branch: Option<&str>) -> bool {
+    if pattern == DEFAULT_BRANCH {
+        return default_branch.is_some_and(|branch| ref_name == format!("refs/heads/{branch}"));
+    }
+    glob::matches(pattern, ref_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_BRANCH, RefFilter};
+
+    #[test]
+    fn ref_filter_include_exclude() {
+        let filter = RefFilter {
+            include: vec![
+                DEFAULT_BRANCH.to_string(),
+                "refs/heads/release/*".to_string(),
+            ],
+            exclude: vec!["refs/heads/release/*-rc".to_string()],
+        };
+        assert!(filter.uses_default_branch());
+
+        assert!(filter.matches("refs/heads/main", Some("main")));
+        assert!(!filter.matches("refs/heads/main", Some("trunk")));
+        assert!(!filter.matches("refs/heads/main", None));
+        assert!(filter.matches("refs/heads/release/1.2", None));
+        assert!(!filter.matches("refs/heads/release/1.3-rc", None));
+        assert!(!filter.matches("refs/tags/v1.2", Some("main")));
+
+        assert!(RefFilter::default().matches("refs/tags/v1.2", None));
+        assert!(!RefFilter::default().uses_default_branch());
+    }
+}
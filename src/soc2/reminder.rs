@@ -0,0 +1,306 @@
+//! Reminders for critical bypasses without a justification.
+//!
+//! If the actor did not justify a critical bypass within [`ReminderConfig::remind_after`] of the
+//! notification, the actor is reminded by direct message. If there is still no justification
+//! [`ReminderConfig::escalate_after`] after the reminder, the bypass is escalated to the SOC2
+//! channel, mentioning the security owners. The [`ReminderStatus`] is stored per event, so each
+//! step happens once, also across restarts. Bypasses of actors unknown to Slack are only
+//! reminded and escalated if there is a fallback recipient, who is asked instead. Bypasses that
+//! were acknowledged or marked as false positive are not reminded.
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use slack_morphism::prelude::*;
+
+use crate::{
+    BotConfig, GithubRuleSuiteEvent, PostedMessage, RulesetBot, SlackClient,
+    soc2::{
+        action::{ActionKind, has_action},
+        actor_recipients, build_text_message,
+        escalation::{EscalationContact, resolve_contacts},
+        justification::ts_to_datetime,
+        policy::PolicyDecision,
+        retroactive_review::format_window,
+        rule_suit::RuleSuite,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReminderConfig {
+    /// How long after the notification the actor is reminded
+    pub remind_after: TimeDelta,
+    /// How long after the reminder the bypass is escalated
+    pub escalate_after: TimeDelta,
+    /// Mentioned in the escalation
+    pub security_owners: Vec<EscalationContact>,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            remind_after: TimeDelta::days(1),
+            escalate_after: TimeDelta::days(2),
+            security_owners: vec![],
+        }
+    }
+}
+
+/// Which reminders were sent for a rule suite event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReminderStatus {
+    /// The actor was reminded
+    Reminded { reminded_at: DateTime<Utc> },
+    /// The bypass was escalated to the SOC2 channel
+    Escalated {
+        reminded_at: DateTime<Utc>,
+        escalated_at: DateTime<Utc>,
+    },
+}
+
+/// The next reminder step of an unjustified bypass, if it is due at `now`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderStep {
+    Remind,
+    Escalate { reminded_at: DateTime<Utc> },
+}
+
+impl ReminderConfig {
+    /// The step that is due for a bypass notified at `notified_at`
+    pub fn next_step(
+        &self,
+        notified_at: DateTime<Utc>,
+        status: Option<&ReminderStatus>,
+        now: DateTime<Utc>,
+    ) -> Option<ReminderStep> {
+        match status {
+            None if now >= notified_at + self.remind_after => Some(ReminderStep::Remind),
+            Some(ReminderStatus::Reminded { reminded_at })
+                if now >= *reminded_at + self.escalate_after =>
+            {
+                Some(ReminderStep::Escalate {
+                    reminded_at: *reminded_at,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reminds the actors of unjustified critical bypasses and escalates the bypasses that stay
+/// unjustified. `repository_full_name` limits the events to a repository, otherwise events across
+/// the organization are checked.
+pub async fn remind_unjustified(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    repository_full_name: Option<&str>,
+) -> Result<()> {
+    let Some(reminder_config) = &config.reminders else {
+        return Ok(());
+    };
+
+    let unjustified = bot
+        .find_unjustified_rule_suites(repository_full_name)
+        .await?;
+    for event in unjustified {
+        if let Err(e) = remind(bot, config, reminder_config, slack, &event).await {
+            tracing::warn!(
+                "Failed to remind about rule suite ID {}: {e:#}",
+                event.github_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn remind(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    reminder_config: &ReminderConfig,
+    slack: &dyn SlackClient,
+    event: &GithubRuleSuiteEvent,
+) -> Result<()> {
+    let decision: PolicyDecision = serde_json::from_str(
+        event
+            .decision
+            .as_deref()
+            .context("rule suite event has no decision")?,
+    )?;
+    // A bypass marked as false positive needs no justification, and an actor who acknowledged
+    // it already responded
+    if !decision.critical
+        || has_action(event, ActionKind::FalsePositive)?
+        || has_action(event, ActionKind::Acknowledge)?
+    {
+        return Ok(());
+    }
+
+    let messages: Vec<PostedMessage> = serde_json::from_str(
+        event
+            .slack_messages
+            .as_deref()
+            .context("rule suite event has no Slack messages")?,
+    )?;
+    let Some(first) = messages.first() else {
+        return Ok(());
+    };
+    let notified_at = ts_to_datetime(&first.ts)?;
+    let status = event
+        .reminder
+        .as_deref()
+        .map(serde_json::from_str::<ReminderStatus>)
+        .transpose()?;
+
+    let now = Utc::now();
+    let Some(step) = reminder_config.next_step(notified_at, status.as_ref(), now) else {
+        return Ok(());
+    };
+
+    let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+    // Like the notification, the reminder goes to the fallback recipient if the actor is unknown
+    // to Slack. Without any recipient, nobody could justify the bypass.
+    let recipients = actor_recipients(&suite, slack, bot, config).await.users;
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let status = match step {
+        ReminderStep::Remind => {
+            for recipient in recipients {
+                send_reminder(slack, config, reminder_config, &suite, recipient).await?;
+            }
+            ReminderStatus::Reminded { reminded_at: now }
+        }
+        ReminderStep::Escalate { reminded_at } => {
+            send_unjustified_escalation(slack, config, reminder_config, &suite).await?;
+            ReminderStatus::Escalated {
+                reminded_at,
+                escalated_at: now,
+            }
+        }
+    };
+
+    bot.set_rule_suite_reminder(event.id, serde_json::to_string(&status)?)
+        .await
+}
+
+async fn send_reminder(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    reminder_config: &ReminderConfig,
+    suite: &RuleSuite,
+    recipient: SlackUserId,
+) -> Result<()> {
+    let text = format!(
+        "The <{}|push> of `{}` to `{}` in `{}/{}` bypassed a ruleset and was not justified within \
+         {}. Please reply in the thread of the notification and explain why the bypass was \
+         necessary.",
+        suite.get_commit_url(config),
+        suite.actor_name.as_deref().unwrap_or("unknown"),
+        suite.ref_name,
+        config.github_org,
+        suite.repository_name,
+        format_window(reminder_config.remind_after),
+    );
+
+    slack
+        .post_message_user(
+            recipient,
+            build_text_message("Bypass Justification Reminder", text),
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(())
+}
+
+async fn send_unjustified_escalation(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    reminder_config: &ReminderConfig,
+    suite: &RuleSuite,
+) -> Result<()> {
+    let owners = resolve_contacts(slack, &reminder_config.security_owners).await;
+    let mut text = format!(
+        "The critical <{}|push> of `{}` to `{}` in `{}/{}` bypassed a ruleset and is still not \
+         justified.",
+        suite.get_commit_url(config),
+        suite.actor_name.as_deref().unwrap_or("unknown"),
+        suite.ref_name,
+        config.github_org,
+        suite.repository_name,
+    );
+    if !owners.is_empty() {
+        let mentions = owners
+            .iter()
+            .map(|owner| format!("<@{owner}>"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        text.push_str(&format!(" {mentions}, please follow up with the actor."));
+    }
+
+    slack
+        .post_message_channel(
+            SlackChannelId::new(config.slack_soc2_channel.to_string()),
+            build_text_message("Bypass Not Justified", text),
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta};
+
+    use super::{ReminderConfig, ReminderStatus, ReminderStep};
+
+    #[test]
+    fn reminder_steps() {
+        let config = ReminderConfig::default();
+        let notified_at = DateTime::from_timestamp(1_700_000_000, 0).expect("valid timestamp");
+        let reminded_at = notified_at + TimeDelta::days(1);
+        let reminded = ReminderStatus::Reminded { reminded_at };
+        let escalated = ReminderStatus::Escalated {
+            reminded_at,
+            escalated_at: reminded_at + TimeDelta::days(2),
+        };
+
+        assert_eq!(
+            config.next_step(notified_at, None, notified_at + TimeDelta::hours(23)),
+            None
+        );
+        assert_eq!(
+            config.next_step(notified_at, None, reminded_at),
+            Some(ReminderStep::Remind)
+        );
+        assert_eq!(
+            config.next_step(
+                notified_at,
+                Some(&reminded),
+                reminded_at + TimeDelta::days(1)
+            ),
+            None
+        );
+        assert_eq!(
+            config.next_step(
+                notified_at,
+                Some(&reminded),
+                reminded_at + TimeDelta::days(2)
+            ),
+            Some(ReminderStep::Escalate { reminded_at })
+        );
+        assert_eq!(
+            config.next_step(
+                notified_at,
+                Some(&escalated),
+                reminded_at + TimeDelta::days(30)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,531 @@
+//! Retroactive review of review requirement bypasses.
+//!
+//! Pushing without an approved pull request, e.g. directly to `main`, can be made up for by an
+//! approving review after the fact. Bypasses of `pull_request` rules are pending until one of
+//! the following approves the pushed commit within [`RetroactiveReviewConfig::window`]:
+//!
+//! - a review of an associated pull request,
+//! - a review of a follow-up pull request whose title or body contains
+//!   [`RetroactiveReviewConfig::follow_up_marker`] and the commit SHA,
+//! - a commit comment containing [`RetroactiveReviewConfig::approval_comment`].
+//!
+//! Only approvals by owners, members and collaborators of the repository count, see
+//! [`TRUSTED_AUTHOR_ASSOCIATIONS`], and not those by the pushing actor or by bots. Approved bypasses
+//! are resolved, bypasses marked as false positive are dismissed, and the others are escalated to
+//! the SOC2 channel once the window has passed.
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use octocrab::models::pulls::PullRequest;
+use serde::{Deserialize, Serialize};
+use slack_morphism::prelude::*;
+
+use crate::{
+    BotConfig, GitHubApi, GithubRuleSuiteEvent, RulesetBot, SlackClient,
+    soc2::{
+        action::{ActionKind, has_action},
+        build_text_message,
+        rule_suit::RuleSuite,
+    },
+};
+
+/// How bypasses are retroactively reviewed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetroactiveReviewConfig {
+    /// How long after the push an approving review resolves the bypass
+    pub window: TimeDelta,
+    /// Identifies follow-up pull requests reviewing a push, matched case-insensitively
+    pub follow_up_marker: String,
+    /// A commit comment containing this text approves the commit, matched case-insensitively
+    pub approval_comment: String,
+}
+
+impl Default for RetroactiveReviewConfig {
+    fn default() -> Self {
+        Self {
+            window: TimeDelta::days(7),
+            follow_up_marker: "post-merge review".to_string(),
+            approval_comment: "post-merge review: approved".to_string(),
+        }
+    }
+}
+
+/// The `author_association`s of accounts that may approve a push. Anyone can review a pull
+/// request or comment on a commit of a public repository.
+pub const TRUSTED_AUTHOR_ASSOCIATIONS: [&str; 3] = ["OWNER", "MEMBER", "COLLABORATOR"];
+
+/// The account of a review or comment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitHubAccount {
+    pub login: String,
+    pub id: i64,
+}
+
+/// A pull request review, see
+/// https://docs.github.com/en/rest/pulls/reviews?apiVersion=2022-11-28#list-reviews-for-a-pull-request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestReview {
+    pub id: i64,
+    pub user: Option<GitHubAccount>,
+    /// `APPROVED`, `CHANGES_REQUESTED`, `COMMENTED` or `DISMISSED`, among others
+    pub state: String,
+    /// Not set for pending reviews
+    #[serde(default)]
+    pub submitted_at: Option<DateTime<Utc>>,
+    /// The relation of the reviewer to the repository, e.g. `MEMBER` or `CONTRIBUTOR`
+    #[serde(default)]
+    pub author_association: Option<String>,
+    pub html_url: String,
+}
+
+/// A commit comment, see
+/// https://docs.github.com/en/rest/commits/comments?apiVersion=2022-11-28#list-commit-comments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitComment {
+    pub id: i64,
+    pub user: Option<GitHubAccount>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    /// The relation of the commenter to the repository, e.g. `MEMBER` or `CONTRIBUTOR`
+    #[serde(default)]
+    pub author_association: Option<String>,
+    pub html_url: String,
+}
+
+/// Where a retroactive approval was given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalSource {
+    /// A review of a pull request associated with the pushed commit
+    PullRequest,
+    /// A review of a follow-up pull request
+    FollowUpPullRequest,
+    CommitComment,
+}
+
+/// An approving review of a bypass after the fact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetroactiveApproval {
+    pub source: ApprovalSource,
+    /// The login of the reviewer
+    pub reviewer: String,
+    pub approved_at: DateTime<Utc>,
+    /// Link to the review or comment
+    pub url: String,
+}
+
+/// The retroactive review status of a bypass, see [`GithubRuleSuiteEvent::review`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReviewStatus {
+    /// Waiting for an approving review until the deadline
+    Pending { deadline: DateTime<Utc> },
+    /// Resolved by an approving review
+    Approved(RetroactiveApproval),
+    /// Not approved before the deadline and escalated to the SOC2 channel
+    Escalated {
+        deadline: DateTime<Utc>,
+        escalated_at: DateTime<Utc>,
+    },
+    /// Marked as false positive on the notification, no review is needed
+    Dismissed { dismissed_at: DateTime<Utc> },
+}
+
+impl ReviewStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending { .. })
+    }
+}
+
+/// Whether a bypass needs a retroactive review, i.e. a `pull_request` rule was bypassed
+pub fn requires_retroactive_review(suite: &RuleSuite) -> bool {
+    suite.any(|eval| eval.is_failed() && eval.rule_type == "pull_request")
+}
+
+/// Whether an account may retroactively approve the push of the actor
+fn is_independent_reviewer(
+    account: Option<&GitHubAccount>,
+    author_association: Option<&str>,
+    suite: &RuleSuite,
+) -> bool {
+    author_association.is_some_and(|association| TRUSTED_AUTHOR_ASSOCIATIONS.contains(&association))
+        && account.is_some_and(|account| {
+            !account.login.ends_with("[bot]") && suite.actor_name.as_ref() != Some(&account.login)
+        })
+}
+
+/// The earliest review approving the push between the push and the deadline
+pub fn approving_review(
+    reviews: &[PullRequestReview],
+    suite: &RuleSuite,
+    deadline: DateTime<Utc>,
+    source: ApprovalSource,
+) -> Option<RetroactiveApproval> {
+    reviews
+        .iter()
+        .filter(|review| review.state == "APPROVED")
+        .filter(|review| {
+            is_independent_reviewer(
+                review.user.as_ref(),
+                review.author_association.as_deref(),
+                suite,
+            )
+        })
+        .filter_map(|review| {
+            let approved_at = review.submitted_at?;
+            let reviewer = review.user.as_ref()?.login.clone();
+            (suite.pushed_at..=deadline)
+                .contains(&approved_at)
+                .then(|| RetroactiveApproval {
+                    source,
+                    reviewer,
+                    approved_at,
+                    url: review.html_url.clone(),
+                })
+        })
+        .min_by_key(|approval| approval.approved_at)
+}
+
+/// The earliest commit comment approving the push between the push and the deadline
+pub fn approving_comment(
+    comments: &[CommitComment],
+    suite: &RuleSuite,
+    deadline: DateTime<Utc>,
+    config: &RetroactiveReviewConfig,
+) -> Option<RetroactiveApproval> {
+    let approval_comment = config.approval_comment.to_lowercase();
+
+    comments
+        .iter()
+        .filter(|comment| comment.body.to_lowercase().contains(&approval_comment))
+        .filter(|comment| {
+            is_independent_reviewer(
+                comment.user.as_ref(),
+                comment.author_association.as_deref(),
+                suite,
+            )
+        })
+        .filter(|comment| (suite.pushed_at..=deadline).contains(&comment.created_at))
+        .filter_map(|comment| {
+            Some(RetroactiveApproval {
+                source: ApprovalSource::CommitComment,
+                reviewer: comment.user.as_ref()?.login.clone(),
+                approved_at: comment.created_at,
+                url: comment.html_url.clone(),
+            })
+        })
+        .min_by_key(|approval| approval.approved_at)
+}
+
+/// Whether a pull request is a follow-up review of the push: its title or body contains the
+/// marker and its body or title references the pushed commit by full or abbreviated SHA.
+pub fn is_follow_up_pull_request(
+    pr: &PullRequest,
+    suite: &RuleSuite,
+    config: &RetroactiveReviewConfig,
+) -> bool {
+    let text = format!(
+        "{}\n{}",
+        pr.title.as_deref().unwrap_or_default(),
+        pr.body.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    let short_sha = suite.after_sha.get(..7).unwrap_or(&suite.after_sha);
+
+    text.contains(&config.follow_up_marker.to_lowercase())
+        && text.contains(&short_sha.to_lowercase())
+}
+
+/// Looks for the earliest retroactive approval of the push in all sources.
+async fn find_approval(
+    github: &dyn GitHubApi,
+    config: &BotConfig,
+    review_config: &RetroactiveReviewConfig,
+    suite: &RuleSuite,
+    prs: &[PullRequest],
+    deadline: DateTime<Utc>,
+) -> Result<Option<RetroactiveApproval>> {
+    let owner = &config.github_org;
+    let repo = &suite.repository_name;
+    let mut approvals = Vec::new();
+
+    for pr in prs {
+        let reviews = github
+            .list_pull_request_reviews(owner, repo, pr.number)
+            .await?;
+        approvals.extend(approving_review(
+            &reviews,
+            suite,
+            deadline,
+            ApprovalSource::PullRequest,
+        ));
+    }
+
+    // Pushes without a pull request are reviewed in a follow-up pull request.
+    if prs.is_empty() {
+        // Follow-up pull requests are opened after the push.
+        for pr in github
+            .list_pull_requests(owner, repo, suite.pushed_at)
+            .await?
+        {
+            if !is_follow_up_pull_request(&pr, suite, review_config) {
+                continue;
+            }
+            let reviews = github
+                .list_pull_request_reviews(owner, repo, pr.number)
+                .await?;
+            approvals.extend(approving_review(
+                &reviews,
+                suite,
+                deadline,
+                ApprovalSource::FollowUpPullRequest,
+            ));
+        }
+    }
+
+    let comments = github
+        .list_commit_comments(owner, repo, &suite.after_sha)
+        .await?;
+    approvals.extend(approving_comment(&comments, suite, deadline, review_config));
+
+    Ok(approvals
+        .into_iter()
+        .min_by_key(|approval| approval.approved_at))
+}
+
+/// Checks the bypasses awaiting a retroactive review, optionally only those of one repository.
+/// Approved bypasses are resolved, bypasses past their deadline are escalated to the SOC2
+/// channel. A failure to check one bypass does not stop the others, it is retried with the next
+/// check.
+#[tracing::instrument(skip(bot, config, slack, github))]
+pub async fn check_retroactive_reviews(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    repository_full_name: Option<&str>,
+) -> Result<()> {
+    let Some(review_config) = &config.retroactive_review else {
+        return Ok(());
+    };
+
+    let pending = bot.find_pending_reviews().await?;
+    for event in pending {
+        if repository_full_name.is_some_and(|name| name != event.repository_full_name) {
+            continue;
+        }
+
+        if let Err(e) =
+            check_retroactive_review(bot, config, review_config, slack, github, &event).await
+        {
+            tracing::warn!(
+                "Failed to check the retroactive review of rule suite ID {}: {e:#}",
+                event.github_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn check_retroactive_review(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    review_config: &RetroactiveReviewConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    event: &GithubRuleSuiteEvent,
+) -> Result<()> {
+    let review: ReviewStatus = serde_json::from_str(
+        event
+            .review
+            .as_deref()
+            .context("rule suite event has no review status")?,
+    )?;
+    let ReviewStatus::Pending { deadline } = review else {
+        return Ok(());
+    };
+
+    if has_action(event, ActionKind::FalsePositive)? {
+        let status = ReviewStatus::Dismissed {
+            dismissed_at: Utc::now(),
+        };
+        return bot
+            .set_rule_suite_review(event.id, serde_json::to_string(&status)?)
+            .await;
+    }
+
+    let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+    let prs = event
+        .prs
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<Vec<PullRequest>>(json).ok())
+        .unwrap_or_default();
+
+    let status = match find_approval(github, config, review_config, &suite, &prs, deadline).await? {
+        Some(approval) => {
+            tracing::info!(
+                "Rule suite {} was retroactively approved by {} at {}",
+                suite.id,
+                approval.reviewer,
+                approval.url
+            );
+            ReviewStatus::Approved(approval)
+        }
+        None if Utc::now() > deadline => {
+            send_unreviewed_escalation(slack, config, review_config, &suite).await?;
+            ReviewStatus::Escalated {
+                deadline,
+                escalated_at: Utc::now(),
+            }
+        }
+        None => return Ok(()),
+    };
+
+    bot.set_rule_suite_review(event.id, serde_json::to_string(&status)?)
+        .await
+}
+
+/// Formats the review window in days, or hours if shorter than a day
+pub fn format_window(window: TimeDelta) -> String {
+    if window.num_days() > 0 {
+        format!("{} day(s)", window.num_days())
+    } else {
+        format!("{} hour(s)", window.num_hours())
+    }
+}
+
+/// Escalates a bypass that was not approved within the review window to the SOC2 channel.
+async fn send_unreviewed_escalation(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    review_config: &RetroactiveReviewConfig,
+    suite: &RuleSuite,
+) -> Result<()> {
+    let text = format!(
+        "The <{}|push> of `{}` to `{}` in `{}/{}` bypassed the review requirement and was not \
+         approved within {}. Please review the changes.",
+        suite.get_commit_url(config),
+        suite.actor_name.as_deref().unwrap_or("unknown"),
+        suite.ref_name,
+        config.github_org,
+        suite.repository_name,
+        format_window(review_config.window),
+    );
+
+    let content = build_text_message("Bypass Not Reviewed", text);
+
+    slack
+        .post_message_channel(
+            SlackChannelId::new(config.slack_soc2_channel.to_string()),
+            content,
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeDelta;
+
+    use super::{
+        ApprovalSource, CommitComment, GitHubAccount, PullRequestReview, RetroactiveReviewConfig,
+        approving_comment, approving_review, is_follow_up_pull_request,
+        requires_retroactive_review,
+    };
+    use crate::soc2::rule_suit::RuleSuite;
+
+    fn load_fixture(name: &str) -> String {
+        fs::read_to_string(format!("tests/fixtures/{name}"))
+            .unwrap_or_else(|_| panic!("Fixture {name} not found"))
+    }
+
+    #[test]
+    fn retroactive_approvals() {
+        let suite: RuleSuite =
+            serde_json::from_str(&load_fixture("bypass_review_requirement.json"))
+                .expect("valid rule suite");
+        assert!(requires_retroactive_review(&suite));
+        let config = RetroactiveReviewConfig::default();
+        let deadline = suite.pushed_at + config.window;
+
+        let reviews: Vec<PullRequestReview> =
+            serde_json::from_str(&load_fixture("pull_request_reviews.json")).expect("valid");
+        let approval = approving_review(&reviews, &suite, deadline, ApprovalSource::PullRequest)
+            .expect("should be approved");
+        // The approvals of the actor, before the push and of an outside account do not count.
+        assert_eq!(approval.reviewer, "jessfraz");
+        assert_eq!(approval.source, ApprovalSource::PullRequest);
+        assert!(
+            approving_review(
+                &reviews,
+                &suite,
+                suite.pushed_at + TimeDelta::hours(1),
+                ApprovalSource::PullRequest
+            )
+            .is_none()
+        );
+
+        let comments: Vec<CommitComment> =
+            serde_json::from_str(&load_fixture("commit_comments.json")).expect("valid");
+        let approval =
+            approving_comment(&comments, &suite, deadline, &config).expect("should be approved");
+        // The comment of an outside account does not count.
+        assert_eq!(approval.reviewer, "jessfraz");
+        assert_eq!(approval.source, ApprovalSource::CommitComment);
+
+        let follow_up = serde_json::from_str(&load_fixture("pull_request_post_merge_review.json"))
+            .expect("valid pull request");
+        assert!(is_follow_up_pull_request(&follow_up, &suite, &config));
+        let other = serde_json::from_str(&load_fixture("pull_request_policy_exception.json"))
+            .expect("valid pull request");
+        assert!(!is_follow_up_pull_request(&other, &suite, &config));
+    }
+
+    #[test]
+    fn outside_approvals_are_rejected() {
+        let suite: RuleSuite =
+            serde_json::from_str(&load_fixture("bypass_review_requirement.json"))
+                .expect("valid rule suite");
+        let deadline = suite.pushed_at + TimeDelta::days(7);
+        let review = |author_association: Option<&str>| PullRequestReview {
+            id: 1,
+            user: Some(GitHubAccount {
+                login: "someone".to_string(),
+                id: 1,
+            }),
+            state: "APPROVED".to_string(),
+            submitted_at: Some(suite.pushed_at + TimeDelta::hours(1)),
+            author_association: author_association.map(str::to_string),
+            html_url: "https://github.com/KittyCAD/modeling-app/pull/1#pullrequestreview-1"
+                .to_string(),
+        };
+
+        for association in [None, Some("NONE"), Some("CONTRIBUTOR"), Some("FIRST_TIMER")] {
+            assert!(
+                approving_review(
+                    &[review(association)],
+                    &suite,
+                    deadline,
+                    ApprovalSource::PullRequest
+                )
+                .is_none()
+            );
+        }
+        for association in ["OWNER", "MEMBER", "COLLABORATOR"] {
+            assert!(
+                approving_review(
+                    &[review(Some(association))],
+                    &suite,
+                    deadline,
+                    ApprovalSource::PullRequest
+                )
+                .is_some()
+            );
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use std::fmt::{Display, Formatter};
 
-use crate::BotConfig;
-use crate::soc2::asset_level::AssetLevel;
-use chrono::{DateTime, Utc};
-use octocrab::models::{pulls::PullRequest, repos::RepoCommit};
+use crate::notify::{ViolatedRule, Violation, ViolationField};
+use crate::soc2::ci::{CI_RULE_TYPES, CiChecks};
+use crate::soc2::force_push::DiscardedCommits;
+use crate::soc2::policy::PolicyDecision;
+use crate::{BotConfig, RuleSuiteFilter};
+use chrono::{DateTime, TimeDelta, Utc};
+use octocrab::models::pulls::PullRequest;
 use serde::{Deserialize, Serialize};
-use slack_morphism::prelude::*;
+use slack_morphism::SlackUser;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuleSuite {
     pub id: i64,
 
@@ -33,59 +36,16 @@ pub struct RuleSuite {
 }
 
 impl RuleSuite {
-    pub fn call_out_violation(
-        &self,
-        asset_level: AssetLevel,
-        resulting_commit: Option<RepoCommit>,
-        pr: Option<PullRequest>,
-        config: &BotConfig,
-    ) -> bool {
-        if config.callout_asset_level.contains(&asset_level) {
-            let is_review_force_push_violation =
-                self.any(|eval| eval.is_block_force_push_bypass(config));
-
-            if is_review_force_push_violation {
-                return true;
-            } else {
-                // Ignore CO violations for now. Not SOC2 relevant. Send as DM.
-                // let is_codeowner_violation = self.any(|eval| eval.is_codeowners_bypass(config));
-                // Ignore CIO violations for now. Send as DM.
-                // let is_ci_violation = false;
-
-                let is_review_violation =
-                    self.any(|eval| eval.is_review_requirement_bypass(config));
-                let is_branch_protection =
-                    self.any(|eval| match eval.rule_source.evaluated_rule_source() {
-                        EvaluatedRuleSource::Ruleset { .. } => false,
-                        EvaluatedRuleSource::ProtectedBranch => true,
-                        // coulde be a branch protection one
-                        EvaluatedRuleSource::Unknown { .. } => true,
-                    });
-
-                if is_branch_protection {
-                    return true;
-                }
-
-                let is_dependabot_pr = resulting_commit
-                    .and_then(|commit| commit.author)
-                    .map(|author| author.id.0 == 49699333 && author.login == "dependabot[bot]")
-                    .unwrap_or(false);
-                let is_policy_exception_label = pr
-                    .map(|pr| {
-                        pr.labels
-                            .iter()
-                            .flatten()
-                            .any(|label| label.name.contains("policy-exception"))
-                    })
-                    .unwrap_or(false);
-
-                if is_review_violation && !is_dependabot_pr && !is_policy_exception_label {
-                    return true;
-                }
+    /// The categories of the reported rules, in order of first occurrence
+    pub fn categories(&self, config: &BotConfig) -> Vec<ViolationCategory> {
+        let mut categories = Vec::new();
+        for eval in self.rule_evaluations.iter().flatten() {
+            let category = eval.category(config);
+            if eval.is_reported() && !categories.contains(&category) {
+                categories.push(category);
             }
         }
-
-        false
+        categories
     }
 
     /// Returns true if any rule evaluation satisfies the predicate. This ignored successful evaluations.
@@ -106,10 +66,30 @@ impl RuleSuite {
             })
             .unwrap_or(false)
     }
+
+    /// Whether the pull request was merged into the ref of the suite
+    pub fn is_merged_into_ref(&self, pr: &PullRequest) -> bool {
+        pr.merged_at.is_some() && self.ref_name == format!("refs/heads/{}", pr.base.ref_field)
+    }
+
+    /// Orders the associated pull requests of the pushed commit, those merged into the ref of the
+    /// suite first. The order is otherwise kept.
+    pub fn sort_pull_requests(&self, prs: &mut [PullRequest]) {
+        prs.sort_by_key(|pr| !self.is_merged_into_ref(pr));
+    }
+
+    /// The pull request that landed the pushed commit, i.e. was merged into the ref of the suite
+    /// with the pushed commit as merge commit. Only it can carry exceptions like labels.
+    pub fn landing_pull_request<'a>(&self, prs: &'a [PullRequest]) -> Option<&'a PullRequest> {
+        prs.iter().find(|pr| {
+            self.is_merged_into_ref(pr) && pr.merge_commit_sha.as_ref() == Some(&self.after_sha)
+        })
+    }
+
     pub fn get_commit_url(&self, config: &BotConfig) -> String {
         format!(
             "{base}/{org}/{repo}/commit/{sha}",
-            base = config.github_web_base_url,
+            base = config.github_web_base_url.trim_end_matches('/'),
             org = config.github_org,
             repo = self.repository_name,
             sha = self.after_sha,
@@ -134,246 +114,196 @@ impl RuleSuite {
         })
     }
 
-    pub fn build_soc2_notification(
+    /// Describes the bypass independent of the notification channel. Links are written in
+    /// Markdown, see [`crate::notify`].
+    pub fn violation(
         &self,
-        slack_actor: &SlackUser,
-        pr: &Option<PullRequest>,
-        asset_level: AssetLevel,
+        prs: &[PullRequest],
+        ci_checks: Option<&CiChecks>,
+        discarded_commits: Option<&DiscardedCommits>,
+        decision: &PolicyDecision,
         config: &BotConfig,
-    ) -> SlackMessageContent {
-        let is_critical = config.critical_asset_levels.contains(&asset_level)
-            && if let Some(rule_evaluations) = &self.rule_evaluations {
-                rule_evaluations
-                    .iter()
-                    .any(|eval| eval.is_critical_violation(config))
-            } else {
-                false
-            };
+    ) -> Violation {
+        let commit_url = self.get_commit_url(config);
+        let mut rules = vec![];
+
+        for evaluation in self.rule_evaluations.iter().flatten() {
+            if !evaluation.is_reported() {
+                continue;
+            }
 
-        let mut blocks: Vec<SlackBlock> = Vec::new();
-        blocks.push(
-            SlackHeaderBlock {
-                block_id: None,
-                text: SlackBlockPlainText::from(format!(
-                    "{}GitHub Policy Violation",
-                    if is_critical {
-                        "Critical "
+            let mut fields = vec![
+                ViolationField {
+                    title: "Commit".to_string(),
+                    value: format!(
+                        "[`{}`]({}) in `{}`.",
+                        &self.after_sha.get(..7).unwrap_or("commit"),
+                        commit_url,
+                        self.repository_name
+                    ),
+                    short: true,
+                },
+                ViolationField {
+                    title: "Sub-type".to_string(),
+                    value: format!("**{}**", evaluation.rule_type),
+                    short: true,
+                },
+            ];
+
+            let category = evaluation.category(config);
+            if category != ViolationCategory::Other {
+                fields.push(ViolationField {
+                    title: "Category".to_string(),
+                    value: category.to_string(),
+                    short: true,
+                });
+            }
+
+            let pr_links: Vec<String> = prs
+                .iter()
+                .filter_map(|pr| {
+                    let html_url = pr.html_url.as_ref()?;
+                    Some(format!("[#{}]({})", pr.number, html_url))
+                })
+                .collect();
+            if !pr_links.is_empty() {
+                fields.push(ViolationField {
+                    title: if pr_links.len() == 1 {
+                        "Pull Request"
                     } else {
-                        "Potential "
+                        "Pull Requests"
                     }
-                ))
-                .into(),
+                    .to_string(),
+                    value: pr_links.join(", "),
+                    short: false,
+                });
             }
-            .into(),
-        );
-
-        let summary = if is_critical {
-            format!(
-                "<@{}>, please leave a comment in the thread why the below rules were violated.",
-                slack_actor.id.0,
-            )
-        } else {
-            format!(
-                "<@{}>, please make sure no security policy has been violated. No need to comment.",
-                slack_actor.id.0,
-            )
-        };
 
-        blocks.push(
-            SlackSectionBlock {
-                block_id: None,
-                text: Some(SlackBlockText::MarkDown(SlackBlockMarkDownText::from(
-                    summary.clone(),
-                ))),
-                fields: None,
-                accessory: None,
-            }
-            .into(),
-        );
-
-        let actor = format!(
-            "*Actor*\n{}",
-            self.actor_name.clone().unwrap_or("Unknown".to_string())
-        );
-
-        blocks.push(
-            SlackSectionBlock {
-                block_id: None,
-                text: Some(SlackBlockText::MarkDown(SlackBlockMarkDownText::from(
-                    actor,
-                ))),
-                fields: None,
-                accessory: None,
+            if let Some(details) = &evaluation.details {
+                fields.push(ViolationField {
+                    title: "Details".to_string(),
+                    value: details.clone(),
+                    short: false,
+                });
             }
-            .into(),
-        );
 
-        let mut attachments = vec![];
+            if category == ViolationCategory::Ci
+                && let Some(ci_checks) = ci_checks
+            {
+                fields.push(ViolationField {
+                    title: "Checks".to_string(),
+                    value: ci_checks.to_string(),
+                    short: false,
+                });
+            }
 
-        if let Some(rule_evaluations) = &self.rule_evaluations {
-            for evaluation in rule_evaluations {
-                if !evaluation.is_failed() {
-                    continue;
-                }
+            if evaluation.is_force_push_rule()
+                && let Some(discarded_commits) = discarded_commits
+            {
+                fields.push(ViolationField {
+                    title: "Discarded Commits".to_string(),
+                    value: discarded_commits.to_string(),
+                    short: false,
+                });
+            }
 
-                let commit_url = self.get_commit_url(config);
-
-                let mut fields: Vec<SlackMessageAttachmentFieldObject> = vec![
-                    SlackMessageAttachmentFieldObject {
-                        title: Some("Commit".to_string()),
-                        value: Some(format!(
-                            "<{}|`{}`> in `{}`.",
-                            commit_url,
-                            &self.after_sha.get(..7).unwrap_or("commit"),
-                            self.repository_name
-                        )),
-                        short: Some(true),
-                    },
-                    SlackMessageAttachmentFieldObject {
-                        title: Some("Sub-type".to_string()),
-                        value: Some(format!("*{}*", evaluation.rule_type)),
-                        short: Some(true),
-                    },
-                ];
-
-                if let Some(PullRequest {
-                    number,
-                    html_url: Some(html_url),
-                    ..
-                }) = &pr
+            let (title, value) = match evaluation.rule_source.evaluated_rule_source() {
+                EvaluatedRuleSource::Ruleset { name, .. }
+                    if evaluation.enforcement == Enforcement::DeletedRuleset =>
                 {
-                    fields.push(SlackMessageAttachmentFieldObject {
-                        title: Some("Pull Request".to_string()),
-                        value: Some(format!("<{}|#{}>", html_url, number)),
-                        short: Some(false),
-                    });
-                }
-
-                if let Some(details) = &evaluation.details {
-                    fields.push(SlackMessageAttachmentFieldObject {
-                        title: Some("Details".to_string()),
-                        value: Some(details.clone()),
-                        short: Some(false),
-                    });
+                    ("Ruleset", format!("{name} (deleted)"))
                 }
-
-                let color = evaluation.attachment_color(config).to_string();
-
-                match evaluation.rule_source.evaluated_rule_source() {
-                    EvaluatedRuleSource::Ruleset { name, id } => {
-                        fields.push(SlackMessageAttachmentFieldObject {
-                            title: Some("Ruleset".to_string()),
-                            value: Some(format!(
-                                // TODO this url might be broken if its a repo ruleset
-                                "<https://github.com/organizations/KittyCAD/settings/rules/{id}|{name}>",
-                            )),
-                            short: Some(false),
-                        });
-                    }
-                    EvaluatedRuleSource::ProtectedBranch => {
-                        fields.push(SlackMessageAttachmentFieldObject {
-                            title: Some("Source".to_string()),
-                            value: Some("branch protection".to_string()),
-                            short: Some(false),
-                        });
-                    }
-                    EvaluatedRuleSource::Unknown { typ, .. } => {
-                        fields.push(SlackMessageAttachmentFieldObject {
-                            title: Some("Source".to_string()),
-                            value: Some(typ.to_string()),
-                            short: Some(false),
-                        });
-                    }
-                }
-
-                attachments.push(SlackMessageAttachment {
-                    id: None,
-                    color: Some(color),
-                    fallback: Some("no fallback".to_string()),
-                    title: None,
-                    fields: Some(fields),
-                    mrkdwn_in: Some(vec!["fields".to_string()]),
-                    text: None,
-                    blocks: None,
-                });
-            }
+                EvaluatedRuleSource::Ruleset { name, id } => (
+                    "Ruleset",
+                    format!(
+                        // TODO this url might be broken if its a repo ruleset
+                        "[{name}]({base}/organizations/{org}/settings/rules/{id})",
+                        base = config.github_web_base_url.trim_end_matches('/'),
+                        org = config.github_org,
+                    ),
+                ),
+                EvaluatedRuleSource::ProtectedBranch => ("Source", "branch protection".to_string()),
+                EvaluatedRuleSource::Unknown { typ, .. } => ("Source", typ),
+            };
+            fields.push(ViolationField {
+                title: title.to_string(),
+                value,
+                short: false,
+            });
+
+            rules.push(ViolatedRule {
+                rule_type: evaluation.rule_type.clone(),
+                critical: evaluation.is_critical_violation(config),
+                fields,
+            });
         }
 
-        let fallback = format!("{summary}\n\n{self}");
-
-        SlackMessageContent {
-            text: Some(fallback),
-            blocks: Some(blocks),
-            attachments: Some(attachments),
-            upload: None,
-            files: None,
-            reactions: None,
-            metadata: None,
+        Violation {
+            rule_suite_id: self.id,
+            critical: decision.critical,
+            actor: self.actor_name.clone(),
+            repository: self.repository_name.clone(),
+            ref_name: self.ref_name.clone(),
+            commit_sha: self.after_sha.clone(),
+            commit_url,
+            description: self.description(config),
+            explanation: decision.explanation(),
+            rules,
         }
     }
-}
 
-impl Display for RuleSuite {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    /// Describes the failed rules of the bypass in Markdown, the text of [`Violation`]
+    pub fn description(&self, config: &BotConfig) -> String {
         if self.result != RuleOutcome::Bypass {
-            return writeln!(f, "Non-bypass rule must not be evaluated.");
+            return "Non-bypass rule must not be evaluated.\n".to_string();
         }
+        let Some(rule_evaluations) = &self.rule_evaluations else {
+            return "Bypass without rule evaluations.\n".to_string();
+        };
 
-        let mut no_failures = true;
-
-        if let Some(rule_evaluations) = &self.rule_evaluations {
-            for evaluation in rule_evaluations {
-                if evaluation.result != RuleEvalResult::Fail {
-                    continue;
-                }
-
-                no_failures = false;
+        let mut description = String::new();
+        let actor = self.actor_name.as_deref().unwrap_or("unknown");
+        for evaluation in rule_evaluations {
+            if evaluation.result != RuleEvalResult::Fail {
+                continue;
+            }
 
-                let rule_type = &evaluation.rule_source.typ;
-                let sub_type = &evaluation.rule_type;
-                let actor = self.actor_name.clone().unwrap_or("unknown".to_string());
+            let rule_type = &evaluation.rule_source.typ;
+            let sub_type = &evaluation.rule_type;
 
-                write!(f, "{actor} violated rule (`{sub_type}`)")?;
+            description.push_str(&format!("{actor} violated rule (`{sub_type}`)"));
 
-                if let Some(name) = &evaluation.rule_source.name {
-                    if rule_type == "ruleset" {
-                        write!(f, " from ruleset `{name}`")?;
-                    } else {
-                        write!(f, " from `{name}`")?;
-                    }
+            if let Some(name) = &evaluation.rule_source.name {
+                if evaluation.enforcement == Enforcement::DeletedRuleset {
+                    description.push_str(&format!(" from deleted ruleset `{name}`"));
+                } else if rule_type == "ruleset" {
+                    description.push_str(&format!(" from ruleset `{name}`"));
+                } else {
+                    description.push_str(&format!(" from `{name}`"));
                 }
+            }
 
-                // Note: Display trait doesn't have access to config, so we use a basic format
-                let commit_url = format!(
-                    "https://github.com/{}/commit/{}",
-                    self.repository_name, self.after_sha
-                );
-                writeln!(
-                    f,
-                    " with <{}|`{}`> in `{}`.",
-                    commit_url,
-                    &self.after_sha.get(..7).unwrap_or("commit"),
-                    self.repository_name
-                )?;
-
-                if let Some(details) = &evaluation.details {
-                    writeln!(f)?;
-                    writeln!(f, "{details}")?;
-                }
+            description.push_str(&format!(
+                " with [`{}`]({}) in `{}`.\n",
+                self.after_sha.get(..7).unwrap_or("commit"),
+                self.get_commit_url(config),
+                self.repository_name
+            ));
+
+            if let Some(details) = &evaluation.details {
+                description.push_str(&format!("\n{details}\n"));
             }
-        } else {
-            return writeln!(f, "Bypass without rule evaluations.");
         }
 
-        if no_failures {
-            writeln!(f, "Bypass with no failures.")?;
+        if description.is_empty() {
+            description.push_str("Bypass with no failures.\n");
         }
 
-        Ok(())
+        description
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum RuleOutcome {
     Pass,
@@ -381,6 +311,73 @@ pub enum RuleOutcome {
     Bypass,
 }
 
+/// The time window used when listing rule suites. GitHub defaults to [`TimePeriod::Day`].
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimePeriod {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl TimePeriod {
+    /// Returns the shortest time period that reaches back from `now` to `since`. Falls back to
+    /// [`TimePeriod::Month`], the longest period supported by GitHub.
+    pub fn covering(since: DateTime<Utc>, now: DateTime<Utc>) -> Self {
+        let elapsed = now - since;
+        if elapsed <= TimeDelta::hours(1) {
+            TimePeriod::Hour
+        } else if elapsed <= TimeDelta::days(1) {
+            TimePeriod::Day
+        } else if elapsed <= TimeDelta::weeks(1) {
+            TimePeriod::Week
+        } else {
+            TimePeriod::Month
+        }
+    }
+}
+
+/// Query parameters of the rule suites listing endpoints.
+///
+/// https://docs.github.com/en/rest/repos/rule-suites?apiVersion=2022-11-28#list-repository-rule-suites
+#[derive(Debug, Serialize, Clone)]
+pub struct RuleSuiteQuery {
+    pub time_period: TimePeriod,
+
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor_name: Option<String>,
+
+    /// `None` lists suites of all outcomes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_suite_result: Option<RuleOutcome>,
+
+    pub per_page: u8,
+
+    pub page: u32,
+}
+
+impl RuleSuiteQuery {
+    /// The maximum page size supported by GitHub.
+    pub const MAX_PER_PAGE: u8 = 100;
+
+    /// Builds the query for the first page from the configured filter.
+    pub fn first_page(filter: &RuleSuiteFilter) -> Self {
+        Self {
+            time_period: filter.time_period,
+            ref_name: filter.ref_name.clone(),
+            actor_name: filter.actor_name.clone(),
+            rule_suite_result: filter.rule_suite_result,
+            per_page: Self::MAX_PER_PAGE,
+            page: 1,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RuleEvaluation {
     pub rule_source: RuleSource,
@@ -396,20 +393,28 @@ pub struct RuleEvaluation {
 }
 
 impl RuleEvaluation {
-    pub fn attachment_color(&self, config: &BotConfig) -> &'static str {
-        if self.is_critical_violation(config) {
-            // red
-            "#E01E5A"
-        } else {
-            // orange
-            "#ECB22E"
-        }
-    }
-
     pub fn is_failed(&self) -> bool {
         self.enforcement == Enforcement::Active && self.result == RuleEvalResult::Fail
     }
 
+    /// Whether the bypassed rule belongs to a ruleset that has since been deleted. Such bypasses
+    /// were not reviewed against the ruleset while it existed.
+    pub fn is_deleted_ruleset_bypass(&self) -> bool {
+        self.enforcement == Enforcement::DeletedRuleset && self.result == RuleEvalResult::Fail
+    }
+
+    /// Whether the evaluation is reported in notifications: failed active rules and bypasses of
+    /// deleted rulesets
+    pub fn is_reported(&self) -> bool {
+        self.is_failed() || self.is_deleted_ruleset_bypass()
+    }
+
+    /// Whether the rule of a ruleset in evaluate mode failed, so it would have blocked the push
+    /// if the ruleset was active. See [`crate::soc2::shadow`].
+    pub fn would_have_failed(&self) -> bool {
+        self.enforcement == Enforcement::Evaluate && self.result == RuleEvalResult::Fail
+    }
+
     pub fn is_critical_violation(&self, config: &BotConfig) -> bool {
         self.is_review_requirement_bypass(config) || self.is_block_force_push_bypass(config)
     }
@@ -430,12 +435,77 @@ impl RuleEvaluation {
                 .unwrap_or(false)
     }
 
-    pub fn is_codeowners_bypass(&self, config: BotConfig) -> bool {
-        self.is_failed()
-            && config
-                .codeowners_ruleset_id
-                .map(|id| self.rule_source.id == Some(id))
-                .unwrap_or(false)
+    pub fn is_codeowners_bypass(&self, config: &BotConfig) -> bool {
+        self.is_failed() && self.is_codeowners_rule(config)
+    }
+
+    /// Whether the rule belongs to the configured codeowners ruleset. Other `pull_request` rules
+    /// may also require code owners, but the rule suite does not tell which ones did.
+    fn is_codeowners_rule(&self, config: &BotConfig) -> bool {
+        config
+            .codeowners_ruleset_id
+            .is_some_and(|id| self.rule_source.id == Some(id))
+    }
+
+    /// Whether the rule is enforced by CI, see [`CI_RULE_TYPES`]
+    pub fn is_ci_rule(&self) -> bool {
+        CI_RULE_TYPES.contains(&self.rule_type.as_str())
+    }
+
+    /// Whether the rule blocks force pushes, regardless of the ruleset
+    pub fn is_force_push_rule(&self) -> bool {
+        self.rule_type == "non_fast_forward"
+    }
+
+    /// Classifies the rule, regardless of its result
+    pub fn category(&self, config: &BotConfig) -> ViolationCategory {
+        let is_ruleset = |id: Option<i64>| id.is_some() && self.rule_source.id == id;
+
+        // A deleted ruleset no longer enforces anything, whatever it used to control. The rule
+        // type is more specific than the ruleset, which may also require checks.
+        if self.enforcement == Enforcement::DeletedRuleset {
+            ViolationCategory::DeletedRuleset
+        } else if self.is_ci_rule() {
+            ViolationCategory::Ci
+        } else if is_ruleset(config.block_force_push_ruleset_id) {
+            ViolationCategory::BlockForcePush
+        } else if is_ruleset(config.review_requirement_ruleset_id) {
+            ViolationCategory::ReviewRequirement
+        } else if self.is_codeowners_rule(config) {
+            ViolationCategory::Codeowners
+        } else {
+            ViolationCategory::Other
+        }
+    }
+}
+
+/// What kind of control a bypassed rule implements
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationCategory {
+    /// [`BotConfig::review_requirement_ruleset_id`]
+    ReviewRequirement,
+    /// [`BotConfig::block_force_push_ruleset_id`]
+    BlockForcePush,
+    /// A required review by code owners, see [`BotConfig::codeowners_ruleset_id`]
+    Codeowners,
+    /// Required status checks, deployments or workflows, see [`CI_RULE_TYPES`]
+    Ci,
+    /// A rule of a ruleset that has since been deleted
+    DeletedRuleset,
+    Other,
+}
+
+impl Display for ViolationCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ViolationCategory::ReviewRequirement => "Review requirement bypass",
+            ViolationCategory::BlockForcePush => "Force push",
+            ViolationCategory::Codeowners => "Code owner review bypass",
+            ViolationCategory::Ci => "CI bypass",
+            ViolationCategory::DeletedRuleset => "Bypass of a ruleset that has since been deleted",
+            ViolationCategory::Other => "Other",
+        })
     }
 }
 
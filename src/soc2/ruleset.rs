@@ -0,0 +1,65 @@
+//! Rulesets as returned by the GitHub rules API.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A repository or organization ruleset, see
+/// https://docs.github.com/en/rest/repos/rules?apiVersion=2022-11-28#get-a-repository-ruleset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub rules: Vec<RulesetRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RulesetRule {
+    #[serde(rename = "type")]
+    pub rule_type: String,
+    /// Depends on the rule type
+    #[serde(default)]
+    pub parameters: Option<Value>,
+}
+
+/// A ruleset that was deleted, e.g. as reported by a `repository_ruleset` webhook delivery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedRuleset {
+    pub id: i64,
+    pub name: String,
+    /// The repository name of a repository ruleset. `None` for organization rulesets.
+    pub repository: Option<String>,
+    /// The login of the user who deleted the ruleset
+    pub deleted_by: Option<String>,
+}
+
+impl Ruleset {
+    /// The contexts of the status checks required by the ruleset
+    pub fn required_status_checks(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.rule_type == "required_status_checks")
+            .filter_map(|rule| rule.parameters.as_ref()?.get("required_status_checks"))
+            .filter_map(Value::as_array)
+            .flatten()
+            .filter_map(|check| check.get("context")?.as_str())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Ruleset;
+
+    #[test]
+    fn ruleset_required_status_checks() {
+        let json =
+            fs::read_to_string("tests/fixtures/ruleset_required_checks.json").expect("fixture");
+        let ruleset: Ruleset = serde_json::from_str(&json).expect("valid ruleset");
+
+        assert_eq!(ruleset.required_status_checks(), ["build", "e2e", "lint"]);
+    }
+}
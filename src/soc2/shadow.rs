@@ -0,0 +1,228 @@
+//! Shadow reporting of rulesets in evaluate mode.
+//!
+//! Rules of a ruleset in evaluate mode are evaluated but never enforced, so they do not show up in
+//! bypass notifications. Their failures, including those of pushes that passed all active rules,
+//! are summarized per ruleset to judge the impact of switching the ruleset to active.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+use anyhow::{Result, anyhow};
+use slack_morphism::prelude::*;
+
+use crate::{
+    BotConfig, GitHubApi, RuleSuiteFilter, SlackClient,
+    soc2::{
+        RuleSuiteSource, build_text_message, create_octocrab, list_rule_suites,
+        rule_suit::{RuleOutcome, RuleSuite},
+    },
+};
+
+/// What a ruleset in evaluate mode would have blocked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RulesetShadowSummary {
+    pub ruleset_id: Option<i64>,
+    pub ruleset_name: String,
+    /// Pushes with at least one failed rule of the ruleset
+    pub rule_suites: usize,
+    /// Pushes that passed all active rules, so the ruleset would newly block them
+    pub newly_blocked: usize,
+    /// Failed rules by rule type
+    pub rule_types: BTreeMap<String, usize>,
+    pub repositories: BTreeSet<String>,
+    pub actors: BTreeSet<String>,
+}
+
+/// Failed evaluate-mode rules of rule suites, summarized per ruleset
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShadowReport {
+    /// Keyed by ruleset id, or name for rule sources without id
+    pub rulesets: BTreeMap<String, RulesetShadowSummary>,
+}
+
+impl ShadowReport {
+    /// Adds the failed evaluate-mode rules of a full rule suite, regardless of its outcome
+    pub fn add(&mut self, suite: &RuleSuite) {
+        let mut counted = BTreeSet::new();
+
+        for eval in suite.rule_evaluations.iter().flatten() {
+            if !eval.would_have_failed() {
+                continue;
+            }
+
+            let source = &eval.rule_source;
+            let name = source.name.clone().unwrap_or_else(|| source.typ.clone());
+            let key = source.id.map_or_else(|| name.clone(), |id| id.to_string());
+            let summary =
+                self.rulesets
+                    .entry(key.clone())
+                    .or_insert_with(|| RulesetShadowSummary {
+                        ruleset_id: source.id,
+                        ruleset_name: name,
+                        ..Default::default()
+                    });
+
+            *summary
+                .rule_types
+                .entry(eval.rule_type.clone())
+                .or_default() += 1;
+
+            // Count every push once per ruleset.
+            if counted.insert(key) {
+                summary.rule_suites += 1;
+                if suite.result == RuleOutcome::Pass {
+                    summary.newly_blocked += 1;
+                }
+                summary.repositories.insert(suite.repository_name.clone());
+                if let Some(actor) = &suite.actor_name {
+                    summary.actors.insert(actor.clone());
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rulesets.is_empty()
+    }
+}
+
+impl Display for RulesetShadowSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ruleset = match self.ruleset_id {
+            Some(id) => format!("{} ({id})", self.ruleset_name),
+            None => self.ruleset_name.clone(),
+        };
+        let rule_types = self
+            .rule_types
+            .iter()
+            .map(|(rule_type, count)| format!("`{rule_type}` ({count})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(
+            f,
+            "*{ruleset}* would have blocked {} push(es), {} of them passing all active rules, \
+             in {} repositories by {} actor(s). Failed rules: {rule_types}",
+            self.rule_suites,
+            self.newly_blocked,
+            self.repositories.len(),
+            self.actors.len(),
+        )
+    }
+}
+
+impl Display for ShadowReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("No rulesets in evaluate mode would have blocked a push.");
+        }
+
+        let lines: Vec<String> = self.rulesets.values().map(ToString::to_string).collect();
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// Builds the shadow report of all repositories in [`BotConfig::github_org`] using an
+/// [`octocrab::Octocrab`] client created from [`BotConfig::github_auth`].
+pub async fn build_shadow_report(config: &BotConfig) -> Result<ShadowReport> {
+    let octocrab = create_octocrab(config)?;
+
+    build_shadow_report_with_github(config, &octocrab).await
+}
+
+/// Like [`build_shadow_report`], but talks to GitHub through the given [`GitHubApi`].
+///
+/// Rule suites of all outcomes within [`BotConfig::rule_suite_filter`] are listed. Only those
+/// that would fail if all rules were active are fetched in full.
+#[tracing::instrument(skip(config, github))]
+pub async fn build_shadow_report_with_github(
+    config: &BotConfig,
+    github: &dyn GitHubApi,
+) -> Result<ShadowReport> {
+    let filter = RuleSuiteFilter {
+        rule_suite_result: None,
+        ..config.rule_suite_filter.clone()
+    };
+    let rule_suites = list_rule_suites(
+        github,
+        &filter,
+        RuleSuiteSource::Organization(&config.github_org),
+        None,
+    )
+    .await?;
+
+    let mut report = ShadowReport::default();
+    for suite in rule_suites {
+        // Suites are listed with the evaluation result of all rules, as if they were active.
+        if suite.evaluation_result != Some(RuleOutcome::Fail) {
+            continue;
+        }
+
+        let repository_full_name = format!("{}/{}", config.github_org, suite.repository_name);
+        match github.get_rule_suite(&repository_full_name, suite.id).await {
+            Ok(full_result) => report.add(&full_result),
+            Err(e) => tracing::warn!("Failed to fetch rule suite ID {}: {e:#}", suite.id),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Posts the shadow report to the SOC2 channel
+pub async fn send_shadow_report(
+    slack: &dyn SlackClient,
+    config: &BotConfig,
+    report: &ShadowReport,
+) -> Result<()> {
+    let text = report.to_string();
+
+    let content = build_text_message("Evaluate-mode Ruleset Report", text);
+
+    slack
+        .post_message_channel(
+            SlackChannelId::new(config.slack_soc2_channel.to_string()),
+            content,
+        )
+        .await
+        .map_err(|e| anyhow!("posting a slack message failed: {e}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::ShadowReport;
+    use crate::soc2::rule_suit::RuleSuite;
+
+    fn load_suite(name: &str) -> RuleSuite {
+        let json = fs::read_to_string(format!("tests/fixtures/{name}"))
+            .unwrap_or_else(|_| panic!("Fixture {name} not found"));
+        serde_json::from_str(&json).expect("Failed to deserialize RuleSuite fixture")
+    }
+
+    #[test]
+    fn shadow_report_summarizes_per_ruleset() {
+        let mut report = ShadowReport::default();
+        report.add(&load_suite("bypass_evaluate_only.json"));
+        report.add(&load_suite("pass_evaluate_fail.json"));
+        // Only active rules failed.
+        report.add(&load_suite("bypass_force_push.json"));
+
+        assert_eq!(report.rulesets.len(), 1);
+        let summary = &report.rulesets["3757757"];
+        assert_eq!(summary.rule_suites, 2);
+        assert_eq!(summary.newly_blocked, 1);
+        assert_eq!(summary.repositories.len(), 2);
+        assert_eq!(summary.actors.len(), 2);
+        assert_eq!(
+            report.to_string(),
+            "*Default branch - reasonable (evaluate) (3757757)* would have blocked 2 push(es), \
+             1 of them passing all active rules, in 2 repositories by 2 actor(s). \
+             Failed rules: `pull_request` (2), `required_signatures` (1)"
+        );
+
+        assert!(ShadowReport::default().is_empty());
+    }
+}
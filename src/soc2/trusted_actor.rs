@@ -0,0 +1,183 @@
+//! Accounts, usually automation, that are trusted to bypass rules within a scope.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    glob,
+    soc2::rule_suit::{RuleEvalResult, RuleSuite},
+};
+
+/// A GitHub account trusted to bypass rules, e.g. a bot merging dependency updates.
+///
+/// The account is identified by all fields of `id`, `login` and `app_slug` that are set. At least
+/// one of them must be set, otherwise the entry trusts nobody.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrustedActor {
+    /// The GitHub user id, which unlike the login cannot be taken over
+    pub id: Option<i64>,
+    /// Glob pattern for the login, e.g. `zoo-*`
+    pub login: Option<String>,
+    /// The slug of a GitHub App. Apps act as the `<slug>[bot]` user.
+    pub app_slug: Option<String>,
+    /// Glob patterns for the repository names the actor may bypass rules in. Empty allows all.
+    pub repositories: Vec<String>,
+    /// The rule types the actor may bypass, e.g. `pull_request`. Empty allows all.
+    pub rule_types: Vec<String>,
+}
+
+impl TrustedActor {
+    /// Dependabot merging its own updates without review
+    pub fn dependabot() -> Self {
+        Self {
+            id: Some(49699333),
+            login: Some("dependabot[bot]".to_string()),
+            app_slug: None,
+            repositories: vec![],
+            rule_types: vec!["pull_request".to_string()],
+        }
+    }
+
+    /// Whether this entry identifies the account
+    pub fn is_account(&self, id: Option<i64>, login: Option<&str>) -> bool {
+        if self.id.is_none() && self.login.is_none() && self.app_slug.is_none() {
+            return false;
+        }
+
+        self.id.is_none_or(|expected| id == Some(expected))
+            && self
+                .login
+                .as_ref()
+                .is_none_or(|pattern| login.is_some_and(|login| glob::matches(pattern, login)))
+            && self
+                .app_slug
+                .as_ref()
+                .is_none_or(|slug| login == Some(format!("{slug}[bot]").as_str()))
+    }
+
+    /// Whether the actor may bypass a rule of the type in the repository
+    pub fn may_bypass(&self, repository_name: &str, rule_type: &str) -> bool {
+        (self.repositories.is_empty()
+            || self
+                .repositories
+                .iter()
+                .any(|pattern| glob::matches(pattern, repository_name)))
+            && (self.rule_types.is_empty() || self.rule_types.iter().any(|t| t == rule_type))
+    }
+}
+
+/// Whether the account is trusted to bypass every failed rule of the suite. An account without
+/// any failed rules is trusted if it may bypass anything in the repository.
+pub fn is_trusted(
+    trusted_actors: &[TrustedActor],
+    id: Option<i64>,
+    login: Option<&str>,
+    suite: &RuleSuite,
+) -> bool {
+    let trusted: Vec<&TrustedActor> = trusted_actors
+        .iter()
+        .filter(|actor| actor.is_account(id, login))
+        .collect();
+    if trusted.is_empty() {
+        return false;
+    }
+
+    let mut failed_rule_types = suite
+        .rule_evaluations
+        .iter()
+        .flatten()
+        .filter(|eval| eval.result == RuleEvalResult::Fail)
+        .map(|eval| eval.rule_type.as_str())
+        .peekable();
+
+    if failed_rule_types.peek().is_none() {
+        return trusted.iter().any(|actor| {
+            actor.repositories.is_empty()
+                || actor
+                    .repositories
+                    .iter()
+                    .any(|pattern| glob::matches(pattern, &suite.repository_name))
+        });
+    }
+
+    failed_rule_types.all(|rule_type| {
+        trusted
+            .iter()
+            .any(|actor| actor.may_bypass(&suite.repository_name, rule_type))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{TrustedActor, is_trusted};
+    use crate::soc2::rule_suit::RuleSuite;
+
+    fn load_suite(name: &str) -> RuleSuite {
+        let json = fs::read_to_string(format!("tests/fixtures/{name}"))
+            .unwrap_or_else(|_| panic!("Fixture {name} not found"));
+        serde_json::from_str(&json).expect("Failed to deserialize RuleSuite fixture")
+    }
+
+    #[test]
+    fn trusted_actor_identity() {
+        let dependabot = TrustedActor::dependabot();
+        assert!(dependabot.is_account(Some(49699333), Some("dependabot[bot]")));
+        // A renamed account or a lookalike login is not trusted.
+        assert!(!dependabot.is_account(Some(1), Some("dependabot[bot]")));
+        assert!(!dependabot.is_account(None, Some("dependabot[bot]")));
+
+        let app = TrustedActor {
+            app_slug: Some("zoo-github-actions-auth".to_string()),
+            ..Default::default()
+        };
+        assert!(app.is_account(Some(155849648), Some("zoo-github-actions-auth[bot]")));
+        assert!(!app.is_account(Some(155849648), Some("zoo-github-actions-auth")));
+
+        let pattern = TrustedActor {
+            login: Some("zoo-*".to_string()),
+            ..Default::default()
+        };
+        assert!(pattern.is_account(None, Some("zoo-deploy")));
+        assert!(!pattern.is_account(None, None));
+
+        assert!(!TrustedActor::default().is_account(Some(1), Some("anyone")));
+    }
+
+    #[test]
+    fn trusted_actor_scope() {
+        // Fails `pull_request` of a ruleset in `modeling-app`.
+        let suite = load_suite("bypass_review_requirement.json");
+        let id = suite.actor_id;
+        let login = suite.actor_name.as_deref();
+
+        let mut actor = TrustedActor {
+            id,
+            ..Default::default()
+        };
+        assert!(is_trusted(&[actor.clone()], id, login, &suite));
+
+        actor.repositories = vec!["website-*".to_string()];
+        assert!(!is_trusted(&[actor.clone()], id, login, &suite));
+        actor.repositories = vec!["modeling-*".to_string()];
+        assert!(is_trusted(&[actor.clone()], id, login, &suite));
+
+        actor.rule_types = vec!["non_fast_forward".to_string()];
+        assert!(!is_trusted(&[actor.clone()], id, login, &suite));
+
+        // Scopes of multiple entries for the same account add up.
+        let force_push = load_suite("bypass_force_push.json");
+        let entries = [
+            actor.clone(),
+            TrustedActor {
+                rule_types: vec!["pull_request".to_string()],
+                ..actor.clone()
+            },
+        ];
+        assert!(is_trusted(&entries, id, login, &suite));
+        assert!(is_trusted(&entries, id, login, &force_push));
+
+        assert!(!is_trusted(&entries, Some(1), Some("someone"), &suite));
+    }
+}
@@ -0,0 +1,171 @@
+//! Time-boxed exceptions from the escalation policy.
+//!
+//! A waiver approved for a repository suppresses the channel callout and the critical status of
+//! matching bypasses until it expires. The actor is still notified, and the waiver is recorded in
+//! the stored [`crate::soc2::policy::PolicyDecision`].
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    glob,
+    soc2::rule_suit::{RuleEvalResult, RuleSuite},
+};
+
+/// A stored waiver
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Waiver {
+    pub id: i32,
+    /// The repository the waiver applies to, e.g. `KittyCAD/modeling-app`
+    pub repository_full_name: String,
+    /// Glob pattern for the ref, e.g. `refs/heads/release/*`. `None` waives all refs.
+    pub ref_pattern: Option<String>,
+    /// The rule types that may be bypassed. Empty waives all rule types.
+    pub rule_types: Vec<String>,
+    /// The login of the actor who may bypass. `None` waives all actors.
+    pub actor_name: Option<String>,
+    /// Why the exception is needed
+    pub reason: String,
+    /// Who approved the exception
+    pub approver: String,
+    /// Bypasses pushed at or after this time are no longer waived
+    pub expires_at: DateTime<Utc>,
+    /// Whether bypasses pushed before the waiver was created are waived too. Otherwise a waiver
+    /// only covers bypasses from its creation on.
+    #[serde(default)]
+    pub retroactive: bool,
+    #[serde(deserialize_with = "crate::null_date_format::deserialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// New waiver to be created
+#[derive(Debug, Clone)]
+pub struct NewWaiver {
+    pub repository_full_name: String,
+    pub ref_pattern: Option<String>,
+    pub rule_types: Vec<String>,
+    pub actor_name: Option<String>,
+    pub reason: String,
+    pub approver: String,
+    pub expires_at: DateTime<Utc>,
+    pub retroactive: bool,
+}
+
+impl Waiver {
+    /// Whether the waiver covers the bypass of a rule suite in the repository. Every failed rule
+    /// must be covered, and the suite must have been pushed before the waiver expired and, unless
+    /// the waiver is retroactive, not before it was created.
+    pub fn covers(&self, repository_full_name: &str, suite: &RuleSuite) -> bool {
+        self.repository_full_name == repository_full_name
+            && (self.retroactive || suite.pushed_at >= self.created_at)
+            && suite.pushed_at < self.expires_at
+            && self
+                .ref_pattern
+                .as_ref()
+                .is_none_or(|pattern| glob::matches(pattern, &suite.ref_name))
+            && self
+                .actor_name
+                .as_ref()
+                .is_none_or(|actor| suite.actor_name.as_ref() == Some(actor))
+            && (self.rule_types.is_empty()
+                || suite
+                    .rule_evaluations
+                    .iter()
+                    .flatten()
+                    .filter(|eval| eval.result == RuleEvalResult::Fail)
+                    .all(|eval| self.rule_types.contains(&eval.rule_type)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use super::Waiver;
+    use crate::soc2::rule_suit::RuleSuite;
+
+    fn load_suite(name: &str) -> RuleSuite {
+        let json = fs::read_to_string(format!("tests/fixtures/{name}"))
+            .unwrap_or_else(|_| panic!("Fixture {name} not found"));
+        serde_json::from_str(&json).expect("Failed to deserialize RuleSuite fixture")
+    }
+
+    fn waiver(suite: &RuleSuite) -> Waiver {
+        Waiver {
+            id: 7,
+            repository_full_name: "KittyCAD/modeling-app".to_string(),
+            ref_pattern: Some("refs/heads/main".to_string()),
+            rule_types: vec!["pull_request".to_string()],
+            actor_name: Some("maxammann".to_string()),
+            reason: "Hotfix for the login outage".to_string(),
+            approver: "security@zoo.dev".to_string(),
+            expires_at: suite.pushed_at + TimeDelta::hours(1),
+            retroactive: false,
+            created_at: DateTime::<Utc>::from_timestamp(0, 0).expect("valid timestamp"),
+        }
+    }
+
+    #[test]
+    fn waiver_covers_scope() {
+        let suite = load_suite("bypass_review_requirement.json");
+        let repo = "KittyCAD/modeling-app";
+        assert!(waiver(&suite).covers(repo, &suite));
+
+        assert!(!waiver(&suite).covers("KittyCAD/website", &suite));
+        assert!(
+            !Waiver {
+                ref_pattern: Some("refs/heads/release/*".to_string()),
+                ..waiver(&suite)
+            }
+            .covers(repo, &suite)
+        );
+        assert!(
+            !Waiver {
+                actor_name: Some("someone-else".to_string()),
+                ..waiver(&suite)
+            }
+            .covers(repo, &suite)
+        );
+        assert!(
+            Waiver {
+                ref_pattern: None,
+                actor_name: None,
+                rule_types: vec![],
+                ..waiver(&suite)
+            }
+            .covers(repo, &suite)
+        );
+
+        // The force push is not covered by a waiver for reviews.
+        let force_push = load_suite("bypass_force_push.json");
+        assert!(!waiver(&force_push).covers(repo, &force_push));
+    }
+
+    #[test]
+    fn waiver_expires() {
+        let suite = load_suite("bypass_review_requirement.json");
+        let expired = Waiver {
+            expires_at: suite.pushed_at,
+            ..waiver(&suite)
+        };
+        assert!(!expired.covers("KittyCAD/modeling-app", &suite));
+    }
+
+    #[test]
+    fn waiver_covers_earlier_bypasses_only_if_retroactive() {
+        let suite = load_suite("bypass_review_requirement.json");
+        let later = Waiver {
+            created_at: suite.pushed_at + TimeDelta::minutes(5),
+            ..waiver(&suite)
+        };
+        assert!(!later.covers("KittyCAD/modeling-app", &suite));
+
+        let retroactive = Waiver {
+            retroactive: true,
+            ..later
+        };
+        assert!(retroactive.covers("KittyCAD/modeling-app", &suite));
+    }
+}
@@ -0,0 +1,449 @@
+//! Receiving Slack interactions with notification buttons.
+//!
+//! [`handle_interaction`] takes the raw headers and body of a request to the interactivity
+//! request URL of the Slack app, verifies the `X-Slack-Signature` header and returns the action,
+//! which [`apply_action`] applies, see [`crate::soc2::action`]. Applying an action looks up the
+//! event and the allowed users, which may take longer than Slack waits for the response.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use slack_morphism::{SlackChannelId, SlackMessageContent, SlackTriggerId, SlackUserId};
+
+use crate::{
+    BotConfig, GithubRuleSuiteEvent, PostedMessage, RulesetBot, SlackClient,
+    soc2::{
+        action::{
+            ActionKind, REASON_INPUT, ReasonModalMetadata, ViolationAction, reason_modal,
+            rebuild_notification,
+        },
+        actor_recipients,
+        escalation::resolve_contacts,
+        rule_suit::RuleSuite,
+    },
+    webhook::WebhookError,
+};
+
+const SIGNATURE_HEADER: &str = "x-slack-signature";
+const TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+
+/// Requests older than this are rejected as replays, in seconds
+const MAX_REQUEST_AGE: i64 = 5 * 60;
+
+/// A raw Slack interaction request
+#[derive(Debug, Clone, Copy)]
+pub struct InteractionDelivery<'a> {
+    /// The `X-Slack-Request-Timestamp` header, in seconds since the epoch
+    pub timestamp: &'a str,
+    /// The `X-Slack-Signature` header, e.g. `v0=a2114d57...`
+    pub signature: &'a str,
+    /// The form encoded body, with the JSON payload in the `payload` field
+    pub body: &'a [u8],
+}
+
+impl<'a> InteractionDelivery<'a> {
+    /// Extracts the delivery from raw HTTP headers. Header names are matched case-insensitively.
+    pub fn from_headers<I>(headers: I, body: &'a [u8]) -> Result<Self, WebhookError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut timestamp = None;
+        let mut signature = None;
+
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case(TIMESTAMP_HEADER) {
+                timestamp = Some(value);
+            } else if name.eq_ignore_ascii_case(SIGNATURE_HEADER) {
+                signature = Some(value);
+            }
+        }
+
+        Ok(Self {
+            timestamp: timestamp.ok_or(WebhookError::MissingHeader(TIMESTAMP_HEADER))?,
+            signature: signature.ok_or(WebhookError::MissingHeader(SIGNATURE_HEADER))?,
+            body,
+        })
+    }
+}
+
+/// What was done with an interaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteractionOutcome {
+    /// An action to apply with [`apply_action`]
+    Received(ReceivedAction),
+    /// A modal asking for the reason of the action was opened
+    ModalOpened(ActionKind),
+    /// The interaction is not an action on a notification
+    Ignored,
+}
+
+/// An action on a notification, taken by a user who may not be allowed to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedAction {
+    pub kind: ActionKind,
+    pub user: SlackUserId,
+    /// The GitHub id of the rule suite
+    pub github_id: String,
+    /// Empty for acknowledgements
+    pub reason: String,
+    /// The channel of the clicked notification
+    pub channel: Option<SlackChannelId>,
+}
+
+/// What was done with a received action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The action was stored and the notification edited
+    Recorded(ViolationAction),
+    /// The user may not take the action and was told so
+    Denied {
+        user: SlackUserId,
+        action: ActionKind,
+    },
+}
+
+/// Verifies the `X-Slack-Signature` header of a request in constant time. `now` is the current
+/// time in seconds since the epoch.
+///
+/// https://api.slack.com/authentication/verifying-requests-from-slack
+pub fn verify_slack_signature(
+    signing_secret: &str,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    now: i64,
+) -> Result<(), WebhookError> {
+    let sent_at: i64 = timestamp
+        .parse()
+        .map_err(|_| WebhookError::InvalidSignature)?;
+    if (now - sent_at).abs() > MAX_REQUEST_AGE {
+        return Err(WebhookError::InvalidSignature);
+    }
+
+    let signature = signature
+        .strip_prefix("v0=")
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+        .ok_or(WebhookError::InvalidSignature)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())
+        .map_err(|_| WebhookError::InvalidSignature)?;
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| WebhookError::InvalidSignature)
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionUser {
+    id: SlackUserId,
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionChannel {
+    id: SlackChannelId,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockAction {
+    action_id: String,
+    /// The GitHub id of the rule suite
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InputValue {
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ViewState {
+    /// Input values by block id and action id
+    values: HashMap<String, HashMap<String, InputValue>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmittedView {
+    callback_id: String,
+    /// JSON serialized [`ReasonModalMetadata`]
+    private_metadata: String,
+    state: ViewState,
+}
+
+/// The fields of `block_actions` and `view_submission` payloads we need
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Payload {
+    BlockActions {
+        user: InteractionUser,
+        channel: Option<InteractionChannel>,
+        trigger_id: SlackTriggerId,
+        actions: Vec<BlockAction>,
+    },
+    ViewSubmission {
+        user: InteractionUser,
+        view: SubmittedView,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Verifies a Slack interaction and returns the action taken, to be applied with
+/// [`apply_action`].
+///
+/// Slack expects a response within three seconds, so nothing is looked up here. The buttons
+/// asking for a reason open a modal right away, whose submission is the action. Whether the user
+/// may take the action is only checked when applying it.
+#[tracing::instrument(skip_all)]
+pub async fn handle_interaction(
+    slack: &dyn SlackClient,
+    signing_secret: &str,
+    delivery: InteractionDelivery<'_>,
+) -> Result<InteractionOutcome, WebhookError> {
+    verify_slack_signature(
+        signing_secret,
+        delivery.timestamp,
+        delivery.signature,
+        delivery.body,
+        Utc::now().timestamp(),
+    )?;
+
+    // An empty payload fails to parse below
+    let payload = url::form_urlencoded::parse(delivery.body)
+        .find(|(name, _)| name == "payload")
+        .map(|(_, payload)| payload.into_owned())
+        .unwrap_or_default();
+    let payload: Payload = serde_json::from_str(&payload).map_err(WebhookError::InvalidPayload)?;
+
+    let action = match payload {
+        Payload::BlockActions {
+            user,
+            channel,
+            trigger_id,
+            actions,
+        } => {
+            let Some((kind, github_id)) = actions.into_iter().find_map(|action| {
+                Some((
+                    ActionKind::from_action_id(&action.action_id)?,
+                    action.value?,
+                ))
+            }) else {
+                return Ok(InteractionOutcome::Ignored);
+            };
+            let channel = channel.map(|channel| channel.id);
+
+            if kind.requires_reason() {
+                let metadata = ReasonModalMetadata { github_id, channel };
+                slack
+                    .open_view(&trigger_id, reason_modal(kind, &metadata))
+                    .await
+                    .map_err(WebhookError::Processing)?;
+                return Ok(InteractionOutcome::ModalOpened(kind));
+            }
+
+            ReceivedAction {
+                kind,
+                user: user.id,
+                github_id,
+                reason: String::new(),
+                channel,
+            }
+        }
+        Payload::ViewSubmission { user, view } => {
+            let Some(kind) = ActionKind::from_action_id(&view.callback_id) else {
+                return Ok(InteractionOutcome::Ignored);
+            };
+            let metadata: ReasonModalMetadata = serde_json::from_str(&view.private_metadata)
+                .map_err(WebhookError::InvalidPayload)?;
+            let reason = view
+                .state
+                .values
+                .get(REASON_INPUT)
+                .and_then(|block| block.get(REASON_INPUT))
+                .and_then(|input| input.value.clone())
+                .unwrap_or_default();
+
+            ReceivedAction {
+                kind,
+                user: user.id,
+                github_id: metadata.github_id,
+                reason,
+                channel: metadata.channel,
+            }
+        }
+        Payload::Other => return Ok(InteractionOutcome::Ignored),
+    };
+
+    Ok(InteractionOutcome::Received(action))
+}
+
+/// Applies an action received by [`handle_interaction`].
+///
+/// Only the notified actor may acknowledge and request a waiver, and only the escalation
+/// contacts may mark a false positive. Others are told so by an ephemeral message in the channel
+/// of the notification.
+#[tracing::instrument(skip(bot, config, slack))]
+pub async fn apply_action(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    action: ReceivedAction,
+) -> anyhow::Result<ActionOutcome> {
+    let event = bot
+        .find_rule_suite_by_github_id(&action.github_id)
+        .await?
+        .with_context(|| format!("unknown rule suite ID {}", action.github_id))?;
+
+    if !may_take(bot, config, slack, &event, action.kind, &action.user).await? {
+        tracing::warn!(
+            "Slack user {} may not {}",
+            action.user,
+            action.kind.action_id()
+        );
+        if let Some(channel) = &action.channel {
+            send_denial(slack, channel, &action.user, action.kind).await?;
+        }
+        return Ok(ActionOutcome::Denied {
+            user: action.user,
+            action: action.kind,
+        });
+    }
+
+    let recorded = ViolationAction::new(action.kind, action.user, action.reason, Utc::now());
+    record_action(bot, config, slack, &event, recorded.clone()).await?;
+
+    Ok(ActionOutcome::Recorded(recorded))
+}
+
+/// Whether the user may take the action on the notification of the event
+async fn may_take(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    event: &GithubRuleSuiteEvent,
+    kind: ActionKind,
+    user: &SlackUserId,
+) -> anyhow::Result<bool> {
+    let allowed = if kind.requires_security() {
+        resolve_contacts(slack, &config.escalation_contacts.all()).await
+    } else {
+        let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+        actor_recipients(&suite, slack, bot, config).await.users
+    };
+
+    Ok(allowed.contains(user))
+}
+
+async fn send_denial(
+    slack: &dyn SlackClient,
+    channel: &SlackChannelId,
+    user: &SlackUserId,
+    kind: ActionKind,
+) -> anyhow::Result<()> {
+    let allowed = if kind.requires_security() {
+        "the escalation contacts"
+    } else {
+        "the notified actor"
+    };
+    let content = SlackMessageContent {
+        text: Some(format!(
+            "You may not take the action *{}* on this bypass, only {allowed} may.",
+            kind.label()
+        )),
+        blocks: None,
+        attachments: None,
+        upload: None,
+        files: None,
+        reactions: None,
+        metadata: None,
+    };
+
+    slack.post_ephemeral(channel, user, content).await
+}
+
+/// Stores the action and edits the posted notifications to show it. Waiver requests are
+/// additionally posted to the SOC2 channel.
+async fn record_action(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    event: &GithubRuleSuiteEvent,
+    action: ViolationAction,
+) -> anyhow::Result<()> {
+    let actions: Vec<ViolationAction> = serde_json::from_str(
+        &bot.append_rule_suite_action(event.id, serde_json::to_string(&action)?)
+            .await?,
+    )?;
+
+    let content = rebuild_notification(bot, config, slack, event, &actions).await?;
+    let messages: Vec<PostedMessage> = event
+        .slack_messages
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?
+        .unwrap_or_default();
+    for message in &messages {
+        slack.update_message(message, content.clone()).await?;
+    }
+
+    if matches!(action, ViolationAction::WaiverRequested { .. }) {
+        let suite: RuleSuite = serde_json::from_str(&event.event_data)?;
+        slack
+            .post_message_channel(
+                SlackChannelId::new(config.slack_soc2_channel.to_string()),
+                SlackMessageContent {
+                    text: Some(format!(
+                        "{action} for the <{}|push> of `{}` to `{}` in `{}/{}`.",
+                        suite.get_commit_url(config),
+                        suite.actor_name.as_deref().unwrap_or("unknown"),
+                        suite.ref_name,
+                        config.github_org,
+                        suite.repository_name,
+                    )),
+                    blocks: None,
+                    attachments: None,
+                    upload: None,
+                    files: None,
+                    reactions: None,
+                    metadata: None,
+                },
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_slack_signature;
+
+    // Example from the Slack documentation.
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: &str = "1531420618";
+    const BODY: &[u8] = b"token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+    const NOW: i64 = 1531420618 + 60;
+
+    #[test]
+    fn verify_slack_signature_accepts_valid_signature() {
+        assert!(verify_slack_signature(SECRET, TIMESTAMP, SIGNATURE, BODY, NOW).is_ok());
+    }
+
+    #[test]
+    fn verify_slack_signature_rejects_tampered_requests() {
+        assert!(
+            verify_slack_signature(SECRET, TIMESTAMP, SIGNATURE, b"token=forged", NOW).is_err()
+        );
+        assert!(verify_slack_signature(SECRET, "1531420619", SIGNATURE, BODY, NOW).is_err());
+        assert!(verify_slack_signature(SECRET, TIMESTAMP, "v1=a2114d57", BODY, NOW).is_err());
+    }
+
+    #[test]
+    fn verify_slack_signature_rejects_replays() {
+        assert!(verify_slack_signature(SECRET, TIMESTAMP, SIGNATURE, BODY, NOW + 60 * 60).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Receiving GitHub webhook deliveries instead of polling.
+//!
+//! [`handle_webhook`] is framework-agnostic: it takes the raw headers and body of a delivery,
+//! verifies the `X-Hub-Signature-256` header and dispatches to the rule suite processing of the
+//! affected repository. With the `webhook-server` feature, `server::WebhookServer` provides a
+//! small HTTP server around it. [`interaction`] handles interactions with Slack notifications in
+//! the same way.
+
+pub mod interaction;
+#[cfg(feature = "webhook-server")]
+pub mod server;
+
+use std::fmt::{Display, Formatter};
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::{
+    BotConfig, GitHubApi, RulesetBot, SlackClient,
+    soc2::{
+        alert_deleted_ruleset, asset_level::AssetLevelCache, process_org_rule_suites_with_github,
+        process_rule_suites_with_github, ruleset::DeletedRuleset,
+    },
+};
+
+const EVENT_HEADER: &str = "x-github-event";
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// A raw GitHub webhook delivery
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookDelivery<'a> {
+    /// The `X-GitHub-Event` header, e.g. `push`
+    pub event: &'a str,
+    /// The `X-Hub-Signature-256` header, e.g. `sha256=757107ea...`
+    pub signature: Option<&'a str>,
+    pub body: &'a [u8],
+}
+
+impl<'a> WebhookDelivery<'a> {
+    /// Extracts the delivery from raw HTTP headers. Header names are matched case-insensitively.
+    pub fn from_headers<I>(headers: I, body: &'a [u8]) -> Result<Self, WebhookError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut event = None;
+        let mut signature = None;
+
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case(EVENT_HEADER) {
+                event = Some(value);
+            } else if name.eq_ignore_ascii_case(SIGNATURE_HEADER) {
+                signature = Some(value);
+            }
+        }
+
+        Ok(Self {
+            event: event.ok_or(WebhookError::MissingHeader(EVENT_HEADER))?,
+            signature,
+            body,
+        })
+    }
+
+    /// Verifies the signature of the delivery, see [`verify_signature`].
+    pub fn verify(&self, secret: &str) -> Result<(), WebhookError> {
+        let signature = self
+            .signature
+            .ok_or(WebhookError::MissingHeader(SIGNATURE_HEADER))?;
+        verify_signature(secret, signature, self.body)
+    }
+}
+
+/// What was done with a webhook delivery
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WebhookOutcome {
+    /// The rule suites of a single repository were processed
+    Repository { repository_full_name: String },
+    /// The rule suites of the whole organization were processed, e.g. after an organization
+    /// ruleset changed
+    Organization,
+    /// The delivery does not concern rule suites of [`BotConfig::github_org`]
+    Ignored { event: String },
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    /// A required header is missing
+    MissingHeader(&'static str),
+    /// The signature is missing, malformed or does not match the body
+    InvalidSignature,
+    /// The body is not a valid payload for the event
+    InvalidPayload(serde_json::Error),
+    /// Processing the rule suites of the affected repository failed
+    Processing(anyhow::Error),
+}
+
+impl Display for WebhookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::MissingHeader(name) => write!(f, "missing header `{name}`"),
+            WebhookError::InvalidSignature => f.write_str("invalid webhook signature"),
+            WebhookError::InvalidPayload(e) => write!(f, "invalid webhook payload: {e}"),
+            WebhookError::Processing(e) => write!(f, "processing webhook failed: {e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+/// Verifies the `X-Hub-Signature-256` header of a delivery in constant time.
+///
+/// https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+pub fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> Result<(), WebhookError> {
+    let signature = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+        .ok_or(WebhookError::InvalidSignature)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| WebhookError::InvalidSignature)?;
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| WebhookError::InvalidSignature)
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+    name: String,
+    owner: RepositoryOwner,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryOwner {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryRuleset {
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sender {
+    login: String,
+}
+
+/// The fields of `push` and `repository_ruleset` payloads we need. Rulesets of an organization
+/// are delivered without a repository.
+#[derive(Debug, Deserialize)]
+struct Payload {
+    action: Option<String>,
+    repository: Option<Repository>,
+    repository_ruleset: Option<RepositoryRuleset>,
+    sender: Option<Sender>,
+}
+
+/// Verifies and dispatches a webhook delivery.
+///
+/// `push` deliveries process the rule suites of the pushed repository. `repository_ruleset`
+/// deliveries process the affected repository, or the whole organization for organization
+/// rulesets. Deleted rulesets are additionally alerted, see [`alert_deleted_ruleset`]. All other
+/// events are ignored.
+///
+/// This is [`route_webhook`] followed by [`process_webhook_outcome`]. Callers handling deliveries
+/// concurrently should serialize the processing per repository, like `server::WebhookServer`.
+#[tracing::instrument(skip_all, fields(event = delivery.event))]
+pub async fn handle_webhook(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    secret: &str,
+    delivery: WebhookDelivery<'_>,
+) -> Result<WebhookOutcome, WebhookError> {
+    let outcome = route_webhook(config, slack, github, asset_levels, secret, delivery).await?;
+    process_webhook_outcome(bot, config, slack, github, asset_levels, &outcome).await?;
+    Ok(outcome)
+}
+
+/// Verifies a webhook delivery and returns the rule suites it concerns, without processing them.
+/// Deleted rulesets are alerted right away, see [`alert_deleted_ruleset`].
+#[tracing::instrument(skip_all, fields(event = delivery.event))]
+pub async fn route_webhook(
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    secret: &str,
+    delivery: WebhookDelivery<'_>,
+) -> Result<WebhookOutcome, WebhookError> {
+    delivery.verify(secret)?;
+
+    let ignored = || WebhookOutcome::Ignored {
+        event: delivery.event.to_string(),
+    };
+
+    if !matches!(delivery.event, "push" | "repository_ruleset") {
+        return Ok(ignored());
+    }
+
+    let payload: Payload =
+        serde_json::from_slice(delivery.body).map_err(WebhookError::InvalidPayload)?;
+
+    if delivery.event == "repository_ruleset"
+        && payload.action.as_deref() == Some("deleted")
+        && let Some(ruleset) = &payload.repository_ruleset
+        && payload
+            .repository
+            .as_ref()
+            .is_none_or(|repository| repository.owner.login == config.github_org)
+    {
+        alert_deleted_ruleset(
+            slack,
+            config,
+            github,
+            asset_levels,
+            &DeletedRuleset {
+                id: ruleset.id,
+                name: ruleset.name.clone(),
+                repository: payload
+                    .repository
+                    .as_ref()
+                    .map(|repository| repository.name.clone()),
+                deleted_by: payload.sender.as_ref().map(|sender| sender.login.clone()),
+            },
+        )
+        .await
+        .map_err(WebhookError::Processing)?;
+    }
+
+    Ok(match payload.repository {
+        Some(repository) if repository.owner.login == config.github_org => {
+            WebhookOutcome::Repository {
+                repository_full_name: repository.full_name,
+            }
+        }
+        Some(_) => ignored(),
+        None if delivery.event == "repository_ruleset" => WebhookOutcome::Organization,
+        None => ignored(),
+    })
+}
+
+/// Processes the rule suites of a routed delivery, see [`route_webhook`].
+#[tracing::instrument(skip(bot, config, slack, github, asset_levels))]
+pub async fn process_webhook_outcome(
+    bot: &dyn RulesetBot,
+    config: &BotConfig,
+    slack: &dyn SlackClient,
+    github: &dyn GitHubApi,
+    asset_levels: &AssetLevelCache,
+    outcome: &WebhookOutcome,
+) -> Result<(), WebhookError> {
+    match outcome {
+        WebhookOutcome::Repository {
+            repository_full_name,
+        } => {
+            let repository_name = repository_full_name
+                .split_once('/')
+                .map_or(repository_full_name.as_str(), |(_, name)| name);
+            process_rule_suites_with_github(
+                bot,
+                config,
+                slack,
+                github,
+                asset_levels,
+                repository_full_name,
+                repository_name,
+            )
+            .await
+        }
+        WebhookOutcome::Organization => {
+            process_org_rule_suites_with_github(bot, config, slack, github, asset_levels).await
+        }
+        WebhookOutcome::Ignored { .. } => Ok(()),
+    }
+    .map_err(WebhookError::Processing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WebhookDelivery, WebhookError, verify_signature};
+
+    // Example from the GitHub documentation.
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    const SIGNATURE: &str =
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    #[test]
+    fn verify_signature_accepts_valid_signature() {
+        assert!(verify_signature(SECRET, SIGNATURE, BODY).is_ok());
+    }
+
+    #[test]
+    fn verify_signature_rejects_tampered_body() {
+        assert!(matches!(
+            verify_signature(SECRET, SIGNATURE, b"Hello, World?"),
+            Err(WebhookError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn verify_signature_rejects_malformed_signature() {
+        assert!(verify_signature(SECRET, "sha1=757107ea", BODY).is_err());
+        assert!(verify_signature(SECRET, "sha256=not-hex", BODY).is_err());
+    }
+
+    #[test]
+    fn delivery_from_headers_is_case_insensitive() {
+        let delivery = WebhookDelivery::from_headers(
+            [
+                ("X-GitHub-Event", "push"),
+                ("X-Hub-Signature-256", SIGNATURE),
+                ("Content-Type", "application/json"),
+            ],
+            BODY,
+        )
+        .expect("should parse headers");
+
+        assert_eq!(delivery.event, "push");
+        assert_eq!(delivery.signature, Some(SIGNATURE));
+    }
+
+    #[test]
+    fn delivery_from_headers_requires_event() {
+        assert!(matches!(
+            WebhookDelivery::from_headers([("X-Hub-Signature-256", SIGNATURE)], BODY),
+            Err(WebhookError::MissingHeader(_))
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Body, Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpListener, sync::mpsc};
+
+use crate::{
+    BotConfig, GitHubApi, RulesetBot, SlackClient,
+    soc2::asset_level::AssetLevelCache,
+    webhook::{
+        WebhookDelivery, WebhookError, WebhookOutcome,
+        interaction::{InteractionDelivery, InteractionOutcome, apply_action, handle_interaction},
+        process_webhook_outcome, route_webhook,
+    },
+};
+
+/// The path of the interactivity request URL of the Slack app
+pub const SLACK_INTERACTIONS_PATH: &str = "/slack/interactions";
+
+/// The largest accepted request body. GitHub caps webhook payloads at 25 MB.
+pub const MAX_BODY_SIZE: usize = 25 * 1024 * 1024;
+
+/// A minimal HTTP/1 server accepting GitHub webhook deliveries via `POST` on any path, and Slack
+/// interactions on [`SLACK_INTERACTIONS_PATH`] if a signing secret is configured.
+///
+/// Responds with `413` for bodies larger than [`MAX_BODY_SIZE`], `401` for invalid signatures and
+/// `400` for malformed deliveries. Verified webhook deliveries are acknowledged with `202` and
+/// processed in the background, because processing, e.g. sweeping the organization, can take
+/// longer than GitHub waits for a response. Likewise, Slack actions are acknowledged with `200`
+/// and applied in the background. Processing failures are only logged.
+///
+/// Rule suites are processed by a single worker, one delivery at a time, so that concurrent
+/// deliveries for a repository do not race on its sync cursor or notify twice. A delivery for a
+/// repository that is already waiting to be processed is coalesced into the waiting run.
+pub struct WebhookServer {
+    pub bot: Arc<dyn RulesetBot>,
+    pub config: BotConfig,
+    pub slack: Arc<dyn SlackClient>,
+    pub github: Arc<dyn GitHubApi>,
+    /// Shared by all deliveries, so that asset levels are not fetched for every push
+    pub asset_levels: AssetLevelCache,
+    /// The secret configured for the webhook on GitHub
+    pub secret: String,
+    /// The signing secret of the Slack app, `None` disables Slack interactions
+    pub slack_signing_secret: Option<String>,
+}
+
+impl WebhookServer {
+    /// Serves deliveries on the listener until accepting a connection fails. Fails right away if
+    /// the config is invalid, see [`BotConfig::validate`].
+    pub async fn serve(self, listener: TcpListener) -> anyhow::Result<()> {
+        self.config.validate()?;
+        let server = Arc::new(self);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let runs = Arc::new(RunQueue {
+            waiting: Mutex::new(HashSet::new()),
+            sender,
+        });
+        tokio::spawn(server.clone().process_runs(runs.clone(), receiver));
+
+        loop {
+            let (stream, remote) = listener.accept().await?;
+            let server = server.clone();
+            let runs = runs.clone();
+
+            tokio::spawn(async move {
+                let service = service_fn(|request| server.clone().respond(runs.clone(), request));
+
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::warn!("Failed to serve webhook connection from {remote}: {e}");
+                }
+            });
+        }
+    }
+
+    /// Processes the queued runs one after another.
+    async fn process_runs(
+        self: Arc<Self>,
+        runs: Arc<RunQueue>,
+        mut receiver: mpsc::UnboundedReceiver<WebhookOutcome>,
+    ) {
+        while let Some(outcome) = receiver.recv().await {
+            // Deliveries arriving from now on may concern changes this run misses.
+            runs.waiting
+                .lock()
+                .expect("should not be poisoned")
+                .remove(&outcome);
+
+            match process_webhook_outcome(
+                self.bot.as_ref(),
+                &self.config,
+                self.slack.as_ref(),
+                self.github.as_ref(),
+                &self.asset_levels,
+                &outcome,
+            )
+            .await
+            {
+                Ok(()) => tracing::info!("Processed webhook delivery: {outcome:?}"),
+                Err(e) => tracing::warn!("Failed to process webhook delivery: {e}"),
+            }
+        }
+    }
+
+    async fn respond(
+        self: Arc<Self>,
+        runs: Arc<RunQueue>,
+        request: Request<Incoming>,
+    ) -> Result<Response<Full<Bytes>>, Infallible> {
+        if request.method() != Method::POST {
+            return Ok(response(StatusCode::METHOD_NOT_ALLOWED, "POST only"));
+        }
+
+        let (parts, body) = request.into_parts();
+        // Reject a declared length early, `Limited` only fails once the limit was read
+        if body.size_hint().lower() > MAX_BODY_SIZE as u64 {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE, "body too large"));
+        }
+        let body = match Limited::new(body, MAX_BODY_SIZE).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) if e.is::<LengthLimitError>() => {
+                return Ok(response(StatusCode::PAYLOAD_TOO_LARGE, &e.to_string()));
+            }
+            Err(e) => {
+                return Ok(response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("reading the body failed: {e}"),
+                ));
+            }
+        };
+        let headers = parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)));
+
+        if parts.uri.path() == SLACK_INTERACTIONS_PATH
+            && let Some(signing_secret) = &self.slack_signing_secret
+        {
+            let result = match InteractionDelivery::from_headers(headers, &body) {
+                Ok(delivery) => {
+                    handle_interaction(self.slack.as_ref(), signing_secret, delivery).await
+                }
+                Err(e) => Err(e),
+            };
+
+            return Ok(match result {
+                Ok(outcome) => {
+                    tracing::info!("Handled Slack interaction: {outcome:?}");
+                    if let InteractionOutcome::Received(action) = outcome {
+                        let server = self.clone();
+                        tokio::spawn(async move {
+                            match apply_action(
+                                server.bot.as_ref(),
+                                &server.config,
+                                server.slack.as_ref(),
+                                action,
+                            )
+                            .await
+                            {
+                                Ok(outcome) => tracing::info!("Applied Slack action: {outcome:?}"),
+                                Err(e) => tracing::warn!("Failed to apply Slack action: {e:#}"),
+                            }
+                        });
+                    }
+                    // An empty body closes a submitted modal
+                    response(StatusCode::OK, "")
+                }
+                Err(e) => error_response(e),
+            });
+        }
+
+        let delivery = match WebhookDelivery::from_headers(headers, &body) {
+            Ok(delivery) => delivery,
+            Err(e) => return Ok(error_response(e)),
+        };
+        if let Err(e) = delivery.verify(&self.secret) {
+            return Ok(error_response(e));
+        }
+
+        let event = delivery.event.to_string();
+        let signature = delivery.signature.map(str::to_string);
+        let server = self.clone();
+        tokio::spawn(async move {
+            let delivery = WebhookDelivery {
+                event: &event,
+                signature: signature.as_deref(),
+                body: &body,
+            };
+
+            match route_webhook(
+                &server.config,
+                server.slack.as_ref(),
+                server.github.as_ref(),
+                &server.asset_levels,
+                &server.secret,
+                delivery,
+            )
+            .await
+            {
+                Ok(outcome @ WebhookOutcome::Ignored { .. }) => {
+                    tracing::info!("Handled webhook delivery: {outcome:?}")
+                }
+                Ok(outcome) => runs.push(outcome),
+                Err(e) => tracing::warn!("Failed to handle webhook delivery: {e}"),
+            }
+        });
+
+        Ok(response(StatusCode::ACCEPTED, "accepted"))
+    }
+}
+
+/// Runs waiting for the worker of [`WebhookServer::serve`]
+struct RunQueue {
+    waiting: Mutex<HashSet<WebhookOutcome>>,
+    sender: mpsc::UnboundedSender<WebhookOutcome>,
+}
+
+impl RunQueue {
+    /// Queues a run, unless the same run is already waiting. A waiting run lists the rule suites
+    /// when it starts, so it covers the changes of the coalesced delivery as well.
+    fn push(&self, outcome: WebhookOutcome) {
+        let mut waiting = self.waiting.lock().expect("should not be poisoned");
+        if !waiting.insert(outcome.clone()) {
+            tracing::debug!("Coalesced webhook delivery into waiting run: {outcome:?}");
+            return;
+        }
+
+        if self.sender.send(outcome).is_err() {
+            tracing::warn!("Webhook worker stopped, dropping delivery");
+        }
+    }
+}
+
+fn error_response(e: WebhookError) -> Response<Full<Bytes>> {
+    match e {
+        WebhookError::InvalidSignature => response(StatusCode::UNAUTHORIZED, &e.to_string()),
+        WebhookError::MissingHeader(_) | WebhookError::InvalidPayload(_) => {
+            response(StatusCode::BAD_REQUEST, &e.to_string())
+        }
+        WebhookError::Processing(_) => {
+            tracing::warn!("{e}");
+            response(StatusCode::INTERNAL_SERVER_ERROR, "processing failed")
+        }
+    }
+}
+
+fn response(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+}
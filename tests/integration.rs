@@ -1,23 +1,49 @@
 use async_trait::async_trait;
-use chrono::DateTime;
+use chrono::{DateTime, TimeDelta};
 use octocrab::models::pulls::PullRequest;
-use octocrab::models::repos::{
-    CommitAuthor, CommitObject, RepoCommit, RepoCommitPage, Verification,
+use ruleset_policy_bot::github::FakeGitHub;
+use ruleset_policy_bot::notify::slack::{SlackNotifier, markdown_links_to_slack};
+use ruleset_policy_bot::notify::{Notifier, Violation};
+use ruleset_policy_bot::soc2::action::ViolationAction;
+use ruleset_policy_bot::soc2::asset_level::{AssetLevel, AssetTaxonomy};
+use ruleset_policy_bot::soc2::asset_level::{
+    AssetLevelCache, AssetLevelWarning, CustomProperty, CustomPropertyValue,
+};
+use ruleset_policy_bot::soc2::ci::{CheckRunList, CiChecks, CombinedStatus};
+use ruleset_policy_bot::soc2::escalation::{
+    EscalationContact, EscalationContacts, EscalationOverride,
+};
+use ruleset_policy_bot::soc2::force_push::DiscardedCommits;
+use ruleset_policy_bot::soc2::justification::Justification;
+use ruleset_policy_bot::soc2::policy::{Policy, PolicyDecision};
+use ruleset_policy_bot::soc2::ref_filter::{DEFAULT_BRANCH, RefFilter};
+use ruleset_policy_bot::soc2::reminder::{ReminderConfig, ReminderStatus, remind_unjustified};
+use ruleset_policy_bot::soc2::retroactive_review::{
+    ApprovalSource, CommitComment, GitHubAccount, PullRequestReview, RetroactiveReviewConfig,
+    ReviewStatus,
 };
-use octocrab::models::{Author, UserId};
-use ruleset_policy_bot::soc2::asset_level::AssetLevel;
 use ruleset_policy_bot::soc2::rule_suit::{
-    Enforcement, RuleEvalResult, RuleEvaluation, RuleSource, RuleSuite,
+    Enforcement, RuleEvalResult, RuleEvaluation, RuleSource, RuleSuite, ViolationCategory,
+};
+use ruleset_policy_bot::soc2::ruleset::DeletedRuleset;
+use ruleset_policy_bot::soc2::shadow::{build_shadow_report_with_github, send_shadow_report};
+use ruleset_policy_bot::soc2::trusted_actor::TrustedActor;
+use ruleset_policy_bot::soc2::waiver::{NewWaiver, Waiver};
+use ruleset_policy_bot::soc2::{
+    alert_deleted_ruleset, evaluate_rule_suites, process_org_rule_suites_with_github,
+    process_rule_suites, process_rule_suites_with_github,
 };
-use ruleset_policy_bot::soc2::{create_octocrab, evaluate_rule_suites, process_rule_suites};
 use ruleset_policy_bot::{
-    BotConfig, GitHubAppCredentials, GitHubAppInstallation, GitHubAuth, GithubRuleSuiteEvent,
-    NewGithubRuleSuiteEvent, RulesetBot, SlackClient, User,
+    BotConfig, GitHubAuth, GithubRuleSuiteEvent, NewGithubRuleSuiteEvent, PostedMessage,
+    RuleSuiteFilter, RulesetBot, SlackClient, SlackThreadReply, SyncCursor,
+};
+use slack_morphism::blocks::{SlackBlock, SlackView};
+use slack_morphism::{
+    SlackChannelId, SlackMessageContent, SlackTriggerId, SlackTs, SlackUser, SlackUserFlags,
+    SlackUserId,
 };
-use slack_morphism::{SlackChannelId, SlackMessageContent, SlackUser, SlackUserFlags, SlackUserId};
 use std::cell::RefCell;
-use std::sync::Mutex;
-use url::Host::Domain;
+use std::sync::{Arc, Mutex};
 
 const COMMIT: &str = // language=json
     r#"
@@ -112,8 +138,63 @@ const COMMIT: &str = // language=json
 }
                 "#;
 
+#[derive(Default)]
 struct MockRulesetBot {
     events: Mutex<RefCell<Vec<NewGithubRuleSuiteEvent>>>,
+    /// Stored policy decisions by event id
+    decisions: Mutex<RefCell<Vec<(i32, String)>>>,
+    waivers: Mutex<RefCell<Vec<Waiver>>>,
+    /// Stored retroactive review statuses by event id
+    reviews: Mutex<RefCell<Vec<(i32, String)>>>,
+    /// Stored Slack messages by event id
+    slack_messages: Mutex<RefCell<Vec<(i32, String)>>>,
+    /// Stored justifications by event id
+    justifications: Mutex<RefCell<Vec<(i32, String)>>>,
+    /// Stored reminder statuses by event id
+    reminders: Mutex<RefCell<Vec<(i32, String)>>>,
+    /// Stored notification actions by event id
+    actions: Mutex<RefCell<Vec<(i32, String)>>>,
+    /// Stored sync cursor, shared by all repositories
+    sync_cursor: Mutex<RefCell<Option<SyncCursor>>>,
+    /// Stored asset level warnings by repository
+    asset_level_warnings: Mutex<RefCell<Vec<(String, AssetLevelWarning)>>>,
+}
+
+impl MockRulesetBot {
+    /// The last value stored in one of the fields
+    fn last(field: &Mutex<RefCell<Vec<(i32, String)>>>) -> Option<String> {
+        field
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .map(|(_, value)| value.clone())
+    }
+}
+
+/// All events are stored with id 123.
+fn stored_event(event: &NewGithubRuleSuiteEvent, review: Option<String>) -> GithubRuleSuiteEvent {
+    GithubRuleSuiteEvent {
+        id: 123,
+        github_id: event.github_id.clone(),
+        repository_full_name: event.repository_full_name.clone(),
+        event_data: event.event_data.clone(),
+        resulting_commit: event.resulting_commit.clone(),
+        prs: event.prs.clone(),
+        ci_checks: event.ci_checks.clone(),
+        discarded_commits: event.discarded_commits.clone(),
+        notified: event.notified,
+        decision: None,
+        review,
+        slack_messages: None,
+        justification: None,
+        justified: false,
+        reminder: None,
+        actions: None,
+        created_at: DateTime::from_timestamp(0, 0).expect("valid timestamp"),
+        updated_at: DateTime::from_timestamp(0, 0).expect("valid timestamp"),
+    }
 }
 
 #[async_trait]
@@ -122,7 +203,20 @@ impl RulesetBot for MockRulesetBot {
         &self,
         github_id: &str,
     ) -> anyhow::Result<Option<GithubRuleSuiteEvent>> {
-        Ok(None)
+        Ok(self
+            .events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .iter()
+            .find(|event| event.github_id == github_id)
+            .map(|event| GithubRuleSuiteEvent {
+                decision: Self::last(&self.decisions),
+                slack_messages: Self::last(&self.slack_messages),
+                actions: Self::last(&self.actions),
+                ..stored_event(event, None)
+            }))
     }
 
     async fn create_rule_suite_event(&self, event: NewGithubRuleSuiteEvent) -> anyhow::Result<()> {
@@ -151,121 +245,415 @@ impl RulesetBot for MockRulesetBot {
             .expect("should not be locked")
             .borrow()
             .iter()
-            .map(|event| GithubRuleSuiteEvent {
-                id: 123,
-                github_id: event.github_id.clone(),
-                repository_full_name: event.repository_full_name.clone(),
-                event_data: event.event_data.clone(),
-                resulting_commit: event.resulting_commit.clone(),
-                prs: event.prs.clone(),
-                notified: event.notified,
-                created_at: DateTime::from_timestamp(0, 0).expect("valid timestamp"),
-                updated_at: DateTime::from_timestamp(0, 0).expect("valid timestamp"),
-            })
+            .filter(|event| event.repository_full_name == repository_full_name && !event.notified)
+            .map(|event| stored_event(event, None))
             .collect())
     }
 
     async fn mark_rule_suite_notified(&self, id: i32) -> anyhow::Result<()> {
         println!("Marked rule suite {} as notified", id);
+        // All events are stored with the same id
+        for event in self
+            .events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .iter_mut()
+        {
+            event.notified = true;
+        }
+        Ok(())
+    }
+
+    async fn set_rule_suite_decision(&self, id: i32, decision: String) -> anyhow::Result<()> {
+        self.decisions
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((id, decision));
+        Ok(())
+    }
+
+    async fn set_rule_suite_review(&self, id: i32, review: String) -> anyhow::Result<()> {
+        self.reviews
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((id, review));
+        Ok(())
+    }
+
+    async fn find_pending_reviews(&self) -> anyhow::Result<Vec<GithubRuleSuiteEvent>> {
+        let review = self
+            .reviews
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .map(|(_, review)| review.clone());
+        let Some(review) = review.filter(|review| review.contains(r#""status":"pending""#)) else {
+            return Ok(vec![]);
+        };
+
+        Ok(self
+            .events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .iter()
+            .map(|event| GithubRuleSuiteEvent {
+                actions: Self::last(&self.actions),
+                ..stored_event(event, Some(review.clone()))
+            })
+            .collect())
+    }
+
+    async fn set_rule_suite_messages(&self, id: i32, messages: String) -> anyhow::Result<()> {
+        self.slack_messages
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((id, messages));
+        Ok(())
+    }
+
+    async fn find_unjustified_rule_suites(
+        &self,
+        repository_full_name: Option<&str>,
+    ) -> anyhow::Result<Vec<GithubRuleSuiteEvent>> {
+        let justified = !self
+            .justifications
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .is_empty();
+        let messages = self
+            .slack_messages
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .map(|(_, messages)| messages.clone());
+        let Some(messages) = messages.filter(|_| !justified) else {
+            return Ok(vec![]);
+        };
+        let decision = self
+            .decisions
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .map(|(_, decision)| decision.clone());
+        let reminder = self
+            .reminders
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .map(|(_, reminder)| reminder.clone());
+
+        Ok(self
+            .events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .iter()
+            .filter(|event| {
+                repository_full_name.is_none_or(|name| name == event.repository_full_name)
+            })
+            .map(|event| GithubRuleSuiteEvent {
+                slack_messages: Some(messages.clone()),
+                decision: decision.clone(),
+                reminder: reminder.clone(),
+                actions: Self::last(&self.actions),
+                ..stored_event(event, None)
+            })
+            .collect())
+    }
+
+    async fn set_rule_suite_justification(
+        &self,
+        id: i32,
+        justification: String,
+    ) -> anyhow::Result<()> {
+        self.justifications
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((id, justification));
+        Ok(())
+    }
+
+    async fn append_rule_suite_action(&self, id: i32, action: String) -> anyhow::Result<String> {
+        let actions = self.actions.lock();
+        let mut actions = actions.as_ref().expect("should not be locked").borrow_mut();
+        let mut appended: Vec<serde_json::Value> = actions
+            .last()
+            .map(|(_, actions)| serde_json::from_str(actions))
+            .transpose()?
+            .unwrap_or_default();
+        appended.push(serde_json::from_str(&action)?);
+        let appended = serde_json::to_string(&appended)?;
+        actions.push((id, appended.clone()));
+        Ok(appended)
+    }
+
+    async fn set_rule_suite_reminder(&self, id: i32, reminder: String) -> anyhow::Result<()> {
+        self.reminders
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((id, reminder));
         Ok(())
     }
 
     async fn get_email_by_github_username(
         &self,
-        github_username: &str,
+        _github_username: &str,
     ) -> anyhow::Result<Option<String>> {
         Ok(Some("max.ammann@zoo.dev".to_string()))
     }
+
+    async fn get_sync_cursor(
+        &self,
+        _repository_full_name: &str,
+    ) -> anyhow::Result<Option<SyncCursor>> {
+        Ok(*self
+            .sync_cursor
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow())
+    }
+
+    async fn set_sync_cursor(
+        &self,
+        repository_full_name: &str,
+        cursor: SyncCursor,
+    ) -> anyhow::Result<()> {
+        println!("Set sync cursor of {repository_full_name} to {cursor:?}");
+        self.sync_cursor
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .replace(Some(cursor));
+        Ok(())
+    }
+
+    async fn get_asset_level_warning(
+        &self,
+        repository_full_name: &str,
+    ) -> anyhow::Result<Option<AssetLevelWarning>> {
+        Ok(self
+            .asset_level_warnings
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .iter()
+            .rev()
+            .find(|(repository, _)| repository == repository_full_name)
+            .map(|(_, warning)| warning.clone()))
+    }
+
+    async fn set_asset_level_warning(
+        &self,
+        repository_full_name: &str,
+        warning: AssetLevelWarning,
+    ) -> anyhow::Result<()> {
+        self.asset_level_warnings
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push((repository_full_name.to_string(), warning));
+        Ok(())
+    }
+
+    async fn create_waiver(&self, waiver: NewWaiver) -> anyhow::Result<()> {
+        let waivers = self.waivers.lock();
+        let mut waivers = waivers.as_ref().expect("should not be locked").borrow_mut();
+        let id = waivers.len() as i32 + 1;
+        waivers.push(Waiver {
+            id,
+            repository_full_name: waiver.repository_full_name,
+            ref_pattern: waiver.ref_pattern,
+            rule_types: waiver.rule_types,
+            actor_name: waiver.actor_name,
+            reason: waiver.reason,
+            approver: waiver.approver,
+            expires_at: waiver.expires_at,
+            retroactive: waiver.retroactive,
+            created_at: chrono::Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn find_waivers(&self, repository_full_name: &str) -> anyhow::Result<Vec<Waiver>> {
+        Ok(self
+            .waivers
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .iter()
+            .filter(|waiver| waiver.repository_full_name == repository_full_name)
+            .cloned()
+            .collect())
+    }
 }
 
+#[derive(Default)]
 struct MockSlackClient {
     messages: Mutex<RefCell<Vec<(String, SlackMessageContent)>>>,
+    /// Edited messages
+    updates: Mutex<RefCell<Vec<(PostedMessage, SlackMessageContent)>>>,
+    /// Opened modals
+    views: Mutex<RefCell<Vec<SlackView>>>,
+    /// Ephemeral messages by channel and user
+    ephemerals: Mutex<RefCell<Vec<(String, String, SlackMessageContent)>>>,
+    /// Replies in the thread of every posted message
+    replies: Vec<SlackThreadReply>,
+}
+
+impl MockSlackClient {
+    /// Records the message, messages are numbered by their `ts`
+    fn post(&self, channel: String, content: SlackMessageContent) -> PostedMessage {
+        let messages = self.messages.lock();
+        let mut messages = messages
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut();
+        let ts = SlackTs(format!("1700000000.{:06}", messages.len()));
+        messages.push((channel.clone(), content));
+        PostedMessage {
+            channel: SlackChannelId(channel),
+            ts,
+        }
+    }
 }
 
 #[async_trait]
 impl SlackClient for MockSlackClient {
     async fn get_user_by_email(&self, email: &str) -> anyhow::Result<SlackUser> {
+        if email.ends_with(".invalid") {
+            anyhow::bail!("users_not_found");
+        }
         Ok(SlackUser::new(
             SlackUserId(email.to_string()),
             SlackUserFlags::new(),
         ))
     }
 
+    async fn get_user_group_members(
+        &self,
+        user_group_id: &str,
+    ) -> anyhow::Result<Vec<SlackUserId>> {
+        match user_group_id {
+            "S0614TZR7" => Ok(vec![
+                SlackUserId("U0SEC0001".to_string()),
+                SlackUserId("U0SEC0002".to_string()),
+            ]),
+            _ => anyhow::bail!("no_such_subteam"),
+        }
+    }
+
     async fn post_message_channel(
         &self,
         channel_id: SlackChannelId,
         content: SlackMessageContent,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<PostedMessage> {
         println!("Posted message to channel {}", channel_id);
-        self.messages
+        Ok(self.post(channel_id.0, content))
+    }
+
+    async fn post_message_user(
+        &self,
+        user_id: SlackUserId,
+        content: SlackMessageContent,
+    ) -> anyhow::Result<PostedMessage> {
+        println!("Posted message to user {}", user_id);
+        // Lets concurrent processing interleave, like waiting for Slack would
+        tokio::task::yield_now().await;
+        // The direct message channel is identified by the user id
+        Ok(self.post(user_id.0, content))
+    }
+
+    async fn update_message(
+        &self,
+        message: &PostedMessage,
+        content: SlackMessageContent,
+    ) -> anyhow::Result<()> {
+        println!("Updated message {}", message.ts);
+        self.updates
             .lock()
             .as_ref()
             .expect("should not be locked")
             .borrow_mut()
-            .push((channel_id.0, content));
+            .push((message.clone(), content));
         Ok(())
     }
 
-    async fn post_message_user(
+    async fn post_ephemeral(
         &self,
-        user_id: SlackUserId,
+        channel_id: &SlackChannelId,
+        user_id: &SlackUserId,
         content: SlackMessageContent,
     ) -> anyhow::Result<()> {
-        println!("Posted message to user {}", user_id);
-        self.messages
+        println!("Posted ephemeral message to {user_id} in {channel_id}");
+        self.ephemerals
             .lock()
             .as_ref()
             .expect("should not be locked")
             .borrow_mut()
-            .push((user_id.0, content));
+            .push((channel_id.0.clone(), user_id.0.clone(), content));
         Ok(())
     }
-}
-
-#[tokio::test]
-async fn test_updating_rule_suites() {
-    let bot = MockRulesetBot {
-        events: Mutex::new(RefCell::new(vec![])),
-    };
-    let slack_client = MockSlackClient {
-        messages: Mutex::new(RefCell::new(vec![])),
-    };
-    process_rule_suites(
-        &bot,
-        &BotConfig {
-            github_org: "KittyCAD".to_string(),
-            github_web_base_url: "https://github.com/".to_string(),
-            slack_soc2_channel: "#soc2".to_string(),
-            review_requirement_ruleset_id: None,
-            block_force_push_ruleset_id: None,
-            codeowners_ruleset_id: None,
-            in_scope_asset_level: AssetLevel::Playground..=AssetLevel::Playground,
-            callout_asset_level: AssetLevel::Production..=AssetLevel::Production,
-            critical_asset_levels: AssetLevel::Production..=AssetLevel::Production,
-            github_auth: GitHubAuth::Token(std::env::var("GH_TOKEN").unwrap()),
-        },
-        &slack_client,
-        "KittyCAD/ruleset-policy-bot",
-        "ruleset-policy-bot",
-    )
-    .await
-    .unwrap();
 
-    let events = bot.events.lock();
-    let events = events.as_ref().expect("should not be locked").borrow();
-    let event = events.first().clone().expect("should have event");
+    async fn open_view(&self, trigger_id: &SlackTriggerId, view: SlackView) -> anyhow::Result<()> {
+        println!("Opened view for trigger {}", trigger_id);
+        self.views
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow_mut()
+            .push(view);
+        Ok(())
+    }
 
-    assert!(!event.event_data.is_empty());
+    async fn get_thread_replies(
+        &self,
+        message: &PostedMessage,
+    ) -> anyhow::Result<Vec<SlackThreadReply>> {
+        println!("Fetched thread replies of {}", message.ts);
+        Ok(self.replies.clone())
+    }
+}
 
-    let message = slack_client.messages.lock();
-    let message = message.as_ref().expect("should not be locked").borrow();
-    let message = message.first().expect("should have message");
-    insta::assert_debug_snapshot!(message.1.blocks);
+/// The security reviewer receiving a copy of every notification
+fn max_ammann_contacts() -> EscalationContacts {
+    EscalationContacts {
+        contacts: vec![EscalationContact::Email("max.ammann@zoo.dev".to_string())],
+        ..Default::default()
+    }
 }
 
-#[tokio::test]
-async fn test_evaluate_rule_suites() {
-    let rule_suite = RuleSuite {
+/// The bypass produced by `scripts/reset-ci-tests.sh`.
+fn ci_tests_rule_suite() -> RuleSuite {
+    RuleSuite {
         id: 1923052992,
         actor_id: Some(905221),
         actor_name: Some("maxammann".to_string()),
@@ -303,41 +691,118 @@ async fn test_evaluate_rule_suites() {
                 details: Some("Changes must be made through a pull request.".to_string()),
             },
         ]),
-    };
+    }
+}
 
-    let bot = MockRulesetBot {
-        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
-            github_id: "1923052992".to_string(),
-            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
-            event_data: serde_json::to_string(&rule_suite).expect("should serialize"),
-            resulting_commit: Some(COMMIT.to_string()),
-            prs: Some(
-                serde_json::to_string::<Vec<PullRequest>>(&vec![]).expect("should serialize"),
-            ),
-            notified: false,
-        }])),
-    };
+fn playground_custom_properties() -> Vec<CustomProperty> {
+    vec![CustomProperty {
+        property_name: "repository-level".to_string(),
+        value: Some(CustomPropertyValue::String("Playground".to_string())),
+    }]
+}
 
-    let slack_client = MockSlackClient {
-        messages: Mutex::new(RefCell::new(vec![])),
-    };
-    let config = BotConfig {
-        github_org: "KittyCAD".to_string(),
-        github_web_base_url: "https://github.com/".to_string(),
-        slack_soc2_channel: "#soc2".to_string(),
-        review_requirement_ruleset_id: None,
+/// Runs against the live `ci-tests` bypass, see `scripts/reset-ci-tests.sh`. CI runs it with
+/// `--include-ignored`, [`test_processing_rule_suites_with_fake_github`] covers the same offline.
+#[tokio::test]
+#[ignore = "needs GH_TOKEN and network access"]
+async fn test_updating_rule_suites() {
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    process_rule_suites(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token(std::env::var("GH_TOKEN").unwrap()),
+        },
+        &slack_client,
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    let event = events.first().expect("should have event");
+
+    assert!(!event.event_data.is_empty());
+
+    let message = slack_client.messages.lock();
+    let message = message.as_ref().expect("should not be locked").borrow();
+    let message = message.first().expect("should have message");
+    insta::assert_debug_snapshot!(message.1.blocks);
+}
+
+#[tokio::test]
+async fn test_evaluate_rule_suites() {
+    let rule_suite = ci_tests_rule_suite();
+
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: "1923052992".to_string(),
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+            event_data: serde_json::to_string(&rule_suite).expect("should serialize"),
+            resulting_commit: Some(COMMIT.to_string()),
+            prs: Some(
+                serde_json::to_string::<Vec<PullRequest>>(&vec![]).expect("should serialize"),
+            ),
+            ci_checks: None,
+            discarded_commits: None,
+            notified: false,
+        }])),
+        ..Default::default()
+    };
+
+    let slack_client = MockSlackClient::default();
+    let github = FakeGitHub::new().with_custom_properties(
+        "KittyCAD",
+        "ruleset-policy-bot",
+        playground_custom_properties(),
+    );
+    let config = BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
         block_force_push_ruleset_id: None,
         codeowners_ruleset_id: None,
-        in_scope_asset_level: AssetLevel::Playground..=AssetLevel::Playground,
-        callout_asset_level: AssetLevel::Production..=AssetLevel::Production,
-        critical_asset_levels: AssetLevel::Production..=AssetLevel::Production,
-        github_auth: GitHubAuth::Token(std::env::var("GH_TOKEN").unwrap()),
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: max_ammann_contacts(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
     };
     evaluate_rule_suites(
         &bot,
         &config,
         &slack_client,
-        &create_octocrab(&config).expect("should create octocrab"),
+        &github,
+        &AssetLevelCache::default(),
         "KittyCAD/ruleset-policy-bot",
         "ruleset-policy-bot",
     )
@@ -351,15 +816,24 @@ async fn test_evaluate_rule_suites() {
         .expect("should not be locked")
         .borrow()
         .clone();
-    assert_eq!(messages.len(), 2); // One to actor one to max
+    assert_eq!(messages.len(), 1); // The escalation contact is the actor and gets a single copy
     insta::assert_debug_snapshot!(messages);
 
-    let slack_client = MockSlackClient {
-        messages: Mutex::new(RefCell::new(vec![])),
-    };
+    let slack_client = MockSlackClient::default();
 
     // Callout
 
+    // Evaluate the same bypass again
+    for event in bot
+        .events
+        .lock()
+        .as_ref()
+        .expect("should not be locked")
+        .borrow_mut()
+        .iter_mut()
+    {
+        event.notified = false;
+    }
     let config = BotConfig {
         github_org: "KittyCAD".to_string(),
         github_web_base_url: "https://github.com/".to_string(),
@@ -367,16 +841,26 @@ async fn test_evaluate_rule_suites() {
         review_requirement_ruleset_id: Some(11660672), // pretend the ruleset checks for reviews
         block_force_push_ruleset_id: None,
         codeowners_ruleset_id: None,
-        in_scope_asset_level: AssetLevel::Playground..=AssetLevel::Playground,
-        callout_asset_level: AssetLevel::Playground..=AssetLevel::Production, // call out anything
-        critical_asset_levels: AssetLevel::Playground..=AssetLevel::Production, // everything is critical
-        github_auth: GitHubAuth::Token(std::env::var("GH_TOKEN").unwrap()),
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(), // call out anything
+        critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(), // everything is critical
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: max_ammann_contacts(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
     };
     evaluate_rule_suites(
         &bot,
         &config,
         &slack_client,
-        &create_octocrab(&config).expect("should create octocrab"),
+        &github,
+        &AssetLevelCache::default(),
         "KittyCAD/ruleset-policy-bot",
         "ruleset-policy-bot",
     )
@@ -390,6 +874,2757 @@ async fn test_evaluate_rule_suites() {
         .expect("should not be locked")
         .borrow()
         .clone();
-    assert_eq!(messages.len(), 3); // one to max, one to actor, one to soc2 channel
+    assert_eq!(messages.len(), 2); // one to the actor, who is also the escalation contact, one to soc2 channel
     insta::assert_debug_snapshot!(messages);
+
+    // The decision of the last evaluation is stored with the event.
+    let decisions = bot.decisions.lock();
+    let decisions = decisions.as_ref().expect("should not be locked").borrow();
+    let (_, decision) = decisions.last().expect("should store decision");
+    let decision: PolicyDecision = serde_json::from_str(decision).expect("should deserialize");
+    assert!(decision.channel && decision.critical);
+    assert_eq!(
+        decision.matched_rules(),
+        [
+            "notify-actor",
+            "critical-violation",
+            "review-requirement-bypass"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_waiver_suppresses_callout() {
+    let rule_suite = ci_tests_rule_suite();
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: "1923052992".to_string(),
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+            event_data: serde_json::to_string(&rule_suite).expect("should serialize"),
+            resulting_commit: Some(COMMIT.to_string()),
+            prs: None,
+            ci_checks: None,
+            discarded_commits: None,
+            notified: false,
+        }])),
+        ..Default::default()
+    };
+    bot.create_waiver(NewWaiver {
+        repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+        ref_pattern: Some("refs/heads/ci-*".to_string()),
+        rule_types: vec!["pull_request".to_string()],
+        actor_name: Some("maxammann".to_string()),
+        reason: "Testing the bot".to_string(),
+        approver: "security@zoo.dev".to_string(),
+        expires_at: rule_suite.pushed_at + chrono::TimeDelta::days(1),
+        // The bypass was pushed before the waiver is created
+        retroactive: true,
+    })
+    .await
+    .unwrap();
+    let slack_client = MockSlackClient::default();
+    let github = FakeGitHub::new().with_custom_properties(
+        "KittyCAD",
+        "ruleset-policy-bot",
+        playground_custom_properties(),
+    );
+
+    evaluate_rule_suites(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: Some(11660672),
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    // Without the waiver, the bypass would be called out in the SOC2 channel.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1); // One to actor
+    assert!(messages.iter().all(|(channel, _)| channel != "#soc2"));
+
+    let decisions = bot.decisions.lock();
+    let decisions = decisions.as_ref().expect("should not be locked").borrow();
+    let (_, decision) = decisions.first().expect("should store decision");
+    let decision: PolicyDecision = serde_json::from_str(decision).expect("should deserialize");
+    assert_eq!(decision.waiver.map(|waiver| waiver.id), Some(1));
+}
+
+/// Associated pull requests of the review requirement bypass: one merged into a release branch
+/// carrying a policy exception label, and the one that landed the commit on `main`.
+fn review_requirement_pull_requests() -> Vec<PullRequest> {
+    let landing: PullRequest = serde_json::from_str(
+        &std::fs::read_to_string("tests/fixtures/pull_request_policy_exception.json")
+            .expect("fixture exists"),
+    )
+    .expect("valid pull request");
+    let mut backport = landing.clone();
+    backport.number = 7135;
+    backport.html_url = Some(
+        "https://github.com/KittyCAD/modeling-app/pull/7135"
+            .parse()
+            .expect("valid url"),
+    );
+    backport.base.ref_field = "release/1.0".to_string();
+    backport.merge_commit_sha = Some("c0ffee0d6da561e94313c17dfadbaf5b62b5f57".to_string());
+
+    let mut landing = landing;
+    landing.labels = None;
+
+    vec![backport, landing]
+}
+
+#[tokio::test]
+async fn test_all_associated_pull_requests_are_listed() {
+    let rule_suite: RuleSuite = serde_json::from_str(
+        &std::fs::read_to_string("tests/fixtures/bypass_review_requirement.json")
+            .expect("fixture exists"),
+    )
+    .expect("valid rule suite");
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: rule_suite.id.to_string(),
+            repository_full_name: "KittyCAD/modeling-app".to_string(),
+            event_data: serde_json::to_string(&rule_suite).expect("should serialize"),
+            resulting_commit: None,
+            prs: Some(
+                serde_json::to_string(&review_requirement_pull_requests())
+                    .expect("should serialize"),
+            ),
+            ci_checks: None,
+            discarded_commits: None,
+            notified: false,
+        }])),
+        ..Default::default()
+    };
+    let slack_client = MockSlackClient::default();
+    let github = FakeGitHub::new().with_custom_properties(
+        "KittyCAD",
+        "modeling-app",
+        playground_custom_properties(),
+    );
+
+    evaluate_rule_suites(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: Some(3973005),
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    // The exception label of the backport does not apply to the commit landed on `main`.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let (_, callout) = messages
+        .iter()
+        .find(|(channel, _)| channel == "#soc2")
+        .expect("should call out");
+
+    // Both pull requests are listed, the landing one first.
+    let fields = callout
+        .attachments
+        .as_ref()
+        .expect("should have attachments")[0]
+        .fields
+        .clone()
+        .expect("should have fields");
+    let prs = fields
+        .iter()
+        .find(|field| field.title.as_deref() == Some("Pull Requests"))
+        .expect("should list pull requests");
+    assert_eq!(
+        prs.value.as_deref(),
+        Some(
+            "<https://github.com/KittyCAD/modeling-app/pull/7131|#7131>, \
+             <https://github.com/KittyCAD/modeling-app/pull/7135|#7135>"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_processing_rule_suites_with_fake_github() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    assert_eq!(events.len(), 1);
+    let event = events.first().expect("should have event");
+    assert_eq!(event.github_id, "1923052992");
+    assert!(event.resulting_commit.is_some());
+    assert_eq!(event.prs.as_deref(), Some("[]"));
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1); // One to actor
+}
+
+#[tokio::test]
+async fn test_rule_suite_listed_late_is_ingested() {
+    let now = chrono::Utc::now();
+    let newest = RuleSuite {
+        id: 1923052993,
+        pushed_at: now - TimeDelta::minutes(1),
+        ..ci_tests_rule_suite()
+    };
+    // Pushed before the newest suite, but only listed by GitHub after it was ingested
+    let late = RuleSuite {
+        pushed_at: now - TimeDelta::minutes(10),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", newest.clone())
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", late.clone())
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let cursor = SyncCursor::new(&newest, now - TimeDelta::seconds(30));
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: newest.id.to_string(),
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+            event_data: serde_json::to_string(&newest).expect("should serialize"),
+            resulting_commit: Some(COMMIT.to_string()),
+            prs: None,
+            ci_checks: None,
+            discarded_commits: None,
+            notified: true,
+        }])),
+        sync_cursor: Mutex::new(RefCell::new(Some(cursor))),
+        ..Default::default()
+    };
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    let github_ids: Vec<&str> = events
+        .iter()
+        .map(|event| event.github_id.as_str())
+        .collect();
+    assert_eq!(github_ids, vec!["1923052993", "1923052992"]);
+
+    // The newest suite is not ingested twice and stays the high-water mark.
+    let synced = bot
+        .sync_cursor
+        .lock()
+        .as_ref()
+        .expect("should not be locked")
+        .borrow()
+        .expect("should have cursor");
+    assert_eq!(synced.rule_suite_id, newest.id);
+    assert!(synced.synced_at > cursor.synced_at);
+}
+
+#[tokio::test]
+async fn test_escalation_contacts_and_fallback() {
+    // The actor cannot be mapped to a Slack user.
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        actor_id: None,
+        actor_name: None,
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let contact = |contact: &str| -> EscalationContact { contact.parse().expect("valid contact") };
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts {
+                contacts: vec![contact("U0SEC0001")],
+                // Playground repositories are escalated to the security reviewers group. The
+                // contact that cannot be looked up is skipped.
+                overrides: vec![EscalationOverride {
+                    asset_levels: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+                    contacts: vec![contact("S0614TZR7"), contact("former-employee@zoo.invalid")],
+                }],
+                fallback: Some(contact("security@zoo.dev")),
+            },
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let recipients: Vec<&str> = messages
+        .iter()
+        .map(|(recipient, _)| recipient.as_str())
+        .collect();
+    assert_eq!(recipients, ["security@zoo.dev", "U0SEC0001", "U0SEC0002"]);
+
+    // The notification is addressed to the fallback recipient.
+    let text = messages[0].1.text.as_deref().expect("should have text");
+    assert!(text.starts_with("<@security@zoo.dev>, please"), "{text}");
+}
+
+#[tokio::test]
+async fn test_fallback_user_group_is_mentioned_and_notified_once() {
+    // The actor cannot be mapped to a Slack user.
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        actor_id: None,
+        actor_name: None,
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let contact = |contact: &str| -> EscalationContact { contact.parse().expect("valid contact") };
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            escalation_contacts: EscalationContacts {
+                contacts: vec![contact("U0SEC0001")],
+                overrides: vec![],
+                fallback: Some(contact("S0614TZR7")),
+            },
+            ..notifier_config(vec![])
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let recipients: Vec<&str> = messages
+        .iter()
+        .map(|(recipient, _)| recipient.as_str())
+        .collect();
+    // The contact is a member of the fallback group and gets no second copy.
+    assert_eq!(recipients, ["U0SEC0001", "U0SEC0002"]);
+
+    // The notification is addressed to the whole group.
+    let text = messages[0].1.text.as_deref().expect("should have text");
+    assert!(text.starts_with("<!subteam^S0614TZR7>, please"), "{text}");
+}
+
+#[tokio::test]
+async fn test_ref_filter_skips_other_refs() {
+    let main_rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ref_name: "refs/heads/main".to_string(),
+        ..ci_tests_rule_suite()
+    };
+    let tag_rule_suite = RuleSuite {
+        id: 1923052993,
+        ref_name: "refs/tags/v1.0.0".to_string(),
+        ..main_rule_suite.clone()
+    };
+    let branch_rule_suite = RuleSuite {
+        id: 1923052994,
+        ..main_rule_suite.clone()
+    };
+    let config = |ref_filter| BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter,
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", main_rule_suite)
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", tag_rule_suite)
+        .with_rule_suite(
+            "KittyCAD/ruleset-policy-bot",
+            RuleSuite {
+                ref_name: "refs/heads/ci-tests".to_string(),
+                ..branch_rule_suite
+            },
+        )
+        .with_default_branch("KittyCAD", "ruleset-policy-bot", "main")
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &config(RefFilter {
+            include: vec![DEFAULT_BRANCH.to_string(), "refs/heads/ci-*".to_string()],
+            exclude: vec!["refs/heads/ci-tests".to_string()],
+        }),
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    // Only the bypass on the default branch is recorded.
+    {
+        let events = bot.events.lock();
+        let events = events.as_ref().expect("should not be locked").borrow();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].github_id, "1923052992");
+    }
+
+    // If the default branch cannot be resolved, nothing is skipped silently.
+    let github = FakeGitHub::new().with_rule_suite(
+        "KittyCAD/ruleset-policy-bot",
+        RuleSuite {
+            pushed_at: chrono::Utc::now(),
+            ..ci_tests_rule_suite()
+        },
+    );
+    let result = process_rule_suites_with_github(
+        &MockRulesetBot::default(),
+        &config(RefFilter {
+            include: vec![DEFAULT_BRANCH.to_string()],
+            exclude: vec![],
+        }),
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_bot_bypasses_are_recorded_unless_trusted() {
+    let bot_rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        actor_id: Some(155849648),
+        actor_name: Some("zoo-github-actions-auth[bot]".to_string()),
+        ..ci_tests_rule_suite()
+    };
+    let trusted_rule_suite = RuleSuite {
+        id: 1923052993,
+        actor_id: Some(49699333),
+        actor_name: Some("dependabot[bot]".to_string()),
+        ..bot_rule_suite.clone()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", bot_rule_suite)
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", trusted_rule_suite)
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    // Only the bypass by the untrusted bot is recorded.
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].github_id, "1923052992");
+
+    // It is called out in the SOC2 channel although Playground is below the callout levels.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert!(messages.iter().any(|(channel, _)| channel == "#soc2"));
+}
+
+#[tokio::test]
+async fn test_empty_trusted_actors_are_rejected() {
+    let result = process_rule_suites_with_github(
+        &MockRulesetBot::default(),
+        &BotConfig {
+            trusted_actors: vec![],
+            ..notifier_config(vec![])
+        },
+        &MockSlackClient::default(),
+        &FakeGitHub::new(),
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await;
+
+    let error = result.expect_err("should be rejected").to_string();
+    assert!(error.contains("TrustedActor::dependabot()"));
+}
+
+#[tokio::test]
+async fn test_ci_bypass_reports_failing_checks() {
+    let load = |name: &str| {
+        std::fs::read_to_string(format!("tests/fixtures/{name}")).expect("fixture exists")
+    };
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..serde_json::from_str(&load("bypass_required_checks.json")).expect("valid rule suite")
+    };
+    let check_runs: CheckRunList =
+        serde_json::from_str(&load("check_runs.json")).expect("valid check runs");
+    let commit_status: CombinedStatus =
+        serde_json::from_str(&load("commit_status.json")).expect("valid commit status");
+    let github = FakeGitHub::new()
+        .with_check_runs(&rule_suite.after_sha, check_runs.check_runs)
+        .with_commit_statuses(&rule_suite.after_sha, commit_status.statuses)
+        .with_ruleset(
+            "KittyCAD/modeling-app",
+            serde_json::from_str(&load("ruleset_required_checks.json")).expect("valid ruleset"),
+        )
+        .with_rule_suite("KittyCAD/modeling-app", rule_suite)
+        .with_custom_properties("KittyCAD", "modeling-app", playground_custom_properties());
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: Some(3973005),
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    // The checks are stored with the event.
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    let ci_checks: CiChecks = serde_json::from_str(
+        events[0]
+            .ci_checks
+            .as_deref()
+            .expect("should store CI checks"),
+    )
+    .expect("should deserialize");
+    assert_eq!(
+        ci_checks.failing,
+        [
+            "build (failure)",
+            "audit (timed_out)",
+            "netlify/deploy-preview (error)"
+        ]
+    );
+    // `lint` is reported as a commit status.
+    assert_eq!(ci_checks.missing, ["e2e"]);
+
+    let decisions = bot.decisions.lock();
+    let decisions = decisions.as_ref().expect("should not be locked").borrow();
+    let decision: PolicyDecision =
+        serde_json::from_str(&decisions[0].1).expect("should deserialize");
+    assert_eq!(decision.categories, [ViolationCategory::Ci]);
+    assert!(decision.matched_rules().contains(&"ci-bypass"));
+
+    // The callout lists the checks.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let (_, callout) = messages
+        .iter()
+        .find(|(channel, _)| channel == "#soc2")
+        .expect("should call out");
+    let fields = callout
+        .attachments
+        .as_ref()
+        .expect("should have attachments")[0]
+        .fields
+        .clone()
+        .expect("should have fields");
+    let checks = fields
+        .iter()
+        .find(|field| field.title.as_deref() == Some("Checks"))
+        .expect("should list checks");
+    assert_eq!(
+        checks.value.as_deref(),
+        Some(
+            "Failing: `build (failure)`, `audit (timed_out)`, `netlify/deploy-preview (error)`\n\
+             Missing: `e2e`"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_force_push_reports_discarded_commits() {
+    let load = |name: &str| {
+        std::fs::read_to_string(format!("tests/fixtures/{name}")).expect("fixture exists")
+    };
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..serde_json::from_str(&load("bypass_force_push.json")).expect("valid rule suite")
+    };
+    let github = FakeGitHub::new()
+        .with_comparison(
+            &rule_suite.after_sha,
+            &rule_suite.before_sha,
+            serde_json::from_str(&load("compare_force_push.json")).expect("valid comparison"),
+        )
+        .with_rule_suite("KittyCAD/modeling-app", rule_suite)
+        .with_custom_properties("KittyCAD", "modeling-app", playground_custom_properties());
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: Some(3973005),
+            block_force_push_ruleset_id: Some(4047011),
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    // The discarded commits are stored as evidence with the event.
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    let discarded_commits: DiscardedCommits = serde_json::from_str(
+        events[0]
+            .discarded_commits
+            .as_deref()
+            .expect("should store discarded commits"),
+    )
+    .expect("should deserialize");
+    assert_eq!(discarded_commits.count, 3);
+    assert_eq!(discarded_commits.authors(), ["jessfraz", "Jane Doe"]);
+
+    // The callout lists them.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let (_, callout) = messages
+        .iter()
+        .find(|(channel, _)| channel == "#soc2")
+        .expect("should call out");
+    let fields = callout
+        .attachments
+        .as_ref()
+        .expect("should have attachments")[0]
+        .fields
+        .clone()
+        .expect("should have fields");
+    let discarded = fields
+        .iter()
+        .find(|field| field.title.as_deref() == Some("Discarded Commits"))
+        .expect("should list discarded commits");
+    // Rendered with Slack links
+    assert_eq!(
+        discarded.value.as_deref(),
+        Some(markdown_links_to_slack(&discarded_commits.to_string()).as_str())
+    );
+    assert!(
+        discarded
+            .value
+            .as_deref()
+            .is_some_and(|value| value.contains("<https://github.com/KittyCAD/"))
+    );
+    assert!(
+        discarded_commits
+            .to_string()
+            .starts_with("3 commit(s) by `jessfraz`, `Jane Doe`: ")
+    );
+}
+
+fn force_push_github() -> FakeGitHub {
+    let load = |name: &str| {
+        std::fs::read_to_string(format!("tests/fixtures/{name}")).expect("fixture exists")
+    };
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..serde_json::from_str(&load("bypass_force_push.json")).expect("valid rule suite")
+    };
+    FakeGitHub::new()
+        .with_comparison(
+            &rule_suite.after_sha,
+            &rule_suite.before_sha,
+            serde_json::from_str(&load("compare_force_push.json")).expect("valid comparison"),
+        )
+        .with_rule_suite("KittyCAD/modeling-app", rule_suite)
+        .with_custom_properties("KittyCAD", "modeling-app", playground_custom_properties())
+}
+
+fn notifier_config(notifiers: Vec<Arc<dyn Notifier>>) -> BotConfig {
+    BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: Some(3973005),
+        block_force_push_ruleset_id: Some(4047011),
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        callout_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers,
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    }
+}
+
+/// Records the violations it is notified of
+#[derive(Debug, Default)]
+struct RecordingNotifier {
+    violations: Mutex<Vec<Violation>>,
+}
+
+#[async_trait]
+impl Notifier for RecordingNotifier {
+    async fn notify(&self, violation: &Violation) -> anyhow::Result<()> {
+        self.violations
+            .lock()
+            .expect("should not be poisoned")
+            .push(violation.clone());
+        Ok(())
+    }
+}
+
+/// Fails every notification
+#[derive(Debug)]
+struct FailingNotifier;
+
+#[async_trait]
+impl Notifier for FailingNotifier {
+    async fn notify(&self, _violation: &Violation) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("the channel is down"))
+    }
+}
+
+/// Never finishes a notification
+#[derive(Debug)]
+struct HangingNotifier;
+
+#[async_trait]
+impl Notifier for HangingNotifier {
+    async fn notify(&self, _violation: &Violation) -> anyhow::Result<()> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_notifiers_receive_violations() {
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let security_slack = Arc::new(MockSlackClient::default());
+    let recording = Arc::new(RecordingNotifier::default());
+    let config = notifier_config(vec![
+        // A failing channel does not keep the others from being notified
+        Arc::new(FailingNotifier),
+        recording.clone(),
+        Arc::new(SlackNotifier {
+            client: security_slack.clone(),
+            channel: SlackChannelId("#security".to_string()),
+        }),
+    ]);
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &force_push_github(),
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    let violations = recording.violations.lock().expect("should not be poisoned");
+    assert_eq!(violations.len(), 1);
+    let violation = &violations[0];
+    assert_eq!(violation.repository, "modeling-app");
+    assert!(violation.commit_url.ends_with(&format!(
+        "/KittyCAD/modeling-app/commit/{}",
+        violation.commit_sha
+    )));
+    let discarded = violation.rules[0]
+        .fields
+        .iter()
+        .find(|field| field.title == "Discarded Commits")
+        .expect("should list discarded commits");
+    assert!(
+        discarded
+            .value
+            .contains("[`4a1f6e2`](https://github.com/KittyCAD/modeling-app/commit/"),
+        "{}",
+        discarded.value
+    );
+    // Channel-neutral Markdown
+    assert!(
+        violation.explanation.starts_with("**Policy decision:**"),
+        "{}",
+        violation.explanation
+    );
+
+    // The other Slack channel receives the notification of the SOC2 channel.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let (_, callout) = messages
+        .iter()
+        .find(|(channel, _)| channel == "#soc2")
+        .expect("should call out");
+    let security_messages = security_slack.messages.lock();
+    let security_messages = security_messages
+        .as_ref()
+        .expect("should not be locked")
+        .borrow();
+    assert_eq!(security_messages.len(), 1);
+    let (channel, content) = &security_messages[0];
+    assert_eq!(channel, "#security");
+    // Only the addressee differs
+    let description = |content: &SlackMessageContent| {
+        content
+            .text
+            .as_deref()
+            .and_then(|text| text.split_once("\n\n"))
+            .map(|(_, description)| description.to_string())
+    };
+    assert!(description(content).is_some());
+    assert_eq!(description(content), description(callout));
+    assert_eq!(
+        format!("{:?}", content.attachments),
+        format!("{:?}", callout.attachments)
+    );
+    // Actions are only taken on the notifications of the bot
+    let has_buttons = |content: &SlackMessageContent| {
+        content
+            .blocks
+            .iter()
+            .flatten()
+            .any(|block| matches!(block, SlackBlock::Actions(_)))
+    };
+    assert!(has_buttons(callout));
+    assert!(!has_buttons(content));
+    assert!(
+        format!("{:?}", content.blocks).contains("*Policy decision:* DM"),
+        "{:?}",
+        content.blocks
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_hanging_notifier_times_out() {
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let recording = Arc::new(RecordingNotifier::default());
+    // A hanging channel does not keep the others from being notified
+    let config = notifier_config(vec![Arc::new(HangingNotifier), recording.clone()]);
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &force_push_github(),
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        recording
+            .violations
+            .lock()
+            .expect("should not be poisoned")
+            .len(),
+        1
+    );
+}
+
+#[cfg(feature = "notify")]
+#[tokio::test]
+async fn test_notifier_backends() {
+    use ruleset_policy_bot::notify::email::SmtpNotifier;
+    use ruleset_policy_bot::notify::json_webhook::JsonWebhookNotifier;
+    use ruleset_policy_bot::notify::teams::TeamsNotifier;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Accepts one HTTP request and returns its request line and JSON body
+    async fn http_sink() -> (String, tokio::task::JoinHandle<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+        let url = format!(
+            "http://{}/hook",
+            listener.local_addr().expect("has address")
+        );
+        let sink = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("should accept");
+            let mut stream = BufReader::new(stream);
+            let mut request_line = String::new();
+            stream
+                .read_line(&mut request_line)
+                .await
+                .expect("request line");
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                stream.read_line(&mut header).await.expect("header");
+                if header.trim_end().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().expect("valid length");
+                }
+            }
+            let mut body = vec![0; content_length];
+            stream.read_exact(&mut body).await.expect("body");
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .expect("should respond");
+            (
+                request_line.trim_end().to_string(),
+                serde_json::from_slice(&body).expect("JSON body"),
+            )
+        });
+        (url, sink)
+    }
+
+    /// Accepts one SMTP session and returns the commands and the message
+    async fn smtp_sink() -> (u16, tokio::task::JoinHandle<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+        let port = listener.local_addr().expect("has address").port();
+        let sink = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("should accept");
+            let mut stream = BufReader::new(stream);
+            let (mut commands, mut message) = (vec![], String::new());
+            stream
+                .write_all(b"220 sink ESMTP\r\n")
+                .await
+                .expect("greet");
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.expect("command") == 0 {
+                    break;
+                }
+                let command = line.trim_end().to_string();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250-sink\r\n250 8BITMIME\r\n"
+                } else if command == "DATA" {
+                    b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                } else if command == "QUIT" {
+                    b"221 Bye\r\n"
+                } else {
+                    b"250 OK\r\n"
+                };
+                commands.push(command.clone());
+                stream.write_all(reply).await.expect("reply");
+                if command == "DATA" {
+                    loop {
+                        let mut line = String::new();
+                        stream.read_line(&mut line).await.expect("data");
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        message.push_str(&line);
+                    }
+                    stream.write_all(b"250 Queued\r\n").await.expect("reply");
+                }
+                if command == "QUIT" {
+                    break;
+                }
+            }
+            (commands, message)
+        });
+        (port, sink)
+    }
+
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let (webhook_url, webhook_sink) = http_sink().await;
+    let (teams_url, teams_sink) = http_sink().await;
+    let (smtp_port, smtp_sink) = smtp_sink().await;
+    let config = notifier_config(vec![
+        Arc::new(JsonWebhookNotifier { url: webhook_url }),
+        Arc::new(TeamsNotifier { url: teams_url }),
+        Arc::new(SmtpNotifier {
+            host: "127.0.0.1".to_string(),
+            port: smtp_port,
+            hello_name: "ruleset-policy-bot.local".to_string(),
+            from: "ruleset-policy-bot@zoo.dev".to_string(),
+            to: vec!["security@zoo.dev".to_string()],
+        }),
+    ]);
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &force_push_github(),
+        &AssetLevelCache::default(),
+        "KittyCAD/modeling-app",
+        "modeling-app",
+    )
+    .await
+    .unwrap();
+
+    let (request_line, body) = webhook_sink.await.expect("webhook sink");
+    assert_eq!(request_line, "POST /hook HTTP/1.1");
+    let violation: Violation = serde_json::from_value(body).expect("should be a violation");
+    assert_eq!(violation.repository, "modeling-app");
+    assert_eq!(violation.rules[0].rule_type, "non_fast_forward");
+
+    let (_, card) = teams_sink.await.expect("Teams sink");
+    assert_eq!(card["type"], "message");
+    let attachment = &card["attachments"][0];
+    assert_eq!(
+        attachment["contentType"],
+        "application/vnd.microsoft.card.adaptive"
+    );
+    assert_eq!(attachment["content"]["body"][0]["text"], violation.title());
+    assert_eq!(
+        attachment["content"]["actions"][0]["url"],
+        violation.commit_url.as_str()
+    );
+
+    let (commands, message) = smtp_sink.await.expect("SMTP sink");
+    assert_eq!(
+        commands,
+        [
+            "EHLO ruleset-policy-bot.local",
+            "MAIL FROM:<ruleset-policy-bot@zoo.dev>",
+            "RCPT TO:<security@zoo.dev>",
+            "DATA",
+            "QUIT",
+        ]
+    );
+    assert!(
+        message.contains(&format!(
+            "Subject: {} in modeling-app\r\n",
+            violation.title()
+        )),
+        "{message}"
+    );
+    assert!(
+        message.contains("Discarded Commits: 3 commit(s) by `jessfraz`, `Jane Doe`: `4a1f6e2` (https://github.com/KittyCAD/modeling-app/commit/"),
+        "{message}"
+    );
+}
+
+fn retroactive_review_config(window: TimeDelta) -> BotConfig {
+    BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: Some(RetroactiveReviewConfig {
+            window,
+            ..Default::default()
+        }),
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_retroactive_review_resolves_bypass() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    // The push has no pull request, it is reviewed in a follow-up pull request.
+    let mut follow_up: PullRequest = serde_json::from_str(
+        &std::fs::read_to_string("tests/fixtures/pull_request_post_merge_review.json")
+            .expect("fixture exists"),
+    )
+    .expect("valid pull request");
+    follow_up.body = Some("Reviews d6602d2, which was pushed directly to ci-tests.".to_string());
+    follow_up.created_at = Some(rule_suite.pushed_at + TimeDelta::minutes(1));
+    let review = PullRequestReview {
+        id: 2843719005,
+        user: Some(GitHubAccount {
+            login: "jessfraz".to_string(),
+            id: 1445228,
+        }),
+        state: "APPROVED".to_string(),
+        submitted_at: Some(rule_suite.pushed_at + TimeDelta::minutes(5)),
+        author_association: Some("MEMBER".to_string()),
+        html_url:
+            "https://github.com/KittyCAD/ruleset-policy-bot/pull/7140#pullrequestreview-2843719005"
+                .to_string(),
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_pull_request("KittyCAD", "ruleset-policy-bot", follow_up)
+        .with_pull_request_reviews(7140, vec![review])
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &retroactive_review_config(TimeDelta::days(7)),
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    // The bypass was pending first, then resolved.
+    let reviews = bot.reviews.lock();
+    let reviews = reviews.as_ref().expect("should not be locked").borrow();
+    assert_eq!(reviews.len(), 2);
+    let pending: ReviewStatus = serde_json::from_str(&reviews[0].1).expect("should deserialize");
+    assert!(pending.is_pending());
+    let ReviewStatus::Approved(approval) =
+        serde_json::from_str(&reviews[1].1).expect("should deserialize")
+    else {
+        panic!("should be approved: {}", reviews[1].1);
+    };
+    assert_eq!(approval.source, ApprovalSource::FollowUpPullRequest);
+    assert_eq!(approval.reviewer, "jessfraz");
+
+    // Nothing is escalated.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert!(!messages.iter().any(|(channel, _)| channel == "#soc2"));
+}
+
+#[tokio::test]
+async fn test_unreviewed_bypass_escalates() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now() - TimeDelta::hours(2),
+        ..ci_tests_rule_suite()
+    };
+    // The actor cannot approve their own push.
+    let comment = CommitComment {
+        id: 157301004,
+        user: Some(GitHubAccount {
+            login: "maxammann".to_string(),
+            id: 905221,
+        }),
+        body: "Post-merge review: approved".to_string(),
+        created_at: rule_suite.pushed_at + TimeDelta::minutes(5),
+        author_association: Some("MEMBER".to_string()),
+        html_url: "https://github.com/KittyCAD/ruleset-policy-bot/commit/d6602d2416760fb1bee076fbd895b97e41a0f0f7#commitcomment-157301004"
+            .to_string(),
+    };
+    let github = FakeGitHub::new()
+        .with_commit_comments(&rule_suite.after_sha, vec![comment])
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_rule_suites_with_github(
+        &bot,
+        &retroactive_review_config(TimeDelta::hours(1)),
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let reviews = bot.reviews.lock();
+    let reviews = reviews.as_ref().expect("should not be locked").borrow();
+    let status: ReviewStatus = serde_json::from_str(&reviews.last().expect("should have review").1)
+        .expect("should deserialize");
+    assert!(matches!(status, ReviewStatus::Escalated { .. }));
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let (_, escalation) = messages
+        .iter()
+        .find(|(channel, _)| channel == "#soc2")
+        .expect("should escalate");
+    let text = escalation.text.as_deref().expect("should have text");
+    assert!(text.contains("was not approved within 1 hour(s)"), "{text}");
+}
+
+#[tokio::test]
+async fn test_thread_reply_justifies_bypass() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let reply = |user: &str, text: &str, ts: &str| SlackThreadReply {
+        user: Some(SlackUserId(user.to_string())),
+        text: text.to_string(),
+        ts: SlackTs(ts.to_string()),
+    };
+    // Only the reply of the actor justifies the bypass.
+    let slack_client = MockSlackClient {
+        replies: vec![
+            reply("U0SEC0001", "Why was this pushed?", "1700000060.000000"),
+            reply(
+                "max.ammann@zoo.dev",
+                "Resetting the CI test branch",
+                "1700000120.000000",
+            ),
+        ],
+        ..Default::default()
+    };
+
+    // Only critical bypasses are justified. The notifications of the mock are posted in 2023, the
+    // threads are read until the bypass is escalated.
+    process_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            review_requirement_ruleset_id: Some(11660672),
+            critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+            retroactive_review: None,
+            reminders: Some(ReminderConfig::default()),
+            notifiers: vec![],
+            ..retroactive_review_config(TimeDelta::days(7))
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    {
+        let slack_messages = bot.slack_messages.lock();
+        let slack_messages = slack_messages
+            .as_ref()
+            .expect("should not be locked")
+            .borrow();
+        let posted: Vec<PostedMessage> =
+            serde_json::from_str(&slack_messages.last().expect("should store messages").1)
+                .expect("should deserialize");
+        assert!(
+            posted
+                .iter()
+                .any(|message| message.channel.0 == "max.ammann@zoo.dev"),
+            "the actor should be asked: {posted:?}"
+        );
+    }
+
+    let justifications = bot.justifications.lock();
+    let justifications = justifications
+        .as_ref()
+        .expect("should not be locked")
+        .borrow();
+    assert_eq!(justifications.len(), 1);
+    let justification: Justification =
+        serde_json::from_str(&justifications[0].1).expect("should deserialize");
+    assert_eq!(justification.text, "Resetting the CI test branch");
+    assert_eq!(justification.user.0, "max.ammann@zoo.dev");
+    assert_eq!(justification.justified_at.timestamp(), 1_700_000_120);
+}
+
+#[tokio::test]
+async fn test_unjustified_critical_bypass_is_reminded_and_escalated() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    // The actor only replies to someone else's thread.
+    let slack_client = MockSlackClient {
+        replies: vec![SlackThreadReply {
+            user: Some(SlackUserId("U0SEC0001".to_string())),
+            text: "Why was this pushed?".to_string(),
+            ts: SlackTs("1700000060.000000".to_string()),
+        }],
+        ..Default::default()
+    };
+    // The notifications of the mock are posted in 2023, the reminder is due right away.
+    let config = BotConfig {
+        review_requirement_ruleset_id: Some(11660672),
+        critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        retroactive_review: None,
+        reminders: Some(ReminderConfig {
+            escalate_after: TimeDelta::zero(),
+            security_owners: vec![EscalationContact::UserGroup("S0614TZR7".to_string())],
+            ..Default::default()
+        }),
+        ..retroactive_review_config(TimeDelta::days(7))
+    };
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let reminder = |bot: &MockRulesetBot| -> ReminderStatus {
+        let reminders = bot.reminders.lock();
+        let reminders = reminders.as_ref().expect("should not be locked").borrow();
+        serde_json::from_str(&reminders.last().expect("should store reminder").1)
+            .expect("should deserialize")
+    };
+    assert!(matches!(reminder(&bot), ReminderStatus::Reminded { .. }));
+    {
+        let messages = slack_client.messages.lock();
+        let messages = messages.as_ref().expect("should not be locked").borrow();
+        let (recipient, content) = messages.last().expect("should remind");
+        assert_eq!(recipient, "max.ammann@zoo.dev");
+        let text = content.text.as_deref().expect("should have text");
+        assert!(text.contains("was not justified within 1 day(s)"), "{text}");
+    }
+
+    // The escalation is due on the next run, and sent once.
+    remind_unjustified(&bot, &config, &slack_client, None)
+        .await
+        .unwrap();
+    remind_unjustified(&bot, &config, &slack_client, None)
+        .await
+        .unwrap();
+
+    assert!(matches!(reminder(&bot), ReminderStatus::Escalated { .. }));
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let escalations: Vec<_> = messages
+        .iter()
+        .filter(|(_, content)| {
+            content
+                .text
+                .as_deref()
+                .is_some_and(|text| text.contains("still not justified"))
+        })
+        .collect();
+    assert_eq!(escalations.len(), 1);
+    let (channel, escalation) = escalations[0];
+    assert_eq!(channel, "#soc2");
+    let text = escalation.text.as_deref().expect("should have text");
+    assert!(text.contains("<@U0SEC0001> <@U0SEC0002>"), "{text}");
+}
+
+#[tokio::test]
+async fn test_bypass_of_unknown_actor_is_justified_by_fallback_recipient() {
+    // The actor cannot be mapped to a Slack user.
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        actor_id: None,
+        actor_name: None,
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let new_bot = || MockRulesetBot::default();
+    let new_slack_client = || MockSlackClient {
+        replies: vec![SlackThreadReply {
+            user: Some(SlackUserId("U0SEC0002".to_string())),
+            text: "Pushed on behalf of the release bot".to_string(),
+            ts: SlackTs("1700000060.000000".to_string()),
+        }],
+        ..Default::default()
+    };
+    // The notifications of the mock are posted in 2023, the reminder is due right away.
+    let config = BotConfig {
+        review_requirement_ruleset_id: Some(11660672),
+        critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        retroactive_review: None,
+        reminders: Some(ReminderConfig {
+            escalate_after: TimeDelta::zero(),
+            ..Default::default()
+        }),
+        ..retroactive_review_config(TimeDelta::days(7))
+    };
+
+    // The reply of a member of the fallback group justifies the bypass.
+    let bot = new_bot();
+    let fallback_config = BotConfig {
+        escalation_contacts: EscalationContacts {
+            fallback: Some(EscalationContact::UserGroup("S0614TZR7".to_string())),
+            ..EscalationContacts::default()
+        },
+        ..config.clone()
+    };
+    process_rule_suites_with_github(
+        &bot,
+        &fallback_config,
+        &new_slack_client(),
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+    {
+        let justifications = bot.justifications.lock();
+        let justifications = justifications
+            .as_ref()
+            .expect("should not be locked")
+            .borrow();
+        assert_eq!(justifications.len(), 1);
+        let justification: Justification =
+            serde_json::from_str(&justifications[0].1).expect("should deserialize");
+        assert_eq!(justification.user.0, "U0SEC0002");
+    }
+
+    // Without a fallback recipient, nobody was asked, reminded or escalated to.
+    let bot = new_bot();
+    let slack_client = new_slack_client();
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+    remind_unjustified(&bot, &config, &slack_client, None)
+        .await
+        .unwrap();
+
+    let reminders = bot.reminders.lock();
+    assert!(
+        reminders
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .is_empty()
+    );
+    let messages = slack_client.messages.lock();
+    assert!(
+        messages
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_false_positive_is_neither_reviewed_justified_nor_reminded() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now() - TimeDelta::hours(2),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    // Security marked the bypass as false positive right after the notification.
+    let false_positive = ViolationAction::FalsePositive {
+        user: SlackUserId("U0SEC0001".to_string()),
+        reason: "The ruleset was misconfigured".to_string(),
+        at: chrono::Utc::now(),
+    };
+    let bot = MockRulesetBot {
+        actions: Mutex::new(RefCell::new(vec![(
+            123,
+            serde_json::to_string(&[false_positive]).expect("should serialize"),
+        )])),
+        ..Default::default()
+    };
+    let slack_client = MockSlackClient {
+        replies: vec![SlackThreadReply {
+            user: Some(SlackUserId("max.ammann@zoo.dev".to_string())),
+            text: "Resetting the CI test branch".to_string(),
+            ts: SlackTs("1700000060.000000".to_string()),
+        }],
+        ..Default::default()
+    };
+    // The review window has passed, and the reminders of the 2023 mock notifications are due.
+    let config = BotConfig {
+        review_requirement_ruleset_id: Some(11660672),
+        critical_asset_levels: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        reminders: Some(ReminderConfig {
+            escalate_after: TimeDelta::zero(),
+            ..Default::default()
+        }),
+        ..retroactive_review_config(TimeDelta::hours(1))
+    };
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+    remind_unjustified(&bot, &config, &slack_client, None)
+        .await
+        .unwrap();
+
+    let reviews = bot.reviews.lock();
+    let reviews = reviews.as_ref().expect("should not be locked").borrow();
+    let status: ReviewStatus =
+        serde_json::from_str(&reviews.last().expect("should store review").1)
+            .expect("should deserialize");
+    assert!(
+        matches!(status, ReviewStatus::Dismissed { .. }),
+        "{status:?}"
+    );
+    for stored in [&bot.justifications, &bot.reminders] {
+        assert!(
+            stored
+                .lock()
+                .as_ref()
+                .expect("should not be locked")
+                .borrow()
+                .is_empty()
+        );
+    }
+    // Only the notification of the actor was sent.
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    let recipients: Vec<&str> = messages
+        .iter()
+        .map(|(recipient, _)| recipient.as_str())
+        .collect();
+    assert_eq!(recipients, ["max.ammann@zoo.dev"]);
+}
+
+#[tokio::test]
+async fn test_shadow_report_includes_passing_pushes() {
+    let load = |name: &str| -> RuleSuite {
+        let json =
+            std::fs::read_to_string(format!("tests/fixtures/{name}")).expect("fixture exists");
+        RuleSuite {
+            pushed_at: chrono::Utc::now(),
+            ..serde_json::from_str(&json).expect("valid rule suite")
+        }
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/kcl-samples", load("pass_evaluate_fail.json"))
+        .with_rule_suite("KittyCAD/modeling-app", load("bypass_evaluate_only.json"))
+        .with_rule_suite("KittyCAD/modeling-app", load("bypass_force_push.json"));
+    let config = BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    };
+
+    let report = build_shadow_report_with_github(&config, &github)
+        .await
+        .unwrap();
+
+    // The passing push is included although only bypasses are listed for notifications.
+    let summary = &report.rulesets["3757757"];
+    assert_eq!(summary.rule_suites, 2);
+    assert_eq!(summary.newly_blocked, 1);
+
+    let slack_client = MockSlackClient::default();
+    send_shadow_report(&slack_client, &config, &report)
+        .await
+        .unwrap();
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].0, "#soc2");
+}
+
+#[tokio::test]
+async fn test_processing_org_rule_suites_with_fake_github() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let other_rule_suite = RuleSuite {
+        id: 1923052993,
+        repository_id: 1108572606,
+        repository_name: "other-repo".to_string(),
+        ..rule_suite.clone()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite)
+        .with_rule_suite("KittyCAD/other-repo", other_rule_suite)
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        )
+        .with_custom_properties("KittyCAD", "other-repo", playground_custom_properties());
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    process_org_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+    )
+    .await
+    .unwrap();
+
+    let events = bot.events.lock();
+    let events = events.as_ref().expect("should not be locked").borrow();
+    let mut repositories: Vec<&str> = events
+        .iter()
+        .map(|event| event.repository_full_name.as_str())
+        .collect();
+    repositories.sort();
+    assert_eq!(
+        repositories,
+        vec!["KittyCAD/other-repo", "KittyCAD/ruleset-policy-bot"]
+    );
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 2); // One to actor per repository
+}
+
+#[tokio::test]
+async fn test_org_sweep_retries_unnotified_rule_suites() {
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite.clone())
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    // The rule suite was ingested by an earlier run, which failed to notify about it
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: rule_suite.id.to_string(),
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+            event_data: serde_json::to_string(&rule_suite).expect("should serialize"),
+            resulting_commit: Some(COMMIT.to_string()),
+            prs: None,
+            ci_checks: None,
+            discarded_commits: None,
+            notified: false,
+        }])),
+        sync_cursor: Mutex::new(RefCell::new(Some(SyncCursor::new(
+            &rule_suite,
+            rule_suite.pushed_at,
+        )))),
+        ..Default::default()
+    };
+    let slack_client = MockSlackClient::default();
+
+    process_org_rule_suites_with_github(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        bot.events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .len(),
+        1
+    );
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1); // The retried notification of the actor
+}
+
+#[cfg(feature = "webhook")]
+#[tokio::test]
+async fn test_push_webhook_processes_repository() {
+    use hmac::{Hmac, Mac};
+    use ruleset_policy_bot::webhook::{
+        WebhookDelivery, WebhookError, WebhookOutcome, handle_webhook,
+    };
+    use sha2::Sha256;
+
+    let secret = "webhook-secret";
+    let body = br#"{
+        "ref": "refs/heads/ci-tests",
+        "repository": {
+            "name": "ruleset-policy-bot",
+            "full_name": "KittyCAD/ruleset-policy-bot",
+            "owner": { "login": "KittyCAD" }
+        }
+    }"#;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("valid key");
+    mac.update(body);
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    let github = FakeGitHub::new()
+        .with_rule_suite(
+            "KittyCAD/ruleset-policy-bot",
+            RuleSuite {
+                pushed_at: chrono::Utc::now(),
+                ..ci_tests_rule_suite()
+            },
+        )
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let config = BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    };
+
+    let tampered = handle_webhook(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "wrong-secret",
+        WebhookDelivery::from_headers(
+            [
+                ("X-GitHub-Event", "push"),
+                ("X-Hub-Signature-256", &signature),
+            ],
+            body,
+        )
+        .expect("valid headers"),
+    )
+    .await;
+    assert!(matches!(tampered, Err(WebhookError::InvalidSignature)));
+
+    let outcome = handle_webhook(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        secret,
+        WebhookDelivery::from_headers(
+            [
+                ("X-GitHub-Event", "push"),
+                ("X-Hub-Signature-256", &signature),
+            ],
+            body,
+        )
+        .expect("valid headers"),
+    )
+    .await
+    .expect("should handle webhook");
+
+    assert_eq!(
+        outcome,
+        WebhookOutcome::Repository {
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string()
+        }
+    );
+    let events = bot.events.lock();
+    assert_eq!(
+        events
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .len(),
+        1
+    );
+}
+
+/// Sends the request head and body and returns the status line of the response
+#[cfg(feature = "webhook-server")]
+async fn status(address: std::net::SocketAddr, head: String, body: &[u8]) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(address)
+        .await
+        .expect("should connect");
+    stream
+        .write_all(head.as_bytes())
+        .await
+        .expect("should send head");
+    stream.write_all(body).await.expect("should send body");
+    let mut response = vec![0; 1024];
+    let read = stream.read(&mut response).await.expect("should respond");
+    String::from_utf8_lossy(&response[..read])
+        .lines()
+        .next()
+        .expect("status line")
+        .to_string()
+}
+
+#[cfg(feature = "webhook-server")]
+#[tokio::test]
+async fn test_webhook_server_acknowledges_deliveries() {
+    use hmac::{Hmac, Mac};
+    use ruleset_policy_bot::webhook::server::{MAX_BODY_SIZE, WebhookServer};
+    use sha2::Sha256;
+    use tokio::net::TcpListener;
+
+    let secret = "webhook-secret";
+    let body = br#"{"zen": "Keep it logically awesome."}"#;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("valid key");
+    mac.update(body);
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+    let address = listener.local_addr().expect("has address");
+    tokio::spawn(
+        WebhookServer {
+            bot: Arc::new(bot),
+            config: notifier_config(vec![]),
+            slack: Arc::new(slack_client),
+            github: Arc::new(FakeGitHub::new()),
+            asset_levels: AssetLevelCache::default(),
+            secret: secret.to_string(),
+            slack_signing_secret: None,
+        }
+        .serve(listener),
+    );
+
+    let head = |signature: &str, content_length: usize| {
+        format!(
+            "POST /github HTTP/1.1\r\nhost: localhost\r\nx-github-event: ping\r\n\
+             x-hub-signature-256: {signature}\r\ncontent-length: {content_length}\r\n\r\n"
+        )
+    };
+    assert_eq!(
+        status(address, head(&signature, body.len()), body).await,
+        "HTTP/1.1 202 Accepted"
+    );
+    assert_eq!(
+        status(address, head("sha256=00", body.len()), body).await,
+        "HTTP/1.1 401 Unauthorized"
+    );
+    assert_eq!(
+        status(address, head(&signature, MAX_BODY_SIZE + 1), body).await,
+        "HTTP/1.1 413 Payload Too Large"
+    );
+}
+
+#[cfg(feature = "webhook-server")]
+#[tokio::test]
+async fn test_webhook_server_serializes_deliveries() {
+    use hmac::{Hmac, Mac};
+    use ruleset_policy_bot::webhook::server::WebhookServer;
+    use sha2::Sha256;
+    use tokio::net::TcpListener;
+
+    let secret = "webhook-secret";
+    let push = |repository_name: &str| {
+        let body = format!(
+            r#"{{
+                "ref": "refs/heads/ci-tests",
+                "repository": {{
+                    "name": "{repository_name}",
+                    "full_name": "KittyCAD/{repository_name}",
+                    "owner": {{ "login": "KittyCAD" }}
+                }}
+            }}"#
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("valid key");
+        mac.update(body.as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        let head = format!(
+            "POST /github HTTP/1.1\r\nhost: localhost\r\nx-github-event: push\r\n\
+             x-hub-signature-256: {signature}\r\ncontent-length: {}\r\n\r\n",
+            body.len()
+        );
+        (head, body)
+    };
+
+    let rule_suite = RuleSuite {
+        pushed_at: chrono::Utc::now(),
+        ..ci_tests_rule_suite()
+    };
+    let github = FakeGitHub::new()
+        .with_rule_suite("KittyCAD/ruleset-policy-bot", rule_suite.clone())
+        .with_rule_suite(
+            "KittyCAD/other-repo",
+            RuleSuite {
+                id: 1923052993,
+                repository_id: 1108572606,
+                repository_name: "other-repo".to_string(),
+                ..rule_suite
+            },
+        )
+        .with_commit(serde_json::from_str(COMMIT).expect("valid commit"))
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        )
+        .with_custom_properties("KittyCAD", "other-repo", playground_custom_properties());
+    let bot = Arc::new(MockRulesetBot::default());
+    let slack_client = Arc::new(MockSlackClient::default());
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+    let address = listener.local_addr().expect("has address");
+    tokio::spawn(
+        WebhookServer {
+            bot: bot.clone(),
+            config: BotConfig {
+                callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+                ..notifier_config(vec![])
+            },
+            slack: slack_client.clone(),
+            github: Arc::new(github),
+            asset_levels: AssetLevelCache::default(),
+            secret: secret.to_string(),
+            slack_signing_secret: None,
+        }
+        .serve(listener),
+    );
+
+    // Two pushes to the same repository at once
+    let (head, body) = push("ruleset-policy-bot");
+    let (first, second) = tokio::join!(
+        status(address, head.clone(), body.as_bytes()),
+        status(address, head, body.as_bytes())
+    );
+    assert_eq!(first, "HTTP/1.1 202 Accepted");
+    assert_eq!(second, "HTTP/1.1 202 Accepted");
+
+    // Deliveries are processed in order, so both pushes are done once the other repository is.
+    let (head, body) = push("other-repo");
+    assert_eq!(
+        status(address, head, body.as_bytes()).await,
+        "HTTP/1.1 202 Accepted"
+    );
+    let messages = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            let messages = slack_client
+                .messages
+                .lock()
+                .as_ref()
+                .expect("should not be locked")
+                .borrow()
+                .clone();
+            if messages
+                .last()
+                .is_some_and(|(_, content)| format!("{content:?}").contains("other-repo"))
+            {
+                return messages;
+            }
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("should process the deliveries");
+
+    assert_eq!(messages.len(), 2); // One to the actor per repository
+    assert_eq!(
+        bot.events
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .len(),
+        2
+    );
+}
+
+#[cfg(feature = "webhook")]
+#[tokio::test]
+async fn test_slack_interactions_update_notification() {
+    use hmac::{Hmac, Mac};
+    use ruleset_policy_bot::soc2::action::{ActionKind, ViolationAction};
+    use ruleset_policy_bot::webhook::WebhookError;
+    use ruleset_policy_bot::webhook::interaction::{
+        ActionOutcome, InteractionDelivery, InteractionOutcome, apply_action, handle_interaction,
+    };
+    use sha2::Sha256;
+
+    let signing_secret = "slack-signing-secret";
+    let github = FakeGitHub::new()
+        .with_rule_suite(
+            "KittyCAD/ruleset-policy-bot",
+            RuleSuite {
+                pushed_at: chrono::Utc::now(),
+                ..ci_tests_rule_suite()
+            },
+        )
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+    // The security reviewers may mark false positives.
+    let config = BotConfig {
+        retroactive_review: None,
+        escalation_contacts: EscalationContacts {
+            contacts: vec![EscalationContact::UserGroup("S0614TZR7".to_string())],
+            ..Default::default()
+        },
+        ..retroactive_review_config(TimeDelta::days(7))
+    };
+
+    process_rule_suites_with_github(
+        &bot,
+        &config,
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        "KittyCAD/ruleset-policy-bot",
+        "ruleset-policy-bot",
+    )
+    .await
+    .unwrap();
+
+    let sign = |payload: &str| {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("payload", payload)
+            .finish();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes()).expect("valid key");
+        mac.update(format!("v0:{timestamp}:{body}").as_bytes());
+        let signature = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
+        (timestamp, signature, body)
+    };
+    // The notification of the actor is clicked in the direct message channel.
+    let click = |user: &str, action: &str| {
+        sign(&format!(
+            r#"{{"type":"block_actions","user":{{"id":"{user}"}},"channel":{{"id":"max.ammann@zoo.dev"}},"trigger_id":"12466734323.1395872398","actions":[{{"action_id":"{action}","value":"1923052992"}}]}}"#
+        ))
+    };
+    let submit = |user: &str| {
+        sign(&format!(
+            r#"{{"type":"view_submission","user":{{"id":"{user}"}},"view":{{"callback_id":"false_positive","private_metadata":"{{\"github_id\":\"1923052992\",\"channel\":\"max.ammann@zoo.dev\"}}","state":{{"values":{{"reason":{{"reason":{{"type":"plain_text_input","value":"Resetting the CI test branch"}}}}}}}}}}}}"#
+        ))
+    };
+    let (bot_ref, config_ref, slack_ref) = (&bot, &config, &slack_client);
+    let interact = |(timestamp, signature, body): (String, String, String)| async move {
+        let delivery = InteractionDelivery::from_headers(
+            [
+                ("X-Slack-Request-Timestamp", timestamp.as_str()),
+                ("X-Slack-Signature", signature.as_str()),
+            ],
+            body.as_bytes(),
+        )
+        .expect("should parse headers");
+        handle_interaction(slack_ref, signing_secret, delivery).await
+    };
+    // Applied after responding to Slack
+    let apply = |outcome: InteractionOutcome| async move {
+        let InteractionOutcome::Received(action) = outcome else {
+            panic!("should receive an action: {outcome:?}");
+        };
+        apply_action(bot_ref, config_ref, slack_ref, action)
+            .await
+            .expect("should apply action")
+    };
+
+    let (timestamp, _, body) = click("max.ammann@zoo.dev", "acknowledge");
+    let forged = interact((timestamp, "v0=00".to_string(), body)).await;
+    assert!(matches!(forged, Err(WebhookError::InvalidSignature)));
+
+    // The actor acknowledges, the notification is edited.
+    let outcome = interact(click("max.ammann@zoo.dev", "acknowledge"))
+        .await
+        .expect("should handle interaction");
+    assert!(matches!(
+        apply(outcome).await,
+        ActionOutcome::Recorded(ViolationAction::Acknowledged { .. })
+    ));
+    {
+        let updates = slack_client.updates.lock();
+        let updates = updates.as_ref().expect("should not be locked").borrow();
+        let (message, content) = updates.first().expect("should edit the notification");
+        assert_eq!(message.channel.0, "max.ammann@zoo.dev");
+        let blocks = format!("{:?}", content.blocks);
+        assert!(
+            blocks.contains("Acknowledged by <@max.ammann@zoo.dev>"),
+            "{blocks}"
+        );
+    }
+
+    // The modal asking for a reason opens right away. Only security may mark a false positive,
+    // others are told so privately.
+    for user in ["max.ammann@zoo.dev", "U0SEC0001"] {
+        let outcome = interact(click(user, "false_positive"))
+            .await
+            .expect("should handle interaction");
+        assert_eq!(
+            outcome,
+            InteractionOutcome::ModalOpened(ActionKind::FalsePositive)
+        );
+    }
+    let outcome = interact(submit("max.ammann@zoo.dev"))
+        .await
+        .expect("should handle interaction");
+    assert!(matches!(
+        apply(outcome).await,
+        ActionOutcome::Denied {
+            action: ActionKind::FalsePositive,
+            ..
+        }
+    ));
+    {
+        let ephemerals = slack_client.ephemerals.lock();
+        let ephemerals = ephemerals.as_ref().expect("should not be locked").borrow();
+        let (channel, user, content) = ephemerals.last().expect("should tell the user");
+        assert_eq!(
+            (channel.as_str(), user.as_str()),
+            ("max.ammann@zoo.dev", "max.ammann@zoo.dev")
+        );
+        let text = content.text.as_deref().expect("should have text");
+        assert!(text.contains("only the escalation contacts may"), "{text}");
+    }
+    let outcome = interact(submit("U0SEC0001"))
+        .await
+        .expect("should handle interaction");
+    assert!(matches!(
+        apply(outcome).await,
+        ActionOutcome::Recorded(ViolationAction::FalsePositive { .. })
+    ));
+
+    let actions: Vec<ViolationAction> = serde_json::from_str(
+        &bot.actions
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .last()
+            .expect("should store actions")
+            .1,
+    )
+    .expect("should deserialize");
+    assert_eq!(actions.len(), 2);
+    let updates = slack_client.updates.lock();
+    let updates = updates.as_ref().expect("should not be locked").borrow();
+    let (_, content) = updates.last().expect("should edit the notification");
+    let blocks = format!("{:?}", content.blocks);
+    assert!(
+        blocks.contains("Marked as false positive by <@U0SEC0001>")
+            && blocks.contains("Resetting the CI test branch"),
+        "{blocks}"
+    );
+    // The copies of the escalation contacts carry the buttons too, so they are edited as well.
+    let edited: Vec<_> = updates[updates.len() - 3..]
+        .iter()
+        .map(|(message, _)| message.channel.0.as_str())
+        .collect();
+    assert_eq!(edited, ["max.ammann@zoo.dev", "U0SEC0001", "U0SEC0002"]);
+    assert_eq!(
+        slack_client
+            .views
+            .lock()
+            .as_ref()
+            .expect("should not be locked")
+            .borrow()
+            .len(),
+        2
+    );
+}
+
+#[cfg(feature = "webhook")]
+#[tokio::test]
+async fn test_ruleset_deletion_webhook_alerts_soc2_channel() {
+    use hmac::{Hmac, Mac};
+    use ruleset_policy_bot::webhook::{WebhookDelivery, WebhookOutcome, handle_webhook};
+    use sha2::Sha256;
+
+    let secret = "webhook-secret";
+    let body = br#"{
+        "action": "deleted",
+        "repository_ruleset": { "id": 4047011, "name": "Block force pushes" },
+        "repository": {
+            "name": "ruleset-policy-bot",
+            "full_name": "KittyCAD/ruleset-policy-bot",
+            "owner": { "login": "KittyCAD" }
+        },
+        "sender": { "login": "maxammann" }
+    }"#;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("valid key");
+    mac.update(body);
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    let github = FakeGitHub::new().with_custom_properties(
+        "KittyCAD",
+        "ruleset-policy-bot",
+        playground_custom_properties(),
+    );
+    let bot = MockRulesetBot::default();
+    let slack_client = MockSlackClient::default();
+
+    let outcome = handle_webhook(
+        &bot,
+        &BotConfig {
+            github_org: "KittyCAD".to_string(),
+            github_web_base_url: "https://github.com/".to_string(),
+            slack_soc2_channel: "#soc2".to_string(),
+            review_requirement_ruleset_id: None,
+            block_force_push_ruleset_id: None,
+            codeowners_ruleset_id: None,
+            asset_taxonomy: AssetTaxonomy::default(),
+            in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+            callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+            policy: Policy::default(),
+            trusted_actors: vec![TrustedActor::dependabot()],
+            escalation_contacts: EscalationContacts::default(),
+            ref_filter: RefFilter::default(),
+            retroactive_review: None,
+            reminders: None,
+            notifiers: vec![],
+            rule_suite_filter: RuleSuiteFilter::default(),
+            github_auth: GitHubAuth::Token("unused".to_string()),
+        },
+        &slack_client,
+        &github,
+        &AssetLevelCache::default(),
+        secret,
+        WebhookDelivery::from_headers(
+            [
+                ("X-GitHub-Event", "repository_ruleset"),
+                ("X-Hub-Signature-256", &signature),
+            ],
+            body,
+        )
+        .expect("valid headers"),
+    )
+    .await
+    .expect("should handle webhook");
+
+    assert_eq!(
+        outcome,
+        WebhookOutcome::Repository {
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string()
+        }
+    );
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1);
+    let (channel, content) = &messages[0];
+    assert_eq!(channel, "#soc2");
+    assert_eq!(
+        content.text.as_deref(),
+        Some(
+            "Ruleset *Block force pushes* (4047011) of repository `KittyCAD/ruleset-policy-bot` \
+             was deleted by `maxammann`. Its rules are no longer enforced, later bypasses of it \
+             are reported as bypasses of a deleted ruleset."
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_deleted_ruleset_alert_is_scoped() {
+    let github = FakeGitHub::new()
+        .with_custom_properties(
+            "KittyCAD",
+            "ruleset-policy-bot",
+            playground_custom_properties(),
+        )
+        .with_custom_properties(
+            "KittyCAD",
+            "website",
+            vec![CustomProperty {
+                property_name: "repository-level".to_string(),
+                value: Some(CustomPropertyValue::String("Corporate".to_string())),
+            }],
+        );
+    let slack_client = MockSlackClient::default();
+    let config = BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Playground.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    };
+    let alert = |repository: Option<&str>| {
+        let ruleset = DeletedRuleset {
+            id: 4047011,
+            name: "Block force pushes".to_string(),
+            repository: repository.map(str::to_string),
+            deleted_by: None,
+        };
+        let (slack_client, config, github) = (&slack_client, &config, &github);
+        async move {
+            alert_deleted_ruleset(
+                slack_client,
+                config,
+                github,
+                &AssetLevelCache::default(),
+                &ruleset,
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    assert!(alert(Some("ruleset-policy-bot")).await);
+    // Out of scope, or without asset level.
+    assert!(!alert(Some("website")).await);
+    assert!(!alert(Some("unknown")).await);
+    // Organization rulesets may cover any repository.
+    assert!(alert(None).await);
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 2);
+}
+
+#[tokio::test]
+async fn test_misconfigured_asset_level_warns_soc2_channel() {
+    let bot = MockRulesetBot {
+        events: Mutex::new(RefCell::new(vec![NewGithubRuleSuiteEvent {
+            github_id: "1923052992".to_string(),
+            repository_full_name: "KittyCAD/ruleset-policy-bot".to_string(),
+            event_data: serde_json::to_string(&ci_tests_rule_suite()).expect("should serialize"),
+            resulting_commit: Some(COMMIT.to_string()),
+            prs: None,
+            ci_checks: None,
+            discarded_commits: None,
+            notified: false,
+        }])),
+        ..Default::default()
+    };
+    let slack_client = MockSlackClient::default();
+    let github = FakeGitHub::new().with_custom_properties(
+        "KittyCAD",
+        "ruleset-policy-bot",
+        vec![CustomProperty {
+            property_name: "repository-level".to_string(),
+            value: Some(CustomPropertyValue::String("Prodution".to_string())),
+        }],
+    );
+
+    let config = BotConfig {
+        github_org: "KittyCAD".to_string(),
+        github_web_base_url: "https://github.com/".to_string(),
+        slack_soc2_channel: "#soc2".to_string(),
+        review_requirement_ruleset_id: None,
+        block_force_push_ruleset_id: None,
+        codeowners_ruleset_id: None,
+        asset_taxonomy: AssetTaxonomy::default(),
+        in_scope_asset_level: AssetLevel::Playground.into()..=AssetLevel::Production.into(),
+        callout_asset_level: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        critical_asset_levels: AssetLevel::Production.into()..=AssetLevel::Production.into(),
+        policy: Policy::default(),
+        trusted_actors: vec![TrustedActor::dependabot()],
+        escalation_contacts: EscalationContacts::default(),
+        ref_filter: RefFilter::default(),
+        retroactive_review: None,
+        reminders: None,
+        notifiers: vec![],
+        rule_suite_filter: RuleSuiteFilter::default(),
+        github_auth: GitHubAuth::Token("unused".to_string()),
+    };
+
+    // The warning is not repeated on every run
+    for _ in 0..2 {
+        evaluate_rule_suites(
+            &bot,
+            &config,
+            &slack_client,
+            &github,
+            &AssetLevelCache::default(),
+            "KittyCAD/ruleset-policy-bot",
+            "ruleset-policy-bot",
+        )
+        .await
+        .unwrap();
+    }
+
+    let messages = slack_client.messages.lock();
+    let messages = messages.as_ref().expect("should not be locked").borrow();
+    assert_eq!(messages.len(), 1);
+    let (channel, content) = messages.first().expect("should have message");
+    assert_eq!(channel, "#soc2");
+    assert_eq!(
+        content.text.as_deref(),
+        Some(
+            "1 bypass(es) in `KittyCAD/ruleset-policy-bot` cannot be evaluated: \
+             unknown asset level `Prodution`. \
+             Please fix the `repository-level` custom property of the repository."
+        )
+    );
 }